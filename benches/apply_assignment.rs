@@ -0,0 +1,57 @@
+//! Bulk substitution of a received fork's 100-variable assignment against substituting the variables one at a time.
+//! Both start from the same table and end with the same terms, the ≥2× target is checked before Criterion runs.
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use rand::{rngs::StdRng, SeedableRng};
+use sat_swarm::structures::clause_table::{ClauseTable, TermState};
+use std::time::{Duration, Instant};
+
+const NUM_VARS: u8 = 100;
+
+/// Resets and assigns one variable at a time, each one scanning every clause for its terms the way a node's scan does
+fn one_variable_at_a_time(table: &mut ClauseTable, assignment: &[Option<bool>]) {
+    for (var, value) in assignment.iter().enumerate() {
+        let Some(value) = value else { continue };
+        for clause in table.clause_table.iter_mut() {
+            for (term, state) in clause.iter_mut().filter(|(term, _)| term.var as usize == var) {
+                *state = TermState::Symbolic;
+                *state = if *value != term.negated { TermState::True } else { TermState::False };
+            }
+        }
+    }
+}
+
+/// Resets the whole table and applies the assignment through the transpose, what a node does with a received fork
+fn bulk(table: &mut ClauseTable, assignment: &[Option<bool>]) {
+    table.reset_to_symbolic();
+    table.apply_assignment(assignment);
+}
+
+/// Fastest of a few runs of `f` on fresh copies of `table`
+fn fastest(table: &ClauseTable, assignment: &[Option<bool>], f: fn(&mut ClauseTable, &[Option<bool>])) -> Duration {
+    (0..20).map(|_| {
+        let mut table = table.clone();
+        let start = Instant::now();
+        f(&mut table, black_box(assignment));
+        start.elapsed()
+    }).min().unwrap()
+}
+
+fn apply_assignment(c: &mut Criterion) {
+    let assignment: Vec<Option<bool>> = (0..=NUM_VARS as usize).map(|var| (var > 0).then_some(var % 3 == 0)).collect();
+    let table: ClauseTable = ClauseTable::random_with_rng(430, NUM_VARS, &mut StdRng::seed_from_u64(0));
+
+    let (mut sequential, mut bulk_table) = (table.clone(), table.clone());
+    one_variable_at_a_time(&mut sequential, &assignment);
+    bulk(&mut bulk_table, &assignment);
+    assert!(sequential.clause_table == bulk_table.clause_table, "both substitutions have to end with the same terms");
+    let (sequential, bulk_time) = (fastest(&table, &assignment, one_variable_at_a_time), fastest(&table, &assignment, bulk));
+    assert!(bulk_time * 2 <= sequential, "apply_assignment took {:?}, not 2x faster than {:?} one variable at a time", bulk_time, sequential);
+
+    let mut group = c.benchmark_group("100 variables");
+    group.bench_function("reset_to_symbolic + apply_assignment", |b| b.iter_batched_ref(|| table.clone(), |table| bulk(table, black_box(&assignment)), BatchSize::SmallInput));
+    group.bench_function("one variable at a time", |b| b.iter_batched_ref(|| table.clone(), |table| one_variable_at_a_time(table, black_box(&assignment)), BatchSize::SmallInput));
+    group.finish();
+}
+
+criterion_group!(benches, apply_assignment);
+criterion_main!(benches);
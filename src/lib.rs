@@ -0,0 +1,1087 @@
+#![allow(unused)]
+use std::hash::{Hash, Hasher};
+use std::io::IsTerminal;
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex};
+use std::time::Duration;
+
+use csv::Writer;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::fs::OpenOptions;
+use structures::logging::{sim_log, LogLevel};
+use structures::message::max_link_delay;
+use structures::minisat::{minisat_table_with_timeout, solve_external};
+use structures::{clause_table::{ClauseIdx, ClauseTable, ParseError, ProblemDescription}, node::ClauseLayout, satswarm::{read_edge_list, SatSwarm, SimulationError}, util_types::VarId};
+pub use structures::util_types::{EnergyModel, ForkPolicy, SearchMode, VerifyLevel};
+
+pub mod structures;
+pub mod testing;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Topology {
+    Grid(usize, usize),
+    Torus(usize, usize),
+    Dense(usize),
+    Ring(usize),
+    Butterfly(u32),               // stages, 2^stages nodes
+    Custom(std::path::PathBuf),   // edge list file, see satswarm::read_edge_list
+}
+impl Topology {
+    /// Nodes in the network, None for an edge list (the file has to be read) or a butterfly too big to build
+    pub fn node_count(&self) -> Option<usize> {
+        match self {
+            Topology::Grid(rows, cols) | Topology::Torus(rows, cols) => Some(rows * cols),
+            Topology::Dense(nodes) | Topology::Ring(nodes) => Some(*nodes),
+            Topology::Butterfly(stages) => 1usize.checked_shl(*stages),
+            Topology::Custom(_) => None,
+        }
+    }
+
+    /// The network of the same family with `num_nodes` nodes, grids and tori as square as the count allows.
+    /// None for an edge list, or a butterfly when `num_nodes` is not a power of two
+    pub fn with_nodes(&self, num_nodes: usize) -> Option<Topology> {
+        let rows = (1..=num_nodes).take_while(|rows| rows * rows <= num_nodes).filter(|&rows| num_nodes.is_multiple_of(rows)).last()?;
+        match self {
+            Topology::Grid(..) => Some(Topology::Grid(rows, num_nodes / rows)),
+            Topology::Torus(..) => Some(Topology::Torus(rows, num_nodes / rows)),
+            Topology::Dense(_) => Some(Topology::Dense(num_nodes)),
+            Topology::Ring(_) => Some(Topology::Ring(num_nodes)),
+            Topology::Butterfly(_) => num_nodes.is_power_of_two().then(|| Topology::Butterfly(num_nodes.trailing_zeros())),
+            Topology::Custom(_) => None,
+        }
+    }
+}
+/// Writes the `--topology` argument that builds this network, with every dimension spelled out (e.g. `torus:8x8`)
+impl std::fmt::Display for Topology {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Topology::Grid(rows, cols) => write!(f, "grid:{}x{}", rows, cols),
+            Topology::Torus(rows, cols) => write!(f, "torus:{}x{}", rows, cols),
+            Topology::Dense(nodes) => write!(f, "dense:{}", nodes),
+            Topology::Ring(nodes) => write!(f, "ring:{}", nodes),
+            Topology::Butterfly(stages) => write!(f, "butterfly:{}", stages),
+            Topology::Custom(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+/// Reads back what `Display` writes: `grid:<ROWS>x<COLS>`, `torus:<ROWS>x<COLS>`, `dense:<NODES>`, `ring:<NODES>`,
+/// `butterfly:<STAGES>` or the path of an edge list file
+impl std::str::FromStr for Topology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |value: &str| value.parse::<usize>().map_err(|_| format!("invalid size {}", value));
+        let dimensions = |value: &str| match value.split_once('x') {
+            Some((rows, cols)) => Ok((number(rows)?, number(cols)?)),
+            None => Err(format!("expected <ROWS>x<COLS>, got {}", value)),
+        };
+        match s.split_once(':') {
+            Some(("grid", value)) => dimensions(value).map(|(rows, cols)| Topology::Grid(rows, cols)),
+            Some(("torus", value)) => dimensions(value).map(|(rows, cols)| Topology::Torus(rows, cols)),
+            Some(("dense", value)) => number(value).map(Topology::Dense),
+            Some(("ring", value)) => number(value).map(Topology::Ring),
+            Some(("butterfly", value)) => value.parse::<u32>().map(Topology::Butterfly).map_err(|_| format!("invalid butterfly stages {}", value)),
+            _ if std::path::Path::new(s).is_file() => Ok(Topology::Custom(std::path::PathBuf::from(s))),
+            _ => Err(String::from("not a topology name or an edge list file")),
+        }
+    }
+}
+
+
+pub struct TestResult {
+    pub simulated_result: bool,
+    pub simulated_cycles: u64,
+    pub cycles_busy: u64,
+    pub cycles_idle: u64,
+    pub fork_attempts: u64,
+    pub fork_successes: u64,
+    pub wakeups: u64,                       // forks that woke an idle node up, each costs the wakeup penalty
+    pub forks_total: u64,                   // forks sent, including those still in flight when the search ended
+    pub forks_productive: u64,              // forks whose receiver explored at least one branch before going idle
+    pub avg_fork_delay: f64,                // mean link latency the forks paid, below the configured one with an adaptive delay
+    pub model: Option<Vec<Option<bool>>>,   // verified satisfying assignment indexed by DIMACS variable ([0] is always None), None if UNSAT or unverified
+    pub conflict_histogram: Vec<u64>,       // conflicts at each decision level over all nodes (last bucket = that level or deeper)
+    pub cycles_unit_prop: u64,              // cycles_busy split by what the node was doing
+    pub cycles_decision: u64,
+    pub cycles_conflict: u64,
+    pub clause_evaluations: u64,            // clauses checked over all nodes, compare eager and lazy evaluation with it
+    pub models_found: u64,                  // distinct satisfying assignments found (at most 1 unless counting)
+    pub search_leaves: u64,                 // conflicts plus models, the leaves of the search tree explored
+    pub timed_out: bool,                    // the search hit the cycle limit, so an UNSAT result is only "unknown"
+    pub clause_accesses: Option<Vec<u64>>,  // checks of each clause over all nodes, if tracked (sums to clause_evaluations)
+    pub total_energy_pj: f64,               // energy of every node including idle cycles, 0 without an energy model
+    pub clause_activity: Vec<u64>,          // conflicts plus unit propagations found in each clause over all nodes
+} impl TestResult {
+    /// Fraction of branching decisions made while some node was idle that reached an idle neighbor
+    pub fn fork_success_rate(&self) -> f64 {
+        if self.fork_attempts == 0 { 0.0 } else { self.fork_successes as f64 / self.fork_attempts as f64 }
+    }
+    /// Fraction of the forks sent that explored at least one branch, the rest only moved work around
+    pub fn fork_efficiency(&self) -> f64 {
+        if self.forks_total == 0 { 0.0 } else { self.forks_productive as f64 / self.forks_total as f64 }
+    }
+    /// How many times faster than a run that took `sequential_cycles` (usually a single node)
+    pub fn speedup(&self, sequential_cycles: u64) -> f64 {
+        sequential_cycles as f64 / self.simulated_cycles as f64
+    }
+    /// Speedup per node, 1.0 is perfect scaling
+    pub fn efficiency(&self, sequential_cycles: u64, num_nodes: usize) -> f64 {
+        self.speedup(sequential_cycles) / num_nodes as f64
+    }
+    /// Fewest, most and mean checks of a clause, if they were tracked
+    pub fn clause_access_stats(&self) -> Option<(u64, u64, f64)> {
+        let accesses = self.clause_accesses.as_ref().filter(|accesses| !accesses.is_empty())?;
+        let mean = accesses.iter().sum::<u64>() as f64 / accesses.len() as f64;
+        Some((*accesses.iter().min()?, *accesses.iter().max()?, mean))
+    }
+    /// The `k` clauses that were falsified or became unit most often, most active first (ties go to the lower index).
+    /// They are the candidates for strengthening, e.g. by vivification, before the next run.
+    pub fn top_k_active_clauses(&self, k: usize) -> Vec<ClauseIdx> {
+        let mut clauses: Vec<ClauseIdx> = (0..self.clause_activity.len()).filter(|&clause| self.clause_activity[clause] > 0).collect();
+        clauses.sort_by_key(|&clause| std::cmp::Reverse(self.clause_activity[clause]));  // stable, so ties stay in index order
+        clauses.truncate(k);
+        clauses
+    }
+    /// Models found per joule, 0 without an energy model
+    pub fn energy_efficiency(&self) -> f64 {
+        if self.total_energy_pj == 0.0 { 0.0 } else { self.models_found as f64 / (self.total_energy_pj * 1e-12) }
+    }
+    /// Fraction of node cycles spent busy
+    pub fn utilization(&self) -> f64 {
+        let total = self.cycles_busy + self.cycles_idle;
+        if total == 0 { 0.0 } else { self.cycles_busy as f64 / total as f64 }
+    }
+}
+pub struct TestLog {
+    pub test_result: TestResult,
+    pub config: TestConfig,
+    pub expected_result: Option<bool>,    // None if the solver timed out
+    pub minisat_speed: Duration,          // the timeout itself if the solver timed out
+    pub test_path: String,
+    pub repeats: RepeatStats,
+    pub description: ProblemDescription,
+    pub sequential_cycles: Option<u64>,   // cycles of the same test on a single node, if it was simulated
+    pub outlier: bool,                    // cycles per MiniSat ns far above the median of the batch, see flag_outliers
+} impl TestLog {
+    /// Simulated cycles per nanosecond MiniSat needed, to compare instances of different difficulty
+    pub fn minisat_ratio(&self) -> f64 {
+        self.test_result.simulated_cycles as f64 / self.minisat_speed.as_nanos().max(1) as f64  // trivially UNSAT tables take 0 ns
+    }
+}
+/// Marks the logs whose `minisat_ratio` exceeds `multiple` times the (lower) median of the batch.
+/// Tests the solver timed out on have no real MiniSat time, so they are neither counted nor flagged.
+pub fn flag_outliers(logs: &mut [TestLog], multiple: f64) {
+    let mut ratios: Vec<f64> = logs.iter().filter(|log| log.expected_result.is_some()).map(|log| log.minisat_ratio()).collect();
+    ratios.sort_by(|a, b| a.total_cmp(b));
+    let Some(&median) = ratios.get(ratios.len().saturating_sub(1) / 2) else {
+        return;
+    };
+    for log in logs.iter_mut() {
+        log.outlier = log.expected_result.is_some() && log.minisat_ratio() > multiple * median;
+    }
+}
+/// Spread of the simulated cycles over the repeated runs of a test
+pub struct RepeatStats {
+    pub runs: usize,
+    pub mean_cycles: f64,
+    pub stddev_cycles: f64,   // population standard deviation, 0 for a single run
+} impl RepeatStats {
+    pub fn from_cycles(cycles: &[u64]) -> Self {
+        let runs = cycles.len();
+        let mean_cycles = cycles.iter().map(|&c| c as f64).sum::<f64>() / runs.max(1) as f64;
+        let variance = cycles.iter().map(|&c| (c as f64 - mean_cycles).powi(2)).sum::<f64>() / runs.max(1) as f64;
+        RepeatStats { runs, mean_cycles, stddev_cycles: variance.sqrt() }
+    }
+}
+#[derive(Debug, Clone)]
+pub struct TestConfig {
+    pub num_nodes: usize,
+    pub topology: Topology,
+    pub node_bandwidth: usize,
+    pub num_vars: usize,
+    pub test_dir: String,
+    pub wrap_delay: usize,
+    pub decision_delay: u64,
+    pub cycles_per_eval: u64,                          // latency of evaluating node_bandwidth clauses, see Node::set_cycles_per_eval
+    pub pipeline_depth: usize,                         // assignments a node can scan at once, see Node::set_pipeline_depth
+    pub clause_layout: ClauseLayout,                   // order the clause scans read the clauses in, see Node::set_clause_layout
+    pub wakeup_penalty: u64,                           // cycles an idle node takes to wake up for a fork, billed as idle
+    pub start_nodes: usize,
+    pub checkpoint_interval: Option<u64>,
+    pub fork_bandwidth_bytes_per_cycle: usize,
+    pub adaptive_fork_delay: bool,                     // scale link latencies down while nodes are idle, see message::compute_adaptive_delay
+    pub fork_policy: ForkPolicy,
+    pub fork_fanout: usize,                            // idle neighbors a decision can fork to at once, see Node::set_fork_fanout
+    pub variable_partition: Option<Vec<Vec<VarId>>>,  // variables each node branches on first, see SatSwarm::assign_variables_to_nodes
+    pub search_mode: SearchMode,
+    pub lazy_evaluation: bool,                         // defer clause scans so chained assignments share one, see Node::set_lazy_mode
+    pub seed: u64,                                     // seed of the randomized policies, run r of a repeated test uses seed + r
+    pub simulation_timeout_cycles: Option<u64>,        // give up after this many cycles, None = no limit
+    pub enable_unit_prop: bool,                        // false = only decisions assign variables, see Node::set_unit_prop
+    pub track_clause_accesses: bool,                   // count the checks of every clause, see Node::set_track_clause_accesses
+    pub random_polarity: bool,                         // draw each node's first speculative value from the seed instead of false
+    pub verify_level: VerifyLevel,                     // how often nodes check their invariants, see Node::set_verify_level
+    pub energy_model: Option<EnergyModel>,             // cost of each node operation, None = don't estimate energy
+}
+
+impl TestConfig {
+    /// Checks the parameters that would otherwise only fail deep inside a simulation, returning every problem found
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.num_nodes < 1 {
+            errors.push(String::from("num_nodes must be >= 1"));
+        }
+        if self.node_bandwidth < 1 {
+            errors.push(String::from("node_bandwidth must be >= 1"));
+        }
+        if self.num_vars < 1 {
+            errors.push(String::from("num_vars must be >= 1"));
+        }
+        if self.fork_bandwidth_bytes_per_cycle < 1 {
+            errors.push(String::from("fork_bandwidth must be >= 1"));
+        }
+        if self.cycles_per_eval < 1 {
+            errors.push(String::from("cycles_per_eval must be >= 1"));
+        }
+        if self.pipeline_depth < 1 {
+            errors.push(String::from("pipeline_depth must be >= 1"));
+        }
+        if self.clause_layout == (ClauseLayout::Interleaved { banks: 0 }) {
+            errors.push(String::from("clause_layout needs at least one bank"));
+        }
+        if self.fork_fanout < 1 {
+            errors.push(String::from("fork_fanout must be >= 1"));
+        }
+        // the largest fork carries every variable (see Node::partner_branch) and has to fit in the message queue behind the wrap latency
+        let largest_fork = 2 * (VarId::MAX as usize + self.fork_fanout.saturating_sub(1));
+        let longest_wrap = max_link_delay(self.fork_bandwidth_bytes_per_cycle.max(1), largest_fork);
+        if self.wrap_delay < 1 || self.wrap_delay > longest_wrap {
+            errors.push(format!("wrap_delay must be between 1 and {} at a fork bandwidth of {}", longest_wrap, self.fork_bandwidth_bytes_per_cycle));
+        }
+        if self.checkpoint_interval == Some(0) {
+            errors.push(String::from("checkpoint_interval must be >= 1"));
+        }
+        if !self.start_nodes.is_power_of_two() || self.start_nodes > self.num_nodes.max(1) {
+            errors.push(format!("start_nodes must be a power of two no larger than num_nodes, got {}", self.start_nodes));
+        }
+        let topology_nodes = match &self.topology {
+            Topology::Custom(path) => match read_edge_list(path) {
+                Ok((nodes, _)) => Some(nodes),
+                Err(e) => {
+                    errors.push(format!("invalid edge list {:?}: {}", path, e));
+                    None
+                }
+            },
+            topology => topology.node_count(),
+        };
+        match topology_nodes {
+            Some(nodes) if nodes != self.num_nodes => errors.push(format!("{} has {} nodes but num_nodes is {}", self.topology, nodes, self.num_nodes)),
+            None if !matches!(self.topology, Topology::Custom(_)) => errors.push(format!("{} has too many nodes", self.topology)),
+            _ => {}
+        }
+        if !std::path::Path::new(&self.test_dir).exists() {
+            errors.push(format!("test path {} does not exist", self.test_dir));
+        } else if get_test_files(&self.test_dir).is_none_or(|files| files.is_empty()) {
+            errors.push(format!("test path {} contains no .cnf or .wcnf files", self.test_dir));
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+
+/// Options that change how a workload is run but not what is simulated
+pub struct RunOptions {
+    pub progress: bool,
+    pub external_solver: Option<String>,   // command of a DIMACS solver to get the expected results from instead of MiniSat
+    pub oracle_timeout: Duration,          // time the solver gets per test before its expected result is left unknown
+    pub repeat: usize,                     // simulations per test file, each with its own seed
+    pub jobs: usize,                       // test files simulated at once, see run_workload_parallel
+    pub baseline: bool,                    // simulate every file on a single node too, for speedup and efficiency
+    pub pending_logs: Arc<PendingLogs>,    // logs of the finished tests, written once the workload is done
+    pub interrupted: Arc<AtomicBool>,      // set by Ctrl-C: stop the current simulation and log what has run so far
+}
+
+/// Logs of the finished tests of a workload, held back until the median ratio of the batch is known (see
+/// `flag_outliers`). Shared with the Ctrl-C handler, so quitting with a second Ctrl-C still writes them.
+pub struct PendingLogs {
+    logs: Mutex<Vec<(usize, TestLog)>>,   // with the index of the test file, parallel runs finish out of order
+    outlier_multiple: f64,                // flag tests whose cycles per MiniSat ns exceed this multiple of the median
+}
+impl PendingLogs {
+    pub fn new(outlier_multiple: f64) -> Self {
+        PendingLogs { logs: Mutex::new(Vec::new()), outlier_multiple }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(usize, TestLog)>> {
+        self.logs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())  // a failed test must not lose the others
+    }
+
+    fn push(&self, file_index: usize, test_log: TestLog) {
+        self.lock().push((file_index, test_log));
+    }
+
+    /// Flags the outliers and writes the logs in file order, leaving nothing pending. The lock is held while
+    /// writing so a second Ctrl-C waits for a write that is under way instead of cutting it short.
+    pub fn write(&self) {
+        let mut pending = self.lock();
+        pending.sort_by_key(|(file_index, _)| *file_index);
+        let mut logs: Vec<TestLog> = pending.drain(..).map(|(_, test_log)| test_log).collect();
+        flag_outliers(&mut logs, self.outlier_multiple);
+        for test_log in logs {
+            if test_log.outlier {
+                println!("Outlier: {} ({:.3} cycles per MiniSat ns)", test_log.test_path, test_log.minisat_ratio());
+            }
+            log_test(test_log);
+        }
+    }
+}
+/// Writes the pending logs when dropped, so a test that panics still leaves the finished ones in the log
+struct WriteOnDrop<'a>(&'a PendingLogs);
+impl Drop for WriteOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.write();
+    }
+}
+
+pub const DEFAULT_ORACLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Progress display for `run_workload`: one bar over the files and one over the current simulation
+struct WorkloadProgress {
+    _bars: MultiProgress,
+    files: ProgressBar,
+    simulation: ProgressBar,
+    finished_cycles: u64,   // cycles of all finished simulations, used to estimate the next one
+    finished_solver_time: Duration,    // MiniSat time of the files of those simulations
+    solver_time: Duration,  // MiniSat time of the current file, the quick sequential solve the estimate is scaled from
+    passed: usize,
+    failed: usize,
+    timed_out: usize,
+    unchecked: usize,       // the solver gave no expected result to check against
+}
+impl WorkloadProgress {
+    fn new(num_files: usize) -> Self {
+        let bars = MultiProgress::new();
+        let files = bars.add(ProgressBar::new(num_files as u64));
+        files.set_style(ProgressStyle::with_template("[{elapsed_precise}] {bar:40} {pos}/{len} files (eta {eta}) {msg}").unwrap());
+        let simulation = bars.add(ProgressBar::no_length());
+        WorkloadProgress { _bars: bars, files, simulation, finished_cycles: 0, finished_solver_time: Duration::ZERO, solver_time: Duration::ZERO, passed: 0, failed: 0, timed_out: 0, unchecked: 0 }
+    }
+
+    /// Sets the MiniSat time of the file about to be simulated
+    fn set_solver_time(&mut self, solver_time: Duration) {
+        self.solver_time = solver_time;
+    }
+
+    /// Cycles the next simulation should take: its MiniSat time scaled by the cycles per MiniSat ns of the finished
+    /// simulations. None before the first one has finished, or if MiniSat decided all of them without searching
+    fn estimate(&self) -> Option<u64> {
+        let finished_ns = self.finished_solver_time.as_nanos();
+        (finished_ns > 0).then(|| (self.solver_time.as_nanos() * self.finished_cycles as u128 / finished_ns) as u64)
+    }
+
+    /// Resets the simulation bar for a new file, sized by `estimate`. Without one the bar only counts the cycles
+    fn start_simulation(&self, file: &std::path::Path) -> ProgressBar {
+        self.simulation.reset();
+        match self.estimate() {
+            Some(cycles) => {
+                self.simulation.set_style(ProgressStyle::with_template("[{elapsed_precise}] {bar:40} {pos}/~{len} cycles {msg}").unwrap());
+                self.simulation.set_length(cycles);
+            }
+            None => {
+                self.simulation.set_style(ProgressStyle::with_template("[{elapsed_precise}] {spinner} {pos} cycles, no estimate yet {msg}").unwrap());
+                self.simulation.unset_length();
+            }
+        }
+        self.simulation.set_message(file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default());
+        self.simulation.clone()
+    }
+
+    fn finish_simulation(&mut self, result: &TestResult, expected_result: Option<bool>) {
+        self.finished_cycles += result.simulated_cycles;
+        self.finished_solver_time += self.solver_time;
+        match expected_result {
+            _ if result.timed_out => self.timed_out += 1,
+            None => self.unchecked += 1,
+            Some(expected) if result.simulated_result == expected => self.passed += 1,
+            Some(_) => self.failed += 1,
+        }
+        self.files.set_message(format!("{} passed, {} failed, {} timed out, {} unchecked", self.passed, self.failed, self.timed_out, self.unchecked));
+    }
+}
+
+fn get_test_files(test_path: &str) -> Option<Vec<std::path::PathBuf>> {
+    let mut files = Vec::new();
+    fn collect_files(dir: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries {
+                if let Ok(entry) = entry {
+                    let path = entry.path();
+                    if path.is_file() && path.extension().is_some_and(|ext| ext == "cnf" || ext == "wcnf") {
+                        // println!("Found test file: {:?}", path);
+                        files.push(path);  // skips anything else kept next to the instances, like tests/cli.rs
+                    } else if path.is_dir() {
+                        collect_files(&path, files);
+                    }
+                }
+            }
+        }
+    }
+
+    collect_files(std::path::Path::new(test_path), &mut files);
+    Some(files)
+}
+pub fn run_workload(test_path: String, config: TestConfig, options: RunOptions) {
+    if let Err(errors) = config.validate() {
+        for error in errors {
+            eprintln!("Invalid configuration: {}", error);
+        }
+        std::process::exit(1);
+    }
+    // load test files from the specified path
+    if let Some(files) = get_test_files(&test_path) {
+        let _write = WriteOnDrop(&options.pending_logs);  // also when a test panics
+        run_test_files(files, &config, &options);
+    } else {
+        println!("No tests directory found at: {}", test_path);
+    }
+}
+/// Runs the test files into `options.pending_logs`. Once interrupted no new file is started, the one being
+/// simulated stops at its next interrupt check and is still logged.
+fn run_test_files(files: Vec<std::path::PathBuf>, config: &TestConfig, options: &RunOptions) {
+    if options.jobs > 1 {
+        run_workload_parallel(files, config, options);
+        return;
+    }
+    let mut progress = options.progress.then(|| WorkloadProgress::new(files.len()));
+    for (file_index, file) in files.into_iter().enumerate() {
+        if options.interrupted.load(Ordering::Relaxed) {
+            break;
+        }
+        if let Some(test_log) = run_test_file(file, config, options, &mut progress) {
+            options.pending_logs.push(file_index, test_log);
+        }
+    }
+}
+/// Simulates `options.jobs` test files at once, each with its own swarm. Every log is pending as soon as its file
+/// finishes and the logs are written in file order like the sequential run, so no writer is shared between the threads.
+fn run_workload_parallel(files: Vec<std::path::PathBuf>, config: &TestConfig, options: &RunOptions) {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(options.jobs).build().expect("Failed to start the test threads");
+    pool.install(|| {
+        files.into_par_iter().enumerate().for_each(|(file_index, file)| {
+            if options.interrupted.load(Ordering::Relaxed) {
+                return;  // files that have not started are dropped, the finished ones are still logged
+            }
+            if let Some(test_log) = run_test_file(file, config, options, &mut None) {
+                options.pending_logs.push(file_index, test_log);
+            }
+        })
+    })
+}
+/// Result and solve time of the table from `options.external_solver`, or MiniSat if there is none or it failed.
+/// None (with the timeout as the time) if the solver ran out of `options.oracle_timeout`.
+fn expected_result(clause_table: &ClauseTable, options: &RunOptions) -> (Option<bool>, Duration) {
+    match &options.external_solver {
+        Some(cmd) => match solve_external(clause_table, cmd, options.oracle_timeout) {
+            Ok((result, elapsed)) => (Some(result), elapsed),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => (None, options.oracle_timeout),
+            Err(e) => {
+                eprintln!("External solver failed ({}), falling back to MiniSat", e);
+                minisat_table_with_timeout(clause_table, options.oracle_timeout)
+            }
+        },
+        None => minisat_table_with_timeout(clause_table, options.oracle_timeout),
+    }
+}
+/// Loads a test file, gets its expected result and simulates it `options.repeat` times.
+/// None if the file can't be loaded or does not have `config.num_vars` variables.
+fn run_test_file(file: std::path::PathBuf, config: &TestConfig, options: &RunOptions, progress: &mut Option<WorkloadProgress>) -> Option<TestLog> {
+    let f_copy = file.clone();
+    let loaded = load_test_file(&file);
+    if let Some(progress) = &progress {
+        progress.files.inc(1);
+    }
+    let mut clause_table = match loaded {
+        Ok(table) => table,
+        Err(e) => {
+            eprintln!("Skipping {:?}: {}", f_copy, e);
+            return None;
+        }
+    };
+    // skip if the clause table > 25 or expected result is unsat
+    if clause_table.number_of_vars() != config.num_vars {
+        return None;
+    }
+    println!("Running test: {:?}", f_copy);
+    let description = ProblemDescription::from_table(&clause_table);
+    let (expected_result, minisat_speed) = expected_result(&clause_table, options);
+    if expected_result.is_none() {
+        println!("No expected result, the solver timed out after {:?}", options.oracle_timeout);
+    }
+    if let Some(progress) = progress {
+        progress.set_solver_time(minisat_speed);
+    }
+    // every run is logged through the first one, the others only add to the cycle statistics
+    let mut first_result = None;
+    let mut cycles = Vec::with_capacity(options.repeat);
+    for run in 0..options.repeat {
+        let run_config = TestConfig { seed: config.seed + run as u64, ..config.clone() };
+        let result = match simulate(clause_table.clone(), &run_config, &f_copy, progress, &options.interrupted) {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("Skipping {:?}: {}", f_copy, e);
+                return None;
+            }
+        };
+        if let Some(progress) = progress {
+            progress.finish_simulation(&result, expected_result);
+        }
+        assert!(result.timed_out || expected_result.is_none_or(|expected| result.simulated_result == expected), "Test failed: expected {:?}, got {}", expected_result, result.simulated_result);
+        cycles.push(result.simulated_cycles);
+        first_result.get_or_insert(result);
+        if options.interrupted.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+    let result = first_result.expect("--repeat is at least 1");
+    println!("Utilization: {:.3}", result.utilization());
+    let sequential_cycles = (options.baseline && !options.interrupted.load(Ordering::Relaxed)).then(|| {
+        let sequential = TestConfig { num_nodes: 1, topology: Topology::Dense(1), start_nodes: 1, checkpoint_interval: None, variable_partition: None, ..config.clone() };
+        simulate(clause_table.clone(), &sequential, &f_copy, &mut None, &options.interrupted).map(|result| result.simulated_cycles)
+    }).transpose().expect("a single dense node can always search");
+    Some(TestLog {
+        test_result: result,
+        config: config.clone(),
+        expected_result,
+        minisat_speed,
+        test_path: f_copy.to_str().unwrap_or("unknown").to_string(),
+        repeats: RepeatStats::from_cycles(&cycles),
+        description,
+        sequential_cycles,
+        outlier: false,
+    })
+}
+/// Why `run_single` or one of the analyses in `testing` could not simulate a file
+#[derive(Debug)]
+pub enum RunError {
+    Parse(ParseError),
+    Simulation(SimulationError),
+    Resize { topology: Topology, num_nodes: usize },   // the topology family has no network of that size
+    Config(Vec<String>),                                 // what TestConfig::validate found wrong
+} impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::Parse(e) => write!(f, "{}", e),
+            RunError::Simulation(e) => write!(f, "{}", e),
+            RunError::Resize { topology, num_nodes } => write!(f, "can't build {} with {} nodes", topology, num_nodes),
+            RunError::Config(errors) => write!(f, "invalid configuration: {}", errors.join(", ")),
+        }
+    }
+} impl std::error::Error for RunError {}
+impl From<ParseError> for RunError {
+    fn from(e: ParseError) -> Self {
+        RunError::Parse(e)
+    }
+}
+impl From<SimulationError> for RunError {
+    fn from(e: SimulationError) -> Self {
+        RunError::Simulation(e)
+    }
+}
+/// Loads a CNF file, or the hard clauses of a `.wcnf` file
+fn load_test_file(file: &std::path::Path) -> Result<ClauseTable, ParseError> {
+    if file.extension().map_or(false, |ext| ext == "wcnf") {
+        ClauseTable::load_wcnf(file.to_path_buf())  // only the hard clauses are simulated
+    } else {
+        ClauseTable::load_file(file.to_path_buf()).map(|(table, _)| table)
+    }
+}
+/// Simulates one file with `config` and returns the result, for embedding the simulator. Unlike `run_workload` it
+/// doesn't check the result against MiniSat, filter on `config.num_vars` or write a log; checkpoints are still
+/// used if `config.checkpoint_interval` is set. The config is validated first, and the only output is the `sim_log!`
+/// lines at or above the level of `logging::set_log_level`.
+pub fn run_single(file: &std::path::Path, config: &TestConfig) -> Result<TestResult, RunError> {
+    config.validate().map_err(RunError::Config)?;
+    let clause_table = load_test_file(file)?;
+    Ok(simulate(clause_table, config, file, &mut None, &Arc::new(AtomicBool::new(false)))?)
+}
+/// Runs one simulation of a test file, resuming from and saving checkpoints if they are enabled
+fn simulate(clause_table: ClauseTable, config: &TestConfig, file: &std::path::Path, progress: &mut Option<WorkloadProgress>, interrupted: &Arc<AtomicBool>) -> Result<TestResult, SimulationError> {
+    let checkpoint_path = checkpoint_path(config, file);
+    let mut simulation = match &checkpoint_path {
+        Some(path) if path.exists() => match SatSwarm::load_checkpoint(path, clause_table.clone()) {
+            Ok(mut simulation) => {
+                sim_log!("-", 0, LogLevel::Info, "Resuming from checkpoint: {:?}", path);
+                simulation.set_timeout(config.simulation_timeout_cycles);
+                simulation
+            }
+            Err(e) => {  // e.g. written by an older build, starting over beats losing the whole workload
+                sim_log!("-", 0, LogLevel::Warn, "Discarding checkpoint {:?} ({}), starting over", path, e);
+                let _ = std::fs::remove_file(path);
+                SatSwarm::generate(clause_table, config)?
+            }
+        },
+        _ => SatSwarm::generate(clause_table, config)?,
+    };
+    if let (Some(interval), Some(path)) = (config.checkpoint_interval, &checkpoint_path) {
+        simulation.set_checkpointing(interval, path.clone());
+    }
+    if let Some(progress) = progress {
+        simulation.set_progress(progress.start_simulation(file));
+    }
+    simulation.set_interrupt_flag(interrupted.clone());
+    let result = simulation.test_satisfiability()?;
+    if let Some(path) = &checkpoint_path {
+        if interrupted.load(Ordering::Relaxed) {
+            if let Err(e) = simulation.save_checkpoint(path) {  // so the next run picks up where this one stopped
+                eprintln!("Failed to save checkpoint {:?}: {}", path, e);
+            }
+        } else {
+            let _ = std::fs::remove_file(path);  // the run finished so there is nothing to resume
+        }
+    }
+    Ok(result)
+}
+/// Where the checkpoints of a test file are kept, `None` if checkpointing is off. The name ends in a hash of the
+/// file's path and the whole config, so a run only resumes a checkpoint of the same simulation.
+fn checkpoint_path(config: &TestConfig, file: &std::path::Path) -> Option<std::path::PathBuf> {
+    config.checkpoint_interval?;
+    if let Err(e) = std::fs::create_dir_all("checkpoints") {
+        eprintln!("Failed to create checkpoints directory: {}", e);
+        return None;
+    }
+    let file_name = file.file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    // neither changes the simulated state, so a resumed run may use another interval or limit
+    let simulated = TestConfig { checkpoint_interval: None, simulation_timeout_cycles: None, ..config.clone() };
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (file, format!("{:?}", simulated)).hash(&mut hasher);
+    Some(std::path::PathBuf::from(format!("checkpoints/{}-{}-{:016x}.bin", config_name(config), file_name, hasher.finish())))
+}
+/// Name of the log file (and prefix of the checkpoints) of a configuration
+pub fn config_name(config: &TestConfig) -> String {
+    let test_name = config.test_dir.split('/').last().unwrap_or("unknown");
+    let topology = match &config.topology {
+        Topology::Custom(path) => format!("Custom({})", path.file_stem().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()),  // no slashes in the log name
+        topology => format!("{:?}", topology),
+    };
+    format!(
+        "{}-{}-{}-{}-{}",
+        test_name, topology, config.num_nodes, config.node_bandwidth, config.num_vars
+    )
+}
+fn log_test(test_log: TestLog) {
+    let log_file_path = format!("logs/{}.csv", config_name(&test_log.config));
+
+    // Create logs directory if it doesn't exist
+    if let Err(e) = std::fs::create_dir_all("logs") {
+        eprintln!("Failed to create logs directory: {}", e);
+        return;
+    }
+
+    // Open or create the CSV file
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_file_path.clone());
+
+    match file {
+        Ok(file) => {
+            let file_is_empty = file.metadata().map(|m| m.len() == 0).unwrap_or(false);
+            let mut writer = Writer::from_writer(file);
+
+            // Write the header if the file is empty
+            if file_is_empty {
+                if let Err(e) = writer.write_record(&[
+                    "Test Path",
+                    "Expected Result",
+                    "Minisat Speed (ns)",
+                    "Simulated Result",
+                    "Simulated Cycles",
+                    "Cycles Busy",
+                    "Cycles Idle",
+                    "Num Nodes",
+                    "Topology",
+                    "Node Bandwidth",
+                    "Number of Variables",
+                    "Fork Attempts",
+                    "Fork Successes",
+                    "Fork Success Rate",
+                    "Cycles Unit Propagation",
+                    "Cycles Decision",
+                    "Cycles Conflict",
+                    "Repeats",
+                    "Simulated Cycles Mean",
+                    "Simulated Cycles Stddev",
+                    "Clause Evaluations",
+                    "Clause Length Min",
+                    "Clause Length Median",
+                    "Clause Length Max",
+                    "Variable Degree Min",
+                    "Variable Degree Median",
+                    "Variable Degree Max",
+                    "Models Found",
+                    "Search Leaves",
+                    "Utilization",
+                    "Speedup",
+                    "Efficiency",
+                    "Cycles per Minisat ns",
+                    "Outlier",
+                    "Timed Out",
+                    "Clause Accesses Min",
+                    "Clause Accesses Max",
+                    "Clause Accesses Mean",
+                    "Energy (pJ)",
+                    "Models per Joule",
+                    "Most Active Clauses",
+                    "Wakeups",
+                    "Forks Total",
+                    "Forks Productive",
+                    "Fork Efficiency",
+                    "Avg Fork Delay"
+                ]) {
+                    eprintln!("Failed to write CSV header: {}", e);
+                    return;
+                }
+            }
+
+            // Write the test log as a CSV record
+            let (clause_lengths, var_degrees) = (test_log.description.clause_length_percentiles(), test_log.description.var_degree_percentiles());
+            let minisat_ratio = test_log.minisat_ratio();
+            let clause_accesses = test_log.test_result.clause_access_stats();
+            if let Err(e) = writer.write_record(&[
+                test_log.test_path,
+                test_log.expected_result.map_or(String::new(), |result| result.to_string()),
+                test_log.minisat_speed.as_nanos().to_string(),
+                test_log.test_result.simulated_result.to_string(),
+                test_log.test_result.simulated_cycles.to_string(),
+                test_log.test_result.cycles_busy.to_string(),
+                test_log.test_result.cycles_idle.to_string(),
+                test_log.config.num_nodes.to_string(),
+                test_log.config.topology.to_string(),
+                test_log.config.node_bandwidth.to_string(),
+                test_log.config.num_vars.to_string(),
+                test_log.test_result.fork_attempts.to_string(),
+                test_log.test_result.fork_successes.to_string(),
+                test_log.test_result.fork_success_rate().to_string(),
+                test_log.test_result.cycles_unit_prop.to_string(),
+                test_log.test_result.cycles_decision.to_string(),
+                test_log.test_result.cycles_conflict.to_string(),
+                test_log.repeats.runs.to_string(),
+                test_log.repeats.mean_cycles.to_string(),
+                test_log.repeats.stddev_cycles.to_string(),
+                test_log.test_result.clause_evaluations.to_string(),
+                clause_lengths.min.to_string(),
+                clause_lengths.median.to_string(),
+                clause_lengths.max.to_string(),
+                var_degrees.min.to_string(),
+                var_degrees.median.to_string(),
+                var_degrees.max.to_string(),
+                test_log.test_result.models_found.to_string(),
+                test_log.test_result.search_leaves.to_string(),
+                test_log.test_result.utilization().to_string(),
+                test_log.sequential_cycles.map_or(String::new(), |cycles| test_log.test_result.speedup(cycles).to_string()),
+                test_log.sequential_cycles.map_or(String::new(), |cycles| test_log.test_result.efficiency(cycles, test_log.config.num_nodes).to_string()),
+                minisat_ratio.to_string(),
+                test_log.outlier.to_string(),
+                test_log.test_result.timed_out.to_string(),
+                clause_accesses.map_or(String::new(), |(min, _, _)| min.to_string()),
+                clause_accesses.map_or(String::new(), |(_, max, _)| max.to_string()),
+                clause_accesses.map_or(String::new(), |(_, _, mean)| mean.to_string()),
+                test_log.config.energy_model.map_or(String::new(), |_| test_log.test_result.total_energy_pj.to_string()),
+                test_log.config.energy_model.map_or(String::new(), |_| test_log.test_result.energy_efficiency().to_string()),
+                test_log.test_result.top_k_active_clauses(10).iter().map(|clause| clause.to_string()).collect::<Vec<_>>().join(" "),
+                test_log.test_result.wakeups.to_string(),
+                test_log.test_result.forks_total.to_string(),
+                test_log.test_result.forks_productive.to_string(),
+                test_log.test_result.fork_efficiency().to_string(),
+                test_log.test_result.avg_fork_delay.to_string(),
+            ]) {
+                eprintln!("Failed to write CSV record: {}", e);
+            }
+
+            if let Err(e) = writer.flush() {
+                eprintln!("Failed to flush CSV writer: {}", e);
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to open log file: {}: {}", log_file_path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Small swarm on the 20 variable satlib files, quick enough to simulate many times
+    pub(crate) fn test_config() -> TestConfig {
+        TestConfig {
+            num_nodes: 4,
+            topology: Topology::Grid(2, 2),
+            node_bandwidth: 100,
+            num_vars: 20,
+            test_dir: String::from("tests"),
+            wrap_delay: 1,
+            decision_delay: 0,
+            cycles_per_eval: 1,
+            pipeline_depth: 1,
+            clause_layout: ClauseLayout::Sequential,
+            wakeup_penalty: 0,
+            start_nodes: 1,
+            checkpoint_interval: None,
+            fork_bandwidth_bytes_per_cycle: 100,
+            adaptive_fork_delay: false,
+            fork_policy: ForkPolicy::FirstNeighbor,
+            fork_fanout: 1,
+            variable_partition: None,
+            search_mode: SearchMode::FirstModel,
+            lazy_evaluation: false,
+            seed: 0,
+            simulation_timeout_cycles: None,
+            enable_unit_prop: true,
+            track_clause_accesses: false,
+            random_polarity: false,
+            verify_level: VerifyLevel::Off,
+            energy_model: None,
+        }
+    }
+
+    fn test_options(jobs: usize) -> RunOptions {
+        RunOptions {
+            progress: false,
+            external_solver: None,
+            oracle_timeout: DEFAULT_ORACLE_TIMEOUT,
+            repeat: 1,
+            jobs,
+            baseline: false,
+            pending_logs: Arc::new(PendingLogs::new(10.0)),
+            interrupted: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Result of a run that took `simulated_cycles`, with nothing else measured
+    fn test_result(simulated_cycles: u64, cycles_busy: u64, cycles_idle: u64) -> TestResult {
+        TestResult {
+            simulated_result: false,
+            simulated_cycles,
+            cycles_busy,
+            cycles_idle,
+            fork_attempts: 0,
+            fork_successes: 0,
+            wakeups: 0,
+            forks_total: 0,
+            forks_productive: 0,
+            avg_fork_delay: 0.0,
+            model: None,
+            conflict_histogram: Vec::new(),
+            cycles_unit_prop: 0,
+            cycles_decision: 0,
+            cycles_conflict: 0,
+            clause_evaluations: 0,
+            models_found: 0,
+            search_leaves: 0,
+            timed_out: false,
+            clause_accesses: None,
+            total_energy_pj: 0.0,
+            clause_activity: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn perfect_scaling_on_four_nodes() {
+        let result = test_result(100, 400, 0);
+        assert_eq!(result.speedup(400), 4.0);
+        assert_eq!(result.efficiency(400, 4), 1.0);
+        assert_eq!(result.utilization(), 1.0);
+    }
+
+    #[test]
+    fn utilization_without_busy_cycles() {
+        assert_eq!(test_result(100, 0, 400).utilization(), 0.0);
+        assert_eq!(test_result(0, 0, 0).utilization(), 0.0);
+    }
+
+    #[test]
+    fn topologies_round_trip_through_their_display() {
+        let edges = std::env::temp_dir().join(format!("sat_swarm_{}_edges.txt", std::process::id()));
+        std::fs::write(&edges, "0 1\n1 2\n").unwrap();
+        let topologies = [
+            Topology::Grid(4, 8),
+            Topology::Torus(8, 8),
+            Topology::Dense(16),
+            Topology::Ring(5),
+            Topology::Butterfly(3),
+            Topology::Custom(edges.clone()),
+        ];
+        for topology in topologies {
+            assert_eq!(topology.to_string().parse::<Topology>(), Ok(topology.clone()), "{}", topology);
+        }
+        assert_eq!(Topology::Torus(8, 8).to_string(), "torus:8x8");
+        assert!("grid:4".parse::<Topology>().is_err());
+        std::fs::remove_file(edges).unwrap();
+    }
+
+    #[test]
+    fn valid_config_passes() {
+        assert_eq!(test_config().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let config = TestConfig { num_nodes: 0, node_bandwidth: 0, test_dir: String::from("tests/satlib/missing"), ..test_config() };
+        let errors = config.validate().unwrap_err();
+        assert!(errors.iter().any(|error| error.contains("num_nodes must be >= 1")), "{:?}", errors);
+        assert!(errors.contains(&String::from("node_bandwidth must be >= 1")));
+        assert!(errors.contains(&String::from("grid:2x2 has 4 nodes but num_nodes is 0")));
+        assert!(errors.contains(&String::from("test path tests/satlib/missing does not exist")));
+    }
+
+    #[test]
+    fn wrap_delay_leaves_room_for_the_largest_fork() {
+        // 255 assigned variables at 2 bytes each take 510 of the 1024 cycles the message queue holds at 1 byte per cycle
+        let config = TestConfig { topology: Topology::Torus(2, 2), fork_bandwidth_bytes_per_cycle: 1, ..test_config() };
+        assert!(TestConfig { wrap_delay: 514, ..config.clone() }.validate().is_ok());
+        let errors = TestConfig { wrap_delay: 515, ..config.clone() }.validate().unwrap_err();
+        assert_eq!(errors, vec![String::from("wrap_delay must be between 1 and 514 at a fork bandwidth of 1")]);
+        assert!(TestConfig { wrap_delay: 1024, ..test_config() }.validate().is_err());
+    }
+
+    /// Log of a test that took `simulated_cycles` cycles and MiniSat `minisat_ns` ns, None = the solver timed out
+    fn test_log(simulated_cycles: u64, minisat_ns: u64, expected_result: Option<bool>) -> TestLog {
+        let table: ClauseTable = ClauseTable::from_clauses(3, &[vec![1, 2, 3]]);
+        TestLog {
+            test_result: test_result(simulated_cycles, simulated_cycles, 0),
+            config: test_config(),
+            expected_result,
+            minisat_speed: Duration::from_nanos(minisat_ns),
+            test_path: format!("{}.cnf", simulated_cycles),
+            repeats: RepeatStats::from_cycles(&[simulated_cycles]),
+            description: ProblemDescription::from_table(&table),
+            sequential_cycles: None,
+            outlier: false,
+        }
+    }
+
+    #[test]
+    fn outliers_are_far_above_the_lower_median() {
+        // ratios 1, 2, 3 and 7: the lower median is 2, the upper one 3 would not flag 7
+        let mut logs = vec![test_log(100, 100, Some(true)), test_log(700, 100, Some(false)), test_log(200, 100, Some(true)), test_log(300, 100, Some(true))];
+        logs.push(test_log(100_000, 1, None));  // counted, it would move the median to 3
+        flag_outliers(&mut logs, 3.0);
+        let flagged: Vec<bool> = logs.iter().map(|log| log.outlier).collect();
+        assert_eq!(flagged, vec![false, true, false, false, false]);
+
+        flag_outliers(&mut logs, 4.0);
+        assert!(logs.iter().all(|log| !log.outlier), "flags are recomputed, not accumulated");
+        let mut timed_out = vec![test_log(100, 1, None)];
+        flag_outliers(&mut timed_out, 1.0);
+        assert!(!timed_out[0].outlier);
+    }
+
+    #[test]
+    fn run_single_gives_the_verdict_of_one_file() {
+        let sat = run_single(std::path::Path::new("tests/satlib/sat/uf20-01.cnf"), &test_config()).unwrap();
+        assert!(sat.simulated_result && sat.model.is_some());
+        // the 50 variables don't match config.num_vars, which only filters workloads
+        let unsat = run_single(std::path::Path::new("tests/satlib/unsat/uuf50-01.cnf"), &test_config()).unwrap();
+        assert!(!unsat.simulated_result && !unsat.timed_out);
+        assert!(matches!(run_single(std::path::Path::new("tests/satlib/missing.cnf"), &test_config()), Err(RunError::Parse(ParseError::Io(_)))));
+        // bad configurations are reported before anything is built, a missing edge list used to panic in generate
+        let no_nodes = TestConfig { num_nodes: 0, topology: Topology::Dense(0), ..test_config() };
+        assert!(matches!(run_single(std::path::Path::new("tests/satlib/sat/uf20-01.cnf"), &no_nodes), Err(RunError::Config(_))));
+        let missing_edges = TestConfig { topology: Topology::Custom("tests/no_such_edges.txt".into()), ..test_config() };
+        assert!(matches!(run_single(std::path::Path::new("tests/satlib/sat/uf20-01.cnf"), &missing_edges), Err(RunError::Config(_))));
+        let empty: ClauseTable = ClauseTable::from_clauses(0, &[]);
+        assert!(matches!(SatSwarm::generate(empty, &missing_edges), Err(SimulationError::BadEdgeList { .. })));
+    }
+
+    #[test]
+    fn unreadable_checkpoints_are_discarded() {
+        let file = std::path::Path::new("tests/satlib/sat/uf20-01.cnf");
+        let config = TestConfig { checkpoint_interval: Some(1_000_000), seed: 1566, ..test_config() };
+        let path = checkpoint_path(&config, file).unwrap();
+        assert_ne!(checkpoint_path(&TestConfig { wrap_delay: 2, ..config.clone() }, file), Some(path.clone()));
+        assert_eq!(checkpoint_path(&TestConfig { simulation_timeout_cycles: Some(10), ..config.clone() }, file), Some(path.clone()));
+        std::fs::write(&path, b"not a checkpoint").unwrap();
+        assert!(run_single(file, &config).unwrap().simulated_result);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn failed_solvers_fall_back_to_minisat() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(std::path::PathBuf::from("tests/satlib/sat/uf20-01.cnf")).unwrap();
+        let missing = RunOptions { external_solver: Some(String::from("tests/no_such_solver")), ..test_options(1) };
+        assert_eq!(expected_result(&table, &missing).0, Some(true));
+        let answerless = RunOptions { external_solver: Some(String::from("true")), ..test_options(1) };
+        assert_eq!(expected_result(&table, &answerless).0, Some(true));
+    }
+
+    #[test]
+    fn slow_solvers_leave_the_result_unknown() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(std::path::PathBuf::from("tests/satlib/sat/uf20-01.cnf")).unwrap();
+        let options = RunOptions { external_solver: Some(String::from("sleep 5")), oracle_timeout: Duration::from_millis(50), ..test_options(1) };
+        assert_eq!(expected_result(&table, &options), (None, Duration::from_millis(50)));
+    }
+
+    /// The logs `run_test_files` left pending, in file order
+    fn take_logs(options: &RunOptions) -> Vec<TestLog> {
+        let mut pending = options.pending_logs.lock();
+        pending.sort_by_key(|(file_index, _)| *file_index);
+        pending.drain(..).map(|(_, test_log)| test_log).collect()
+    }
+
+    #[test]
+    fn parallel_run_matches_sequential() {
+        let files: Vec<_> = ["uf20-01.cnf", "uf20-010.cnf", "uf20-0100.cnf", "uf20-01000.cnf", "uf20-0101.cnf"].iter()
+            .map(|name| std::path::Path::new("tests/satlib/sat").join(name))
+            .collect();
+        let config = test_config();
+        let summary = |logs: Vec<TestLog>| logs.into_iter()
+            .map(|log| (log.test_path, log.test_result.simulated_result, log.test_result.simulated_cycles))
+            .collect::<Vec<_>>();
+        let (sequential, parallel) = (test_options(1), test_options(3));
+        run_test_files(files.clone(), &config, &sequential);
+        run_test_files(files, &config, &parallel);
+        let sequential = summary(take_logs(&sequential));
+        assert_eq!(sequential.len(), 5);
+        assert_eq!(summary(take_logs(&parallel)), sequential);
+    }
+
+    #[test]
+    fn repeats_of_a_deterministic_run_agree() {
+        let files = vec![std::path::PathBuf::from("tests/satlib/sat/uf20-01.cnf")];
+        let (single, repeated) = (test_options(1), RunOptions { repeat: 3, ..test_options(1) });
+        run_test_files(files.clone(), &test_config(), &single);
+        run_test_files(files, &test_config(), &repeated);
+        let (single, repeated) = (take_logs(&single).remove(0), take_logs(&repeated).remove(0));
+        assert_eq!(repeated.repeats.runs, 3);
+        assert_eq!(repeated.repeats.stddev_cycles, 0.0);
+        assert_eq!(repeated.repeats.mean_cycles, single.test_result.simulated_cycles as f64);
+        assert_eq!(repeated.test_result.simulated_cycles, single.test_result.simulated_cycles, "the first run is the one logged");
+
+        let spread = RepeatStats::from_cycles(&[2, 4, 4, 4, 5, 5, 7, 9]);
+        assert_eq!((spread.runs, spread.mean_cycles, spread.stddev_cycles), (8, 5.0, 2.0));
+    }
+
+    #[test]
+    fn interrupted_run_stops_after_the_current_file() {
+        let mut files = get_test_files("tests/satlib/sat").unwrap();
+        files.sort();
+        files.truncate(200);
+        let options = test_options(1);
+        let (flag, pending_logs) = (options.interrupted.clone(), options.pending_logs.clone());
+        let interrupter = std::thread::spawn(move || {
+            while pending_logs.lock().is_empty() {
+                std::thread::yield_now();
+            }
+            flag.store(true, Ordering::Relaxed);
+            pending_logs.lock().len()  // files finished by the time the flag was up, at most one more may have been running
+        });
+        run_test_files(files.clone(), &test_config(), &options);
+        let finished_when_interrupted = interrupter.join().unwrap();
+        let logs = take_logs(&options);
+        assert!(logs.len() <= finished_when_interrupted + 1, "{} files logged, {} when interrupted", logs.len(), finished_when_interrupted);
+        let logged: Vec<String> = logs.into_iter().map(|log| log.test_path).collect();
+        let first: Vec<String> = files.iter().take(logged.len()).map(|file| file.to_str().unwrap().to_string()).collect();
+        assert_eq!(logged, first, "the files before the interrupt all finished");
+    }
+}
@@ -1,17 +1,16 @@
 #![allow(unused)]
 use std::env;
+use std::io::IsTerminal;
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
 use std::time::Duration;
 
-use csv::Writer;
-use std::fs::OpenOptions;
-use structures::minisat::minisat_table;
-use structures::{clause_table::ClauseTable, satswarm::SatSwarm};
-
-mod structures;
+use sat_swarm::structures::logging::{set_log_level, LogLevel, STATIC_MAX_LEVEL};
+use sat_swarm::structures::{clause_table::ClauseTable, node::ClauseLayout, satswarm::{read_edge_list, SatSwarm, DEFAULT_TIMEOUT_CYCLES}};
+use sat_swarm::{config_name, run_workload, testing, EnergyModel, ForkPolicy, PendingLogs, RunOptions, SearchMode, TestConfig, Topology, VerifyLevel, DEFAULT_ORACLE_TIMEOUT};
 
 // example command: cargo run -- --num_nodes 64 --topology grid --test_path /Users/shaanyadav/Desktop/Projects/SatSwarm/src/tests --node_bandwidth 100 --num_vars 50
 fn main() {
-    // build_random_testset(51, 10, 3, 3);
+    // build_random_testset(std::path::Path::new("tests/random"), 10, 5.1, 3, 3, None, 0).unwrap();
     // return;
     let args: Vec<String> = env::args().collect();
     let mut num_nodes: usize = 100; // Default value for --num_nodes
@@ -19,6 +18,37 @@ fn main() {
     let mut test_path = String::from("tests"); // Default value for --test_path
     let mut node_bandwidth = 100; // Default value for --node_bandwidth
     let mut num_vars = 50; // Default value for --num_vars
+    let mut wrap_delay = 1; // Default value for --wrap_delay
+    let mut decision_delay = 0; // Default value for --decision_delay
+    let mut cycles_per_eval = 1; // Default value for --cycles_per_eval
+    let mut pipeline_depth = 1; // Default value for --pipeline_depth
+    let mut clause_layout = ClauseLayout::Sequential; // Default value for --clause_layout
+    let mut wakeup_penalty = 0; // Default value for --wakeup_penalty
+    let mut start_nodes = 1; // Default value for --start_nodes
+    let mut checkpoint_interval = None; // Default value for --checkpoint_interval
+    let mut fork_bandwidth = None; // Default value for --fork_bandwidth (falls back to --node_bandwidth)
+    let mut adaptive_fork_delay = false; // Default value for --adaptive_fork_delay
+    let mut progress = std::io::stdout().is_terminal(); // Progress bars only make sense on a terminal
+    let mut external_solver = None; // Default value for --external_solver (use the bundled MiniSat)
+    let mut oracle_timeout = DEFAULT_ORACLE_TIMEOUT; // Default value for --oracle_timeout
+    let mut fork_policy = ForkPolicy::FirstNeighbor; // Default value for --fork_policy
+    let mut fork_fanout = 1; // Default value for --fork_fanout
+    let mut repeat = 1; // Default value for --repeat
+    let mut jobs = 1; // Default value for --jobs
+    let mut lazy_evaluation = false; // Default value for --lazy_evaluation
+    let mut search_mode = SearchMode::FirstModel; // Default value for --search_mode
+    let mut baseline = false; // Default value for --baseline
+    let mut outlier_multiple = 10.0; // Default value for --outlier_multiple
+    let mut simulation_timeout = Some(DEFAULT_TIMEOUT_CYCLES); // Default value for --simulation_timeout
+    let mut enable_unit_prop = true; // Default value for --disable_unit_prop
+    let mut track_clause_accesses = false; // Default value for --track_clause_accesses
+    let mut random_polarity = false; // Default value for --random_polarity
+    let mut verify_level = VerifyLevel::Off; // Default value for --verify_level
+    let mut log_level = LogLevel::Info; // Default value for --log_level
+    let mut energy_model = None; // Default value for --energy_model
+    let mut compare_logs = None; // Default value for --compare (run a workload instead)
+    let mut cycle_tolerance = 5.0; // Default value for --cycle_tolerance
+    let mut topology_stats = false; // Default value for --topology_stats (run a workload instead)
 
     // Parse command-line arguments
     let mut i = 1;
@@ -79,14 +109,377 @@ fn main() {
                     std::process::exit(1);
                 }
             }
+            "--wrap_delay" => {
+                if i + 1 < args.len() {
+                    wrap_delay = args[i + 1].parse::<usize>().unwrap_or_else(|_| {
+                        eprintln!("Invalid value for --wrap_delay: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --wrap_delay");
+                    std::process::exit(1);
+                }
+            }
+            "--decision_delay" => {
+                if i + 1 < args.len() {
+                    decision_delay = args[i + 1].parse::<u64>().unwrap_or_else(|_| {
+                        eprintln!("Invalid value for --decision_delay: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --decision_delay");
+                    std::process::exit(1);
+                }
+            }
+            "--cycles_per_eval" => {
+                if i + 1 < args.len() {
+                    cycles_per_eval = args[i + 1].parse::<u64>().unwrap_or_else(|_| {
+                        eprintln!("Invalid value for --cycles_per_eval: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --cycles_per_eval");
+                    std::process::exit(1);
+                }
+            }
+            "--pipeline_depth" => {
+                if i + 1 < args.len() {
+                    pipeline_depth = args[i + 1].parse::<usize>().unwrap_or_else(|_| {
+                        eprintln!("Invalid value for --pipeline_depth: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --pipeline_depth");
+                    std::process::exit(1);
+                }
+            }
+            "--clause_layout" => {
+                if i + 1 < args.len() {
+                    let value = args[i + 1].as_str();
+                    clause_layout = match value {
+                        "sequential" => ClauseLayout::Sequential,
+                        _ => match value.strip_prefix("interleaved:").and_then(|n| n.parse::<usize>().ok()).filter(|&n| n > 0) {
+                            Some(banks) => ClauseLayout::Interleaved { banks },
+                            None => {
+                                eprintln!("Invalid value for --clause_layout: {}", value);
+                                std::process::exit(1);
+                            }
+                        },
+                    };
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --clause_layout");
+                    std::process::exit(1);
+                }
+            }
+            "--wakeup_penalty" => {
+                if i + 1 < args.len() {
+                    wakeup_penalty = args[i + 1].parse::<u64>().unwrap_or_else(|_| {
+                        eprintln!("Invalid value for --wakeup_penalty: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --wakeup_penalty");
+                    std::process::exit(1);
+                }
+            }
+            "--start_nodes" => {
+                if i + 1 < args.len() {
+                    start_nodes = args[i + 1].parse::<usize>().unwrap_or_else(|_| {
+                        eprintln!("Invalid value for --start_nodes: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --start_nodes");
+                    std::process::exit(1);
+                }
+            }
+            "--fork_bandwidth" => {
+                if i + 1 < args.len() {
+                    fork_bandwidth = Some(args[i + 1].parse::<usize>().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+                        eprintln!("Invalid value for --fork_bandwidth: {}", args[i + 1]);
+                        std::process::exit(1);
+                    }));
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --fork_bandwidth");
+                    std::process::exit(1);
+                }
+            }
+            "--checkpoint_interval" | "--checkpoint-interval" => {
+                if i + 1 < args.len() {
+                    checkpoint_interval = Some(args[i + 1].parse::<u64>().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+                        eprintln!("Invalid value for --checkpoint_interval: {}", args[i + 1]);
+                        std::process::exit(1);
+                    }));
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --checkpoint_interval");
+                    std::process::exit(1);
+                }
+            }
+            "--fork_policy" => {
+                if i + 1 < args.len() {
+                    fork_policy = match args[i + 1].as_str() {
+                        "first" => ForkPolicy::FirstNeighbor,
+                        "deepest" => ForkPolicy::DeepestNeighbor,
+                        _ => {
+                            eprintln!("Invalid value for --fork_policy: {}", args[i + 1]);
+                            std::process::exit(1);
+                        }
+                    };
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --fork_policy");
+                    std::process::exit(1);
+                }
+            }
+            "--fork_fanout" => {
+                if i + 1 < args.len() {
+                    fork_fanout = args[i + 1].parse::<usize>().unwrap_or_else(|_| {
+                        eprintln!("Invalid value for --fork_fanout: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --fork_fanout");
+                    std::process::exit(1);
+                }
+            }
+            "--repeat" => {
+                if i + 1 < args.len() {
+                    repeat = args[i + 1].parse::<usize>().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+                        eprintln!("Invalid value for --repeat: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --repeat");
+                    std::process::exit(1);
+                }
+            }
+            "--jobs" => {
+                if i + 1 < args.len() {
+                    jobs = args[i + 1].parse::<usize>().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+                        eprintln!("Invalid value for --jobs: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --jobs");
+                    std::process::exit(1);
+                }
+            }
+            "--search_mode" => {
+                if i + 1 < args.len() {
+                    let value = args[i + 1].as_str();
+                    search_mode = match value {
+                        "first" => SearchMode::FirstModel,
+                        "exhaustive" => SearchMode::Exhaustive,
+                        _ => match value.strip_prefix("all:").and_then(|cap| cap.parse::<u64>().ok()).filter(|&cap| cap > 0) {
+                            Some(cap) => SearchMode::AllModels(cap),
+                            None => {
+                                eprintln!("Invalid value for --search_mode: {}", value);
+                                std::process::exit(1);
+                            }
+                        },
+                    };
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --search_mode");
+                    std::process::exit(1);
+                }
+            }
+            "--simulation_timeout" => {
+                if i + 1 < args.len() {
+                    simulation_timeout = match args[i + 1].as_str() {
+                        "off" => None,
+                        value => Some(value.parse::<u64>().ok().filter(|&cycles| cycles > 0).unwrap_or_else(|| {
+                            eprintln!("Invalid value for --simulation_timeout: {}", value);
+                            std::process::exit(1);
+                        })),
+                    };
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --simulation_timeout");
+                    std::process::exit(1);
+                }
+            }
+            "--verify_level" => {
+                if i + 1 < args.len() {
+                    let value = args[i + 1].as_str();
+                    verify_level = match value {
+                        "off" => VerifyLevel::Off,
+                        "full" => VerifyLevel::Full,
+                        _ => match value.strip_prefix("sampled:").and_then(|n| n.parse::<u64>().ok()).filter(|&n| n > 0) {
+                            Some(n) => VerifyLevel::Sampled(n),
+                            None => {
+                                eprintln!("Invalid value for --verify_level: {}", value);
+                                std::process::exit(1);
+                            }
+                        },
+                    };
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --verify_level");
+                    std::process::exit(1);
+                }
+            }
+            "--energy_model" => {
+                if i + 1 < args.len() {
+                    let costs: Vec<f64> = args[i + 1].split(',').map(|cost| cost.parse::<f64>().ok().filter(|&cost| cost >= 0.0)).collect::<Option<_>>().unwrap_or_default();
+                    if costs.len() != 5 {
+                        eprintln!("Invalid value for --energy_model: {} (expected 5 comma separated picojoule costs)", args[i + 1]);
+                        std::process::exit(1);
+                    }
+                    energy_model = Some(EnergyModel {
+                        decision_energy_pj: costs[0],
+                        unit_prop_energy_pj: costs[1],
+                        fork_send_energy_pj: costs[2],
+                        fork_receive_energy_pj: costs[3],
+                        idle_per_cycle_pj: costs[4],
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --energy_model");
+                    std::process::exit(1);
+                }
+            }
+            "--log_level" => {
+                if i + 1 < args.len() {
+                    log_level = args[i + 1].parse::<LogLevel>().unwrap_or_else(|e| {
+                        eprintln!("Invalid value for --log_level: {}", e);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --log_level");
+                    std::process::exit(1);
+                }
+            }
+            "--compare" => {
+                if i + 2 < args.len() {
+                    compare_logs = Some((args[i + 1].clone(), args[i + 2].clone()));
+                    i += 2; // Skip the values
+                } else {
+                    eprintln!("Missing value for --compare");
+                    std::process::exit(1);
+                }
+            }
+            "--cycle_tolerance" => {
+                if i + 1 < args.len() {
+                    cycle_tolerance = args[i + 1].parse::<f64>().ok().filter(|&t| t >= 0.0).unwrap_or_else(|| {
+                        eprintln!("Invalid value for --cycle_tolerance: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --cycle_tolerance");
+                    std::process::exit(1);
+                }
+            }
+            "--outlier_multiple" => {
+                if i + 1 < args.len() {
+                    outlier_multiple = args[i + 1].parse::<f64>().ok().filter(|&m| m > 0.0).unwrap_or_else(|| {
+                        eprintln!("Invalid value for --outlier_multiple: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --outlier_multiple");
+                    std::process::exit(1);
+                }
+            }
+            "--external_solver" => {
+                if i + 1 < args.len() {
+                    external_solver = Some(args[i + 1].clone());
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --external_solver");
+                    std::process::exit(1);
+                }
+            }
+            "--oracle_timeout" => {
+                if i + 1 < args.len() {
+                    oracle_timeout = args[i + 1].parse::<f64>().ok().filter(|&seconds| seconds > 0.0).map(Duration::from_secs_f64).unwrap_or_else(|| {
+                        eprintln!("Invalid value for --oracle_timeout: {}", args[i + 1]);
+                        std::process::exit(1);
+                    });
+                    i += 1; // Skip the value
+                } else {
+                    eprintln!("Missing value for --oracle_timeout");
+                    std::process::exit(1);
+                }
+            }
+            "--lazy_evaluation" => {
+                lazy_evaluation = true;
+            }
+            "--adaptive_fork_delay" => {
+                adaptive_fork_delay = true;
+            }
+            "--baseline" => {
+                baseline = true;
+            }
+            "--disable_unit_prop" => {
+                enable_unit_prop = false;
+            }
+            "--track_clause_accesses" => {
+                track_clause_accesses = true;
+            }
+            "--random_polarity" => {
+                random_polarity = true;
+            }
+            "--no_progress" | "--no-progress" => {
+                progress = false;
+            }
+            "--topology_stats" => {
+                topology_stats = true;
+            }
             "--help" => {
                 println!("Usage: cargo run -- [OPTIONS]");
                 println!("Options:");
                 println!("  --num_nodes <NUM>       Number of nodes (default: 100)");
-                println!("  --topology <TOPOLOGY>   Topology (default: grid)");
+                println!("  --topology <TOPOLOGY>   Topology: grid, torus, dense, ring, sized like grid:<ROWS>x<COLS> or dense:<NODES>, butterfly:<STAGES> or an edge list file (default: torus)");
                 println!("  --test_path <PATH>      Path to test files (default: tests)");
                 println!("  --node_bandwidth <BW>   Node bandwidth (default: 100)");
                 println!("  --num_vars <NUM>        Number of variables (default: 50)");
+                println!("  --wrap_delay <CYCLES>   Fork latency of torus wrap-around links (default: 1)");
+                println!("  --decision_delay <CYCLES> Extra cycles per branching decision (default: 0)");
+                println!("  --cycles_per_eval <CYCLES> Cycles to evaluate a batch of node_bandwidth clauses (default: 1)");
+                println!("  --pipeline_depth <NUM>  Assignments a node scans at once, so chained unit propagations overlap (default: 1)");
+                println!("  --clause_layout <LAYOUT> Order a scan reads the clauses in: sequential or round robin over BANKS memories (interleaved:<BANKS>) (default: sequential)");
+                println!("  --wakeup_penalty <CYCLES> Cycles an idle node needs to wake up when a fork arrives (default: 0)");
+                println!("  --start_nodes <NUM>     Nodes that start searching, must be a power of two (default: 1)");
+                println!("  --fork_bandwidth <BYTES> Bytes per cycle a link carries when forking (default: node bandwidth)");
+                println!("  --adaptive_fork_delay   Shorten link latencies by the share of idle nodes so work spreads faster when the network is starved");
+                println!("  --checkpoint_interval <CYCLES> Save a resumable checkpoint every CYCLES cycles, also spelled --checkpoint-interval (default: off)");
+                println!("  --fork_policy <POLICY>  Which node forks first when several want the same idle neighbor: first, deepest (default: first)");
+                println!("  --fork_fanout <K>       Idle neighbors one branching decision can fork to, closest first above 1 (default: 1)");
+                println!("  --lazy_evaluation       Scan the clauses once for a whole chain of unit propagations instead of once per assignment");
+                println!("  --search_mode <MODE>    Stop at the first model (first), search everything (exhaustive) or stop after CAP models (all:<CAP>) (default: first)");
+                println!("  --disable_unit_prop     Only assign variables by branching, to measure what unit propagation saves");
+                println!("  --track_clause_accesses Log the fewest, most and mean checks of a clause");
+                println!("  --random_polarity       Seed each node with a random value to speculate first, varied by --repeat");
+                println!("  --verify_level <LEVEL>  Check each node's clause table against its assignment: off, full or every Nth time (sampled:<N>) (default: off)");
+                println!("  --energy_model <DECISION,UNIT_PROP,FORK_SEND,FORK_RECEIVE,IDLE> Picojoules per operation and per idle cycle, logs the energy of every test (default: off)");
+                println!("  --baseline              Also simulate every test on a single node to log speedup and efficiency");
+                println!("  --simulation_timeout <CYCLES> Give up on a test after CYCLES cycles, or off (default: 150000000)");
+                println!("  --outlier_multiple <X>  Flag tests whose cycles per MiniSat ns exceed X times the median (default: 10)");
+                println!("  --repeat <NUM>          Runs per test file, the log gets the mean and stddev of the cycles (default: 1)");
+                println!("  --jobs <NUM>            Test files simulated at once on separate threads, without progress bars; MiniSat times get noisier (default: 1)");
+                println!("  --external_solver <CMD> DIMACS solver used for the expected results, e.g. \"kissat -q\" (default: MiniSat)");
+                println!("  --oracle_timeout <SECONDS> Give up on the expected result after SECONDS; such tests are neither passed nor failed (default: 30)");
+                println!("  --compare <OLD> <NEW>   Compare two logs instead of running tests, reporting flipped results and cycle changes");
+                println!("  --cycle_tolerance <PCT> Cycle change in percent --compare ignores (default: 5)");
+                println!("  --log_level <LEVEL>     Print simulation events at this level and above: trace, debug, info, warn, error (default: info)");
+                println!("  --no_progress           Disable progress bars (always off when stdout is not a terminal), also spelled --no-progress");
+                println!("  --topology_stats        Print the size, diameter, bisection width and most central nodes of the topology instead of running tests");
                 std::process::exit(0);
             }
             _ => {
@@ -97,27 +490,101 @@ fn main() {
         i += 1;
     }
 
+    if log_level < STATIC_MAX_LEVEL {
+        eprintln!("This build leaves out {:?} messages, rebuild with --features trace_logs to print them", log_level);
+    }
+    set_log_level(log_level);
+
+    if let Some((old_log, new_log)) = compare_logs {
+        match testing::compare_runs(std::path::Path::new(&old_log), std::path::Path::new(&new_log), cycle_tolerance) {
+            Ok(comparison) => comparison.print_summary(),
+            Err(e) => {
+                eprintln!("Failed to compare {} and {}: {}", old_log, new_log, e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let topology_str = topology;
+    let topology = parse_topology(&topology_str, num_nodes);
+    if let Topology::Custom(path) = &topology {
+        match read_edge_list(path) {
+            Ok((nodes, _)) => num_nodes = nodes,  // the file decides the size of the network
+            Err(e) => {
+                eprintln!("Invalid edge list {:?}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(nodes) = topology.node_count().filter(|_| topology_str.contains(':')) {
+        num_nodes = nodes;  // so do explicit dimensions, e.g. the stages of a butterfly
+    }
+
     println!("Number of nodes: {}", num_nodes);
     println!("Topology: {}", topology);
     println!("Test path: {}", test_path);
 
     let config = TestConfig {
         num_nodes,
-        topology: parse_topology(&topology, num_nodes),
+        topology,
         node_bandwidth,
         num_vars,
         test_dir: test_path.clone(),
+        wrap_delay,
+        decision_delay,
+        cycles_per_eval,
+        pipeline_depth,
+        clause_layout,
+        wakeup_penalty,
+        start_nodes,
+        checkpoint_interval,
+        fork_bandwidth_bytes_per_cycle: fork_bandwidth.unwrap_or(node_bandwidth),
+        adaptive_fork_delay,
+        fork_policy,
+        fork_fanout,
+        variable_partition: None,
+        seed: 0,
+        simulation_timeout_cycles: simulation_timeout,
+        enable_unit_prop,
+        track_clause_accesses,
+        random_polarity,
+        verify_level,
+        energy_model,
+        lazy_evaluation,
+        search_mode,
     };
+    if topology_stats {
+        let swarm: SatSwarm = SatSwarm::generate(ClauseTable::from_clauses(0, &[]), &config).unwrap_or_else(|e| {  // the clauses don't matter
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        print!("{}", swarm.topology_stats());
+        return;
+    }
     let log_file_path = format!("logs/{}.csv", config_name(&config));
     if std::path::Path::new(&log_file_path).exists() {
         eprintln!("Configuration with name '{}' already exists. Exiting to avoid overwriting logs.", log_file_path);
         std::process::exit(1);
     }
-    run_workload(test_path, config);
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let pending_logs = Arc::new(PendingLogs::new(outlier_multiple));
+    let (flag, logs) = (interrupted.clone(), pending_logs.clone());
+    if let Err(e) = ctrlc::set_handler(move || {
+        if flag.swap(true, Ordering::Relaxed) {
+            logs.write();  // second Ctrl-C, stop waiting for the current simulation but keep what has finished
+            std::process::exit(130);
+        }
+        eprintln!("Interrupted, finishing the current test and writing the logs (Ctrl-C again to quit now)");
+    }) {
+        eprintln!("Failed to set the Ctrl-C handler: {}", e);
+    }
+    run_workload(test_path, config, RunOptions { progress, external_solver, oracle_timeout, repeat, jobs, baseline, pending_logs, interrupted });
 
     println!("Done");
 }
 
+/// Parses `--topology`. The bare names take their size from `num_nodes`, anything else goes through `Topology::from_str`
 fn parse_topology(topology_str: &str, num_nodes: usize) -> Topology {
     match topology_str {
         "grid" => {
@@ -129,159 +596,10 @@ fn parse_topology(topology_str: &str, num_nodes: usize) -> Topology {
             Topology::Torus(size, size)
         }
         "dense" => Topology::Dense(num_nodes as usize),
-        _ => panic!("Invalid topology: {}", topology_str),
-    }
-}
-#[derive(Debug, Clone)]
-pub enum Topology {
-    Grid(usize, usize),
-    Torus(usize, usize),
-    Dense(usize),
-}
-
-
-pub struct TestResult {
-    pub simulated_result: bool,
-    pub simulated_cycles: u64,
-    pub cycles_busy: u64,
-    pub cycles_idle: u64,
-}
-pub struct TestLog {
-    pub test_result: TestResult,
-    pub config: TestConfig,
-    pub expected_result: bool,
-    pub minisat_speed: Duration,
-    pub test_path: String,
-}
-#[derive(Clone)]
-pub struct TestConfig {
-    pub num_nodes: usize,
-    pub topology: Topology,
-    pub node_bandwidth: usize,
-    pub num_vars: usize,
-    pub test_dir: String,
-}
-
-
-fn get_test_files(test_path: &str) -> Option<Vec<std::path::PathBuf>> {
-    let mut files = Vec::new();
-    fn collect_files(dir: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
-        if let Ok(entries) = std::fs::read_dir(dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_file() {
-                        // println!("Found test file: {:?}", path);
-                        files.push(path);
-                    } else if path.is_dir() {
-                        collect_files(&path, files);
-                    }
-                }
-            }
-        }
-    }
-
-    collect_files(std::path::Path::new(test_path), &mut files);
-    Some(files)
-}
-fn run_workload(test_path: String, config: TestConfig) {
-    // load test files from the specified path
-    if let Some(files) = get_test_files(&test_path) {
-        for file in files.into_iter() {
-            let f_copy = file.clone();
-            let (mut clause_table, _) = ClauseTable::load_file(file);
-            // skip if the clause table > 25 or expected result is unsat
-            if clause_table.number_of_vars() != config.num_vars {
-                continue;
-            }
-            println!("Running test: {:?}", f_copy);
-            let (expected_result, minisat_speed) = minisat_table(&clause_table);
-            let mut simulation = SatSwarm::generate(clause_table, &config);
-            let result = simulation.test_satisfiability();
-            assert!(result.simulated_result == expected_result, "Test failed: expected {}, got {}", expected_result, result.simulated_result);
-            let test_log = TestLog {
-                test_result: result,
-                config: config.clone(),
-                expected_result,
-                minisat_speed,
-                test_path: f_copy.to_str().unwrap_or("unknown").to_string(),
-            };
-            log_test(test_log);
-        }
-    } else {
-        println!("No tests directory found at: {}", test_path);
-    }
-}
-fn config_name(config: &TestConfig) -> String {
-    let test_name = config.test_dir.split('/').last().unwrap_or("unknown");
-    format!(
-        "{}-{:?}-{}-{}-{}",
-        test_name, config.topology, config.num_nodes, config.node_bandwidth, config.num_vars
-    )
-}
-fn log_test(test_log: TestLog) {
-    let log_file_path = format!("logs/{}.csv", config_name(&test_log.config));
-
-    // Create logs directory if it doesn't exist
-    if let Err(e) = std::fs::create_dir_all("logs") {
-        eprintln!("Failed to create logs directory: {}", e);
-        return;
-    }
-
-    // Open or create the CSV file
-    let file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(log_file_path.clone());
-
-    match file {
-        Ok(file) => {
-            let file_is_empty = file.metadata().map(|m| m.len() == 0).unwrap_or(false);
-            let mut writer = Writer::from_writer(file);
-
-            // Write the header if the file is empty
-            if file_is_empty {
-                if let Err(e) = writer.write_record(&[
-                    "Test Path",
-                    "Expected Result",
-                    "Minisat Speed (ns)",
-                    "Simulated Result",
-                    "Simulated Cycles",
-                    "Cycles Busy",
-                    "Cycles Idle",
-                    "Num Nodes",
-                    "Topology",
-                    "Node Bandwidth",
-                    "Number of Variables"
-                ]) {
-                    eprintln!("Failed to write CSV header: {}", e);
-                    return;
-                }
-            }
-
-            // Write the test log as a CSV record
-            if let Err(e) = writer.write_record(&[
-                test_log.test_path,
-                test_log.expected_result.to_string(),
-                test_log.minisat_speed.as_nanos().to_string(),
-                test_log.test_result.simulated_result.to_string(),
-                test_log.test_result.simulated_cycles.to_string(),
-                test_log.test_result.cycles_busy.to_string(),
-                test_log.test_result.cycles_idle.to_string(),
-                test_log.config.num_nodes.to_string(),
-                format!("{:?}", test_log.config.topology),
-                test_log.config.node_bandwidth.to_string(),
-                test_log.config.num_vars.to_string(),
-            ]) {
-                eprintln!("Failed to write CSV record: {}", e);
-            }
-
-            if let Err(e) = writer.flush() {
-                eprintln!("Failed to flush CSV writer: {}", e);
-            }
-        }
-        Err(e) => {
-            eprintln!("Failed to open log file: {}: {}", log_file_path, e);
-        }
+        "ring" => Topology::Ring(num_nodes),
+        _ => topology_str.parse().unwrap_or_else(|e| {
+            eprintln!("Invalid value for --topology: {} ({})", topology_str, e);
+            std::process::exit(1);
+        }),
     }
 }
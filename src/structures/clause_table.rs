@@ -1,6 +1,8 @@
 use std::{fs::File, io::Write as IoWrite};
-use std::{io::BufRead, path::PathBuf};
-use super::util_types::{NodeId, VarId, CLAUSE_LENGTH}; 
+use std::{io::BufRead, path::PathBuf, sync::Arc};
+use rustsat::instances::SatInstance;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use super::{logging::{sim_log, LogLevel}, util_types::{NodeId, VarId, CLAUSE_LENGTH}}; 
 struct Query {
     source: NodeId,
     var: VarId,
@@ -8,54 +10,147 @@ struct Query {
     reset: bool,
     updates_left: usize,
 }
-#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
 pub struct Term {
     pub var: VarId,
     pub negated: bool,
 }
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TermState {False, True, Symbolic} // True is not needed since the clause is satisfied when any term is true
 impl Default for TermState {fn default() -> Self {TermState::Symbolic}}
-pub type ClauseState = [TermState; CLAUSE_LENGTH];
+pub type ClauseState<const K: usize = CLAUSE_LENGTH> = [TermState; K];
 pub type CNFState = Vec<ClauseState>;
-pub struct ClauseTable {
-    pub clause_table: Vec<[(Term, TermState); CLAUSE_LENGTH]>,   // 2D Vec to store the table of clauses
+pub type ClauseIdx = usize;
+/// Where a term sits in the table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TermLoc {
+    pub clause: ClauseIdx,
+    pub term: usize,    // position within the clause
+}
+/// A clause state packed 2 bits per term (False = 00, True = 01, Symbolic = 10), term i in bits 2i..2i+2.
+/// Fits up to 32 terms so whole-clause checks become a couple of mask operations (a step towards SIMD over clauses).
+pub type ClauseStateBitset = u64;
+const BITSET_TRUE: u64 = 0x5555_5555_5555_5555;      // low bit of every term
+const BITSET_SYMBOLIC: u64 = 0xAAAA_AAAA_AAAA_AAAA;  // high bit of every term
+
+/// Packs a clause state into a bitset, see `ClauseStateBitset`
+pub fn encode_clause_state<const K: usize>(state: &ClauseState<K>) -> ClauseStateBitset {
+    const { assert!(K <= 32, "Clause does not fit in a bitset") };
+    state.iter().enumerate().fold(0, |bits, (i, term)| bits | match term {
+        TermState::False => 0b00,
+        TermState::True => 0b01,
+        TermState::Symbolic => 0b10,
+    } << (2 * i))
+}
+
+/// Every term is False
+pub fn bitset_is_unsat(bits: ClauseStateBitset) -> bool {
+    bits == 0
+}
+
+/// No term is True and exactly one is Symbolic
+pub fn bitset_is_unit(bits: ClauseStateBitset) -> bool {
+    bits & BITSET_TRUE == 0 && (bits & BITSET_SYMBOLIC).count_ones() == 1
+}
+/// Why a DIMACS file could not be loaded
+#[derive(Debug)]
+pub enum ParseError {
+    Io(std::io::Error),
+    BadHeader(String),                               // malformed header line, or the body disagrees with it
+    BadLiteral { line: usize, token: String },       // token that is not an integer, or comes after the closing 0
+    VarOutOfRange { line: usize, var: i64 },         // variables have to fit in a VarId (u8)
+    ClauseTooLong { line: usize, width: usize },     // more literals than the table width
+    UnterminatedClause { line: usize },              // the last clause has no closing 0, `line` is where it starts
+} impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "{}", e),
+            ParseError::BadHeader(header) => write!(f, "bad header: {}", header),
+            ParseError::BadLiteral { line, token } => write!(f, "line {}: bad literal {:?}", line, token),
+            ParseError::VarOutOfRange { line, var } => write!(f, "line {}: variable {} does not fit in a VarId", line, var),
+            ParseError::ClauseTooLong { line, width } => write!(f, "line {}: clause has more than {} literals", line, width),
+            ParseError::UnterminatedClause { line } => write!(f, "line {}: clause is missing its closing 0", line),
+        }
+    }
+} impl std::error::Error for ParseError {}
+impl From<std::io::Error> for ParseError {
+    fn from(e: std::io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+/// Two terms of the same variable whose states give it different values (see `ClauseTable::verify_consistency`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyError {
+    pub var: VarId,
+    pub location: TermLoc,  // the term that disagrees
+    pub value: bool,        // the value it gives the variable
+    pub first: TermLoc,     // the first term of the variable that was not symbolic, which gives it the other value
+} impl std::fmt::Display for ConsistencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "var {} is {} in clause {} term {} but {} in clause {} term {}",
+            self.var, self.value, self.location.clause, self.location.term, !self.value, self.first.clause, self.first.term)
+    }
+} impl std::error::Error for ConsistencyError {}
+
+/// Clauses padded to K terms; the clause width is fixed at compile time so the rows stay plain arrays.
+/// Every constructor accepts clauses of up to K literals, so a 3-SAT file loads into a `ClauseTable<4>` with one more padding term.
+/// Variables are numbered like DIMACS, 1..=n for a `p cnf n` file. Var 0 only fills the padding: it is not a variable
+/// of the formula and nodes fix it to false on activation, so padding terms never satisfy a clause. Assignments are
+/// indexed by variable and keep the slot, so `num_vars` is n + 1 and `number_of_vars` is n. Models leave var 0 out:
+/// `TestResult::model[0]` is always None and recovered assignments have no entry for it.
+pub struct ClauseTable<const K: usize = CLAUSE_LENGTH> {
+    pub clause_table: Vec<[(Term, TermState); K]>,   // 2D Vec to store the table of clauses
     pub num_clauses: usize,           // Number of clauses in the table
-    pub num_vars: usize,              // Number of variables in the table
+    pub num_vars: usize,              // Number of variables in the table plus the padding var 0, the length of an assignment
+    pub has_empty_clause: bool,       // The file contained an empty clause so the formula is trivially UNSAT
+    transpose: Arc<Vec<Vec<TermLoc>>>,  // Terms of each variable, shared by clones since only the states change
 }
 
-impl ClauseTable {
+impl<const K: usize> ClauseTable<K> {
     pub fn _dummy() -> Self {
         let num_clauses = 10; // Number of clauses in the table
-        Self {
-            clause_table: vec![[Default::default(); CLAUSE_LENGTH]; num_clauses as usize], // Initialize the clause table with 0s
-            num_clauses: num_clauses, // Initialize the number of clauses
-            num_vars: 1,
-        }
+        Self::with_clauses(vec![[Default::default(); K]; num_clauses as usize], 1, false)  // Initialize the clause table with 0s
     }
 
     pub fn random(num_clauses: usize, num_vars: u8) -> Self {
+        Self::random_with_rng(num_clauses, num_vars, &mut rand::rng())
+    }
+
+    /// `random` drawing from `rng`, so a seeded generator gives the same table every time
+    pub fn random_with_rng(num_clauses: usize, num_vars: u8, rng: &mut impl rand::Rng) -> Self {
         let mut clause_table = Vec::with_capacity(num_clauses);
         for _ in 0..num_clauses {
-            let mut clause = [(Term{var: 0, negated: false}, TermState::Symbolic); CLAUSE_LENGTH];
-            for i in 0..CLAUSE_LENGTH {
-                let var = ((rand::random::<u8>() % num_vars) + 1) as u8;
-                let negated = rand::random::<bool>();
+            let mut clause = [(Term{var: 0, negated: false}, TermState::Symbolic); K];
+            for i in 0..K {
+                let var = (rng.random::<u8>() % num_vars) + 1;
+                let negated = rng.random::<bool>();
                 clause[i] = (Term{var, negated}, TermState::Symbolic);
             }
             clause_table.push(clause);
         }
-        clause_table.push([(Term{var: 0, negated: true}, TermState::Symbolic); CLAUSE_LENGTH]);  // Add a dummy clause to the end to make var 0 false
-        // clause_table.push([Term{var: 0, negated: false}; CLAUSE_LENGTH]);  // Add a dummy clause to the end to make var 0 true (contradiction)
-        let num_clauses = clause_table.len();
-        Self {
-            clause_table,
-            num_clauses,
-            num_vars: (num_vars as usize),
+        Self::with_clauses(clause_table, (num_vars as usize) + 1, false)
+    }
+
+    /// Every constructor ends here so the transpose always matches the clauses
+    fn with_clauses(clause_table: Vec<[(Term, TermState); K]>, num_vars: usize, has_empty_clause: bool) -> Self {
+        let transpose = Arc::new(Self::transpose_of(&clause_table, num_vars));
+        Self { num_clauses: clause_table.len(), clause_table, num_vars, has_empty_clause, transpose }
+    }
+
+    /// The terms of each variable in table order, indexed by variable
+    fn transpose_of(clause_table: &[[(Term, TermState); K]], num_vars: usize) -> Vec<Vec<TermLoc>> {
+        let vars = clause_table.iter().flatten().map(|(t, _)| t.var as usize + 1).max().unwrap_or(0).max(num_vars);
+        let mut transpose = vec![Vec::new(); vars];
+        for (clause, terms) in clause_table.iter().enumerate() {
+            for (term, (t, _)) in terms.iter().enumerate() {
+                transpose[t.var as usize].push(TermLoc { clause, term });
+            }
         }
+        transpose
     }
 
-    pub fn load_file(file: PathBuf) -> (Self, bool) {
+    pub fn load_file(file: PathBuf) -> Result<(Self, bool), ParseError> {
         // Load a file and return a new ClauseTable with expected SAT result
         /* Example File Format                                  (0 is the end of the clause)
         c
@@ -67,85 +162,233 @@ impl ClauseTable {
         -88  55  -42  0
         -71  -49  46  0
          */
-        let mut num_clauses = 0;
         let sat = !file.to_string_lossy().to_lowercase().contains("unsat");
+        let file = std::fs::File::open(file)?;
+        let table = Self::read_dimacs(std::io::BufReader::new(file))?;
+        Ok((table, sat))
+    }
+
+    /// Parses a DIMACS CNF string exactly like `load_file` parses a file, for instances written inline
+    pub fn from_dimacs_str(s: &str) -> Result<Self, ParseError> {
+        Self::read_dimacs(std::io::Cursor::new(s))
+    }
+
+    fn read_dimacs(reader: impl BufRead) -> Result<Self, ParseError> {
+        let mut num_clauses = 0;
+        let mut header_seen = false;
         let mut clauses = Vec::new();
         let mut var_count = 0;
-        let file = std::fs::File::open(file).unwrap();
-        let reader = std::io::BufReader::new(file);
-        for line in reader.lines() {
-            let line = line.unwrap();
+        let mut has_empty_clause = false;
+        let mut clause = [Default::default(); K];
+        let mut clause_len = 0;   // literals read of the current clause, which may go on over several lines
+        let mut clause_line = 0;  // line the current clause started on
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = line_index + 1;
             // println!("{}", line);
-            let mut clause = [Default::default(); CLAUSE_LENGTH];
             let mut clause_end = false;
             if line.starts_with("p cnf") {  // Parse the number of variables and clauses *header*
                 let mut parts = line.split_whitespace();
                 // println!("{:?}", parts.clone().collect::<Vec<&str>>());
                 parts.next(); // Skip "p"
                 parts.next(); // Skip "cnf"
-                var_count = parts.next().unwrap().parse().unwrap();
-                assert!(var_count < u8::MAX as i32, "Too many variables for u8");
-                num_clauses = parts.next().unwrap().parse().unwrap();
+                var_count = parts.next().and_then(|v| v.parse().ok()).filter(|&v: &i32| v >= 0).ok_or_else(|| ParseError::BadHeader(line.clone()))?;
+                if var_count >= u8::MAX as i32 {
+                    return Err(ParseError::VarOutOfRange { line: line_number, var: var_count as i64 });  // Too many variables for u8
+                }
+                num_clauses = parts.next().and_then(|n| n.parse().ok()).ok_or_else(|| ParseError::BadHeader(line.clone()))?;
                 clauses = Vec::with_capacity(num_clauses);
+                header_seen = true;
             } else if line.starts_with("c") {  // Skip comments
                 continue;
             } else if line.starts_with("%") {  // end this file
                 break;
             } else {
                 let parts = line.split_whitespace();
-                for (term_index, part) in parts.enumerate() {
-                    assert!(!clause_end, "Clause has already ended");
-                    let num: i32 = part.parse().unwrap();
+                for part in parts {
+                    let num: i32 = part.parse().map_err(|_| ParseError::BadLiteral { line: line_number, token: part.to_string() })?;
+                    if clause_end {
+                        return Err(ParseError::BadLiteral { line: line_number, token: part.to_string() });  // Clause has already ended
+                    }
                     if num == 0 {
                         clause_end = true;
-                        assert!(term_index <= CLAUSE_LENGTH, "Only 3SAT is supported");
-                        for i in term_index..CLAUSE_LENGTH {
+                        if clause_len == 0 {
+                            has_empty_clause = true;  // a lone 0 can never be satisfied
+                        }
+                        for i in clause_len..K {
                             clause[i] = (Term{var: 0, negated: false}, TermState::Symbolic);  // Var 0 is always false
                         }
+                        clauses.push(clause);
+                        clause_len = 0;
+                    } else if clause_len >= K {
+                        return Err(ParseError::ClauseTooLong { line: line_number, width: K });
+                    } else if num.abs() >= u8::MAX as i32 {
+                        return Err(ParseError::VarOutOfRange { line: line_number, var: num.abs() as i64 });  // Too many variables for u8
                     } else {
-                        assert!(num.abs() < u8::MAX as i32, "Too many variables for u8");
-                        clause[term_index] = (Term{var: num.abs() as u8, negated: num < 0}, TermState::Symbolic);  // want to 0 index the variables
+                        if clause_len == 0 {
+                            clause_line = line_number;
+                        }
+                        clause[clause_len] = (Term{var: num.abs() as u8, negated: num < 0}, TermState::Symbolic);  // want to 0 index the variables
+                        clause_len += 1;
                     }
                 }
             }
-            if clause_end {
-                clauses.push(clause);
-            }
+        }
+        if clause_len > 0 {
+            return Err(ParseError::UnterminatedClause { line: clause_line });  // the file ended before its closing 0
         }
         if num_clauses < 10 {
-            println!("Clauses: {:?}, expected_num_clauses: {}, expected_sat: {}, expected_vars: {}", clauses, num_clauses, sat, var_count);
-        }
-        assert!(clauses.len() == num_clauses, "Number of clauses does not match header");
-        clauses.push([(Term{var: 0, negated: true}, TermState::Symbolic); CLAUSE_LENGTH]);  // Add a dummy clause to the end to make var 0 false
-        assert!(clauses.iter().map(|c| c.iter().map(|(t, _)| t.var).max().unwrap()).max().unwrap() == var_count as u8, "Variable count does not match header");
-        let num_clauses = clauses.len();
-        let s = Self {
-            clause_table: clauses,
-            num_clauses: num_clauses,
-            num_vars: (var_count+1) as usize
-        };
+            sim_log!("-", 0, LogLevel::Debug, "Clauses: {:?}, expected_num_clauses: {}, expected_vars: {}", clauses, num_clauses, var_count);
+        }
+        if !header_seen {
+            return Err(ParseError::BadHeader(String::from("missing p cnf line")));
+        }
+        if clauses.len() != num_clauses {
+            return Err(ParseError::BadHeader(format!("{} clauses but the header says {}", clauses.len(), num_clauses)));
+        }
+        let max_var = clauses.iter().flat_map(|c| c.iter().map(|(t, _)| t.var)).max().unwrap_or(0);
+        if max_var as i32 > var_count {  // declared variables no clause uses are free
+            return Err(ParseError::BadHeader(format!("highest variable is {} but the header says {}", max_var, var_count)));
+        }
+        Ok(Self::with_clauses(clauses, (var_count+1) as usize, has_empty_clause))
+    }
+
+    /// Builds a table from DIMACS style clauses (1-indexed literals, negative = negated) over variables 1..=num_vars
+    pub fn from_clauses(num_vars: usize, clauses: &[Vec<i32>]) -> Self {
+        assert!(num_vars < u8::MAX as usize, "Too many variables for u8");
+        let mut has_empty_clause = false;
+        let clause_table: Vec<[(Term, TermState); K]> = clauses.iter().map(|literals| {
+            assert!(literals.len() <= K, "Only {}-SAT is supported", K);
+            assert!(literals.iter().all(|&lit| lit != 0 && lit.unsigned_abs() as usize <= num_vars), "Literal out of range in {:?}", literals);
+            has_empty_clause |= literals.is_empty();
+            let mut clause = [(Term{var: 0, negated: false}, TermState::Symbolic); K];  // Var 0 is always false
+            for (i, &lit) in literals.iter().enumerate() {
+                clause[i] = (Term{var: lit.unsigned_abs() as u8, negated: lit < 0}, TermState::Symbolic);
+            }
+            clause
+        }).collect();
+        Self::with_clauses(clause_table, num_vars + 1, has_empty_clause)
+    }
+
+    /// Converts a rustsat instance, so its parsers and generators can feed the simulator.
+    /// rustsat variable `i` becomes variable `i + 1` like in DIMACS, keeping var 0 free for the padding.
+    /// Errors report the (1-indexed) clause number as their line.
+    pub fn from_sat_instance(instance: &SatInstance) -> Result<Self, ParseError> {
+        let mut clauses = Vec::with_capacity(instance.n_clauses());
+        let mut num_vars = 0;
+        for (clause_index, clause) in instance.cnf().iter().enumerate() {
+            let line = clause_index + 1;
+            if clause.len() > K {
+                return Err(ParseError::ClauseTooLong { line, width: K });
+            }
+            let literals = clause.iter().map(|lit| {
+                let var = lit.vidx() + 1;
+                if var >= u8::MAX as usize {
+                    return Err(ParseError::VarOutOfRange { line, var: var as i64 });  // Too many variables for u8
+                }
+                num_vars = num_vars.max(var);
+                Ok(if lit.is_neg() { -(var as i32) } else { var as i32 })
+            }).collect::<Result<Vec<i32>, ParseError>>()?;
+            clauses.push(literals);
+        }
+        Ok(Self::from_clauses(num_vars, &clauses))
+    }
 
-        (s, sat)
+    pub fn load_wcnf(file: PathBuf) -> Result<Self, ParseError> {
+        // Load the hard clauses of a (partial) MaxSAT file; soft clauses are dropped since they never affect satisfiability
+        /* Example File Format                                  (first number is the weight, 0 is the end of the clause)
+        c
+        c Partial MaxSAT instance in DIMACS WCNF input format.
+        c
+        p wcnf 100 286 1000                                     p wcnf <num_vars> <num_clauses> <top weight>
+        1000  80  -39  -21  0                                   weight >= top is a hard clause
+        3  -58  25  0                                           anything lighter is soft
+        h  -88  55  -42  0                                      newer files mark hard clauses with h and have no header
+         */
+        let mut var_count = 0;
+        let mut top: Option<u64> = None;
+        let mut clauses = Vec::new();
+        let mut has_empty_clause = false;
+        let file = std::fs::File::open(file)?;
+        let reader = std::io::BufReader::new(file);
+        for (line_index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line_number = line_index + 1;
+            if line.starts_with("p wcnf") {  // Parse the number of variables and the top weight *header*
+                let mut parts = line.split_whitespace();
+                parts.next(); // Skip "p"
+                parts.next(); // Skip "wcnf"
+                var_count = parts.next().and_then(|v| v.parse().ok()).filter(|&v: &i32| v >= 0).ok_or_else(|| ParseError::BadHeader(line.clone()))?;
+                if var_count >= u8::MAX as i32 {
+                    return Err(ParseError::VarOutOfRange { line: line_number, var: var_count as i64 });  // Too many variables for u8
+                }
+                parts.next(); // Skip the number of clauses, only the hard ones are kept
+                top = match parts.next() {
+                    Some(t) => Some(t.parse().map_err(|_| ParseError::BadHeader(line.clone()))?),
+                    None => None,
+                };
+                continue;
+            } else if line.starts_with("c") || line.trim().is_empty() {  // Skip comments
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let hard = match parts.next().unwrap() {
+                "h" => true,
+                weight => {
+                    let weight: u64 = weight.parse().map_err(|_| ParseError::BadLiteral { line: line_number, token: weight.to_string() })?;
+                    top.map_or(false, |top| weight >= top)
+                },
+            };
+            if !hard {
+                continue;
+            }
+            let mut clause = [Default::default(); K];
+            for (term_index, part) in parts.enumerate() {
+                let num: i32 = part.parse().map_err(|_| ParseError::BadLiteral { line: line_number, token: part.to_string() })?;
+                if num == 0 {
+                    if term_index == 0 {
+                        has_empty_clause = true;  // a lone 0 can never be satisfied
+                    }
+                    for i in term_index..K {
+                        clause[i] = (Term{var: 0, negated: false}, TermState::Symbolic);  // Var 0 is always false
+                    }
+                    break;
+                }
+                if term_index >= K {
+                    return Err(ParseError::ClauseTooLong { line: line_number, width: K });
+                }
+                if num.abs() >= u8::MAX as i32 {
+                    return Err(ParseError::VarOutOfRange { line: line_number, var: num.abs() as i64 });  // Too many variables for u8
+                }
+                clause[term_index] = (Term{var: num.abs() as u8, negated: num < 0}, TermState::Symbolic);
+                var_count = var_count.max(num.abs());  // files without a header only tell us through the clauses
+            }
+            clauses.push(clause);
+        }
+        Ok(Self::with_clauses(clauses, (var_count+1) as usize, has_empty_clause))
     }
     
-    pub fn write_file(&self, mut file: File) -> Result<(), std::io::Error> {
+    pub fn write_file(&self, file: impl IoWrite) -> Result<(), std::io::Error> {
+        self.write_file_with_comments(file, &[])
+    }
+
+    /// `write_file` with each of `comments` on a `c` line of its own after the standard header comments
+    pub fn write_file_with_comments(&self, mut file: impl IoWrite, comments: &[String]) -> Result<(), std::io::Error> {
         
         // Write standard DIMACS CNF header comments
         file.write_all(b"c\n")?;
         file.write_all(b"c SAT instance in DIMACS CNF input format.\n")?;
         file.write_all(b"c\n")?;
+        for comment in comments {
+            file.write_all(format!("c {}\n", comment).as_bytes())?;
+        }
         
         // Write the problem line with number of variables and clauses
-        file.write_all(format!("p cnf {} {}\n", self.num_vars, self.num_clauses-1).as_bytes())?;
+        file.write_all(format!("p cnf {} {}\n", self.num_vars.saturating_sub(1), self.num_clauses).as_bytes())?;
         
         // Write each clause
-        let mut i = 0;
         for clause in &self.clause_table {
-            if i == self.num_clauses-1{
-                break;
-            }
-            i += 1;
-            for (term, _) in clause {
+            for (term, _) in clause.iter().filter(|(term, _)| term.var != 0) {  // var 0 is always false padding, and a 0 would end the clause early
                 file.write_all(format!("{} ", if term.negated { -(term.var as i32) } else { term.var as i32 }).as_bytes())?;
             }
             file.write_all(b"0\n")?;
@@ -155,13 +398,460 @@ impl ClauseTable {
         Ok(())
     }
 
+    /// Forgets every assignment by setting all terms back to `Symbolic`
+    pub fn reset_to_symbolic(&mut self) {
+        for clause in self.clause_table.iter_mut() {
+            for (_, state) in clause.iter_mut() {
+                *state = TermState::Symbolic;
+            }
+        }
+    }
+
+    /// Applies a full assignment vector (indexed by variable), going through the transpose so only the terms of
+    /// assigned variables are touched. Terms of unassigned (`None`) variables are left as they are.
+    pub fn apply_assignment(&mut self, assignments: &[Option<bool>]) {
+        for (locations, value) in self.transpose.iter().zip(assignments) {
+            let Some(value) = value else { continue };
+            for &TermLoc { clause, term } in locations {
+                let (t, state) = &mut self.clause_table[clause][term];
+                *state = if *value != t.negated { TermState::True } else { TermState::False };
+            }
+        }
+    }
+
+    /// Packs the state of every clause into a bitset, see `ClauseStateBitset`
+    pub fn to_bitset_representation(&self) -> Vec<ClauseStateBitset> {
+        self.clause_table.iter().map(|clause| encode_clause_state(&clause.map(|(_, state)| state))).collect()
+    }
+
+    /// Whether the clause is unit (one Symbolic term, nothing True) using the bitset checks
+    pub fn check_unit_bitset(&self, clause_idx: usize) -> bool {
+        bitset_is_unit(encode_clause_state(&self.clause_table[clause_idx].map(|(_, state)| state)))
+    }
+
+    /// Whether every term of the clause is False using the bitset checks
+    pub fn check_unsat_bitset(&self, clause_idx: usize) -> bool {
+        bitset_is_unsat(encode_clause_state(&self.clause_table[clause_idx].map(|(_, state)| state)))
+    }
+
+    /// True if every clause has a term made true by `assignments` (indexed by variable, None = unassigned)
+    pub fn is_satisfied_by(&self, assignments: &[Option<bool>]) -> bool {
+        self.clause_table.iter().all(|clause| {
+            clause.iter().any(|(term, _)| matches!(assignments.get(term.var as usize), Some(Some(value)) if *value != term.negated))
+        })
+    }
+
+    /// True if every clause contains a variable and its negation (or there are no clauses), so any assignment satisfies the table
+    pub fn is_trivially_satisfied(&self) -> bool {
+        self.clause_table.iter().all(|clause| {
+            clause.iter().any(|(a, _)| a.var != 0 && clause.iter().any(|(b, _)| b.var == a.var && b.negated != a.negated))
+        })
+    }
+
+    /// Checks that the terms of each variable agree on its value: the True/False ones must all give it the same value,
+    /// Symbolic ones are allowed anywhere because a scan updates a variable clause by clause. Catches corrupted
+    /// TermStates without knowing the assignment, except while a backtrack is being scanned, when the flipped
+    /// variable has both values until the scan is done.
+    pub fn verify_consistency(&self) -> Result<(), ConsistencyError> {
+        let mut values: Vec<Option<(bool, TermLoc)>> = vec![None; self.num_vars];
+        for (clause, terms) in self.clause_table.iter().enumerate() {
+            for (term, (t, state)) in terms.iter().enumerate() {
+                let value = match state {
+                    TermState::True => !t.negated,
+                    TermState::False => t.negated,
+                    TermState::Symbolic => continue,
+                };
+                let location = TermLoc { clause, term };
+                match values[t.var as usize] {
+                    None => values[t.var as usize] = Some((value, location)),
+                    Some((seen, first)) if seen != value => return Err(ConsistencyError { var: t.var, location, value, first }),
+                    Some(_) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Renumbers the variables so the members of each community (see `community::detect_communities`) are contiguous.
+    /// Communities are laid out smallest first so nodes, which branch on the lowest unassigned variable, finish
+    /// the smallest community first. Variable 0 stays the padding. Returns the new id of every old variable.
+    pub fn reorder_by_community(&mut self, communities: Vec<usize>) -> Vec<VarId> {
+        assert!(communities.len() == self.num_vars, "Need a community for every variable");
+        let mut sizes = std::collections::HashMap::new();
+        for &community in communities.iter().skip(1) {
+            *sizes.entry(community).or_insert(0) += 1;
+        }
+        let mut order: Vec<usize> = (1..self.num_vars).collect();
+        order.sort_by_key(|&var| (sizes[&communities[var]], communities[var], var));
+        let mut new_id = vec![0 as VarId; self.num_vars];
+        for (i, &var) in order.iter().enumerate() {
+            new_id[var] = (i + 1) as VarId;
+        }
+        for clause in self.clause_table.iter_mut() {
+            for (term, _) in clause.iter_mut() {
+                term.var = new_id[term.var as usize];
+            }
+        }
+        self.transpose = Arc::new(Self::transpose_of(&self.clause_table, self.num_vars));
+        new_id
+    }
+
+    /// Every term of `var`, in table order
+    pub fn terms_for_var(&self, var: VarId) -> Vec<TermLoc> {
+        self.transpose.get(var as usize).cloned().unwrap_or_default()
+    }
+
+    /// The distinct clauses `var` appears in (either polarity), in table order
+    pub fn clauses_for_var(&self, var: VarId) -> Vec<ClauseIdx> {
+        let mut clauses: Vec<ClauseIdx> = self.terms_for_var(var).into_iter().map(|loc| loc.clause).collect();
+        clauses.dedup();  // terms come in table order so repeats are adjacent
+        clauses
+    }
+
     pub fn number_of_vars(&self) -> usize {
-        self.clause_table.iter().map(|c| c.iter().map(|(t, _)| t.var).max().unwrap()).max().unwrap() as usize
+        self.clause_table.iter().flat_map(|c| c.iter().map(|(t, _)| t.var)).max().unwrap_or(0) as usize
     }
 }
 
-impl Clone for ClauseTable {
+impl<const K: usize> Clone for ClauseTable<K> {
     fn clone(&self) -> Self {
-        Self { clause_table: self.clause_table.clone(), num_clauses: self.num_clauses, num_vars: self.num_vars }
+        Self { clause_table: self.clause_table.clone(), num_clauses: self.num_clauses, num_vars: self.num_vars, has_empty_clause: self.has_empty_clause, transpose: self.transpose.clone() }
+    }
+}
+// serde cannot derive arrays of a generic length, so the rows go through slices (like CircularBuffer)
+type SerializedTable = (Vec<Vec<(Term, TermState)>>, usize, usize, bool);  // rows, num_clauses, num_vars, has_empty_clause
+impl<const K: usize> Serialize for ClauseTable<K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let rows: Vec<&[(Term, TermState)]> = self.clause_table.iter().map(|clause| clause.as_slice()).collect();
+        (rows, self.num_clauses, self.num_vars, self.has_empty_clause).serialize(serializer)
+    }
+}
+impl<'de, const K: usize> Deserialize<'de> for ClauseTable<K> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (rows, _, num_vars, has_empty_clause): SerializedTable = Deserialize::deserialize(deserializer)?;  // num_clauses is the number of rows
+        let clause_table = rows.into_iter()
+            .map(|row| row.try_into().map_err(|_| D::Error::custom("clause width does not match the table")))
+            .collect::<Result<_, _>>()?;
+        Ok(ClauseTable::with_clauses(clause_table, num_vars, has_empty_clause))
+    }
+}
+/// Whether a clause (DIMACS literals) contains a literal and its negation, so it is always satisfied
+pub fn is_tautology(clause: &[i32]) -> bool {
+    clause.iter().any(|&lit| clause.contains(&-lit))
+}
+
+/// Resolves two clauses (DIMACS literals) on `pivot`, which has to be in `c1` with its negation in `c2`.
+/// Returns the union of the remaining literals without duplicates, or None if the resolvent is a tautology.
+/// Resolvents can be longer than CLAUSE_LENGTH, so they are kept as literals instead of table rows.
+pub fn resolve(c1: &[i32], c2: &[i32], pivot: i32) -> Option<Vec<i32>> {
+    assert!(c1.contains(&pivot) && c2.contains(&-pivot), "Clauses {:?} and {:?} do not clash on {}", c1, c2, pivot);
+    let mut resolvent: Vec<i32> = Vec::with_capacity(c1.len() + c2.len() - 2);
+    for &lit in c1.iter().filter(|&&lit| lit != pivot).chain(c2.iter().filter(|&&lit| lit != -pivot)) {
+        if !resolvent.contains(&lit) {
+            resolvent.push(lit);
+        }
+    }
+    if is_tautology(&resolvent) { None } else { Some(resolvent) }
+}
+
+/// Structural statistics of a formula, logged next to each test to characterize the benchmarks
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProblemDescription {
+    pub num_vars: usize,              // without the padding var 0
+    pub num_clauses: usize,
+    pub clause_lengths: Vec<usize>,   // number of clauses with each count of real (non-padding) literals, 0..=K
+    pub var_degrees: Vec<usize>,      // number of variables appearing in each count of clauses
+}
+/// Minimum, median (lower) and maximum of the values counted by a histogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Percentiles {
+    pub min: usize,
+    pub median: usize,
+    pub max: usize,
+}
+impl ProblemDescription {
+    pub fn from_table<const K: usize>(table: &ClauseTable<K>) -> Self {
+        let mut clause_lengths = vec![0; K + 1];
+        let mut degrees = vec![0; table.num_vars];
+        for clause in table.clause_table.iter() {
+            let mut vars: Vec<VarId> = clause.iter().map(|(term, _)| term.var).filter(|&var| var != 0).collect();
+            clause_lengths[vars.len()] += 1;
+            vars.sort();
+            vars.dedup();  // x | x | y still only counts once towards the degree of x
+            for var in vars {
+                degrees[var as usize] += 1;
+            }
+        }
+        let mut var_degrees = vec![0; degrees.iter().skip(1).max().map_or(0, |&d| d + 1)];
+        for &degree in degrees.iter().skip(1) {
+            var_degrees[degree] += 1;
+        }
+        ProblemDescription { num_vars: table.num_vars.saturating_sub(1), num_clauses: table.num_clauses, clause_lengths, var_degrees }
+    }
+
+    pub fn from_path(path: PathBuf) -> Result<Self, ParseError> {
+        let (table, _): (ClauseTable, bool) = ClauseTable::load_file(path)?;
+        Ok(Self::from_table(&table))
+    }
+
+    pub fn clause_length_percentiles(&self) -> Percentiles {
+        Self::percentiles(&self.clause_lengths)
+    }
+
+    pub fn var_degree_percentiles(&self) -> Percentiles {
+        Self::percentiles(&self.var_degrees)
+    }
+
+    /// Percentiles of a histogram where `histogram[v]` counts the occurrences of value v (all 0 if it is empty)
+    fn percentiles(histogram: &[usize]) -> Percentiles {
+        let total: usize = histogram.iter().sum();
+        let values = || histogram.iter().enumerate().filter(|(_, &count)| count > 0).map(|(value, _)| value);
+        let mut seen = 0;
+        let median = histogram.iter().position(|&count| {
+            seen += count;
+            total > 0 && seen > (total - 1) / 2
+        }).unwrap_or(0);
+        Percentiles { min: values().next().unwrap_or(0), median, max: values().last().unwrap_or(0) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use rustsat::types::Lit;
+
+    /// Writes `contents` to a file of its own in the temp directory, `name` has to be unique among the tests
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sat_swarm_{}_{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn parse(dimacs: &str) -> Result<ClauseTable, ParseError> {
+        ClauseTable::from_dimacs_str(dimacs)
+    }
+
+    #[test]
+    fn missing_file_is_an_io_error() {
+        let missing = std::env::temp_dir().join(format!("sat_swarm_{}_missing.cnf", std::process::id()));
+        assert!(matches!(ClauseTable::<3>::load_file(missing), Err(ParseError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn strings_parse_like_files() {
+        let dimacs = "c comment\np cnf 4 3\n1 -2 3 0\n-1 4 0\n2 -3\n-4 0\n";
+        let (from_file, _): (ClauseTable, bool) = ClauseTable::load_file(temp_file("strings_parse_like_files.cnf", dimacs)).unwrap();
+        let from_str: ClauseTable = parse(dimacs).unwrap();
+        assert_eq!(from_str.clause_table, from_file.clause_table);
+        assert_eq!((from_str.num_clauses, from_str.num_vars, from_str.has_empty_clause), (from_file.num_clauses, from_file.num_vars, from_file.has_empty_clause));
+        assert!(matches!(parse("1 -2 3 0\n"), Err(ParseError::BadHeader(_))));
+    }
+
+    #[test]
+    fn sat_instances_convert_like_their_dimacs() {
+        let dimacs = "p cnf 5 3\n1 -2 3 0\n-1 5 0\n-3 -4 -5 0\n";
+        let from_dimacs: ClauseTable = parse(dimacs).unwrap();
+        let from_instance: ClauseTable = ClauseTable::from_sat_instance(&SatInstance::from_dimacs(&mut dimacs.as_bytes()).unwrap()).unwrap();
+        assert_eq!(from_instance.clause_table, from_dimacs.clause_table);
+        assert_eq!((from_instance.num_clauses, from_instance.num_vars, from_instance.has_empty_clause), (from_dimacs.num_clauses, from_dimacs.num_vars, from_dimacs.has_empty_clause));
+        let mut built = SatInstance::new();
+        for clause in [vec![(0, false), (1, true), (2, false)], vec![(0, true), (4, false)], vec![(2, true), (3, true), (4, true)]] {
+            built.add_clause(clause.into_iter().map(|(var, negated)| Lit::new(var, negated)).collect());
+        }
+        assert_eq!(ClauseTable::<3>::from_sat_instance(&built).unwrap().clause_table, from_dimacs.clause_table);  // rustsat var i is DIMACS var i + 1
+        let too_long = SatInstance::from_dimacs(&mut "p cnf 4 2\n1 2 0\n1 -2 3 -4 0\n".as_bytes()).unwrap();
+        assert!(matches!(ClauseTable::<3>::from_sat_instance(&too_long), Err(ParseError::ClauseTooLong { line: 2, width: 3 })));
+    }
+
+    #[test]
+    fn bad_headers() {
+        assert!(matches!(parse("p cnf three 1\n1 2 3 0\n"), Err(ParseError::BadHeader(_))));
+        assert!(matches!(parse("1 2 3 0\n"), Err(ParseError::BadHeader(header)) if header == "missing p cnf line"));
+        assert!(matches!(parse("p cnf 3 2\n1 2 3 0\n"), Err(ParseError::BadHeader(_))), "fewer clauses than declared");
+        assert!(matches!(parse("p cnf 2 1\n1 2 3 0\n"), Err(ParseError::BadHeader(_))), "highest variable above the declared count");
+        assert_eq!(parse("p cnf 4 1\n1 2 3 0\n").unwrap().num_vars, 5, "declared variables no clause uses are free");  // var 0 is padding
+        assert!(matches!(parse("p cnf -5 0\n"), Err(ParseError::BadHeader(_))), "negative variable count");
+        assert!(matches!(parse("p cnf -1 0\n"), Err(ParseError::BadHeader(_))));
+        let negative = temp_file("negative.wcnf", "p wcnf -3 1 10\n10 1 -2 3 0\n");
+        assert!(matches!(ClauseTable::<3>::load_wcnf(negative), Err(ParseError::BadHeader(_))));
+    }
+
+    #[test]
+    fn bad_literals() {
+        assert!(matches!(parse("p cnf 3 1\n1 x 3 0\n"), Err(ParseError::BadLiteral { line: 2, token }) if token == "x"));
+        assert!(matches!(parse("c comment\np cnf 3 1\n1 2 0 3\n"), Err(ParseError::BadLiteral { line: 3, token }) if token == "3"), "literal after the closing 0");
+    }
+
+    #[test]
+    fn unterminated_clauses() {
+        assert!(matches!(parse("p cnf 3 2\n1 2 0\n-1 3\n"), Err(ParseError::UnterminatedClause { line: 3 })), "last clause without its 0");
+        assert!(matches!(parse("p cnf 3 2\n1 2 0\n-1\n3\n"), Err(ParseError::UnterminatedClause { line: 3 })));
+        // a clause may go on over several lines, only the end of the file can leave one open
+        let table = parse("p cnf 3 2\n1 2\n-3 0\n\n-1\n0\n").unwrap();
+        assert_eq!(table.clause_table, parse("p cnf 3 2\n1 2 -3 0\n-1 0\n").unwrap().clause_table);
+        assert!(!table.has_empty_clause);
+    }
+
+    #[test]
+    fn vars_out_of_range() {
+        assert!(matches!(parse("p cnf 300 1\n1 2 3 0\n"), Err(ParseError::VarOutOfRange { line: 1, var: 300 })));
+        assert!(matches!(parse("p cnf 3 1\n1 -256 3 0\n"), Err(ParseError::VarOutOfRange { line: 2, var: 256 })));
+    }
+
+    #[test]
+    fn clause_too_long() {
+        assert!(matches!(parse("p cnf 4 1\n1 2 3 4 0\n"), Err(ParseError::ClauseTooLong { line: 2, width: 3 })));
+    }
+
+    #[test]
+    fn var_counts_leave_out_the_padding_var() {
+        let table: ClauseTable = ClauseTable::from_dimacs_str("p cnf 5 2\n1 -2 3 0\n-4 5 0\n").unwrap();
+        assert_eq!((table.num_vars, table.number_of_vars()), (6, 5));
+        assert_eq!(table.clause_table[1][2].0.var, 0, "short clauses are padded with var 0");
+        let mut written = Vec::new();
+        table.write_file(&mut written).unwrap();
+        assert!(String::from_utf8(written).unwrap().contains("p cnf 5 2\n"));
+        let built: ClauseTable = ClauseTable::from_clauses(5, &[vec![1, -2, 3], vec![-4, 5]]);
+        assert_eq!((built.num_vars, built.number_of_vars()), (6, 5));
+        assert_eq!(built.clause_table, table.clause_table);
+    }
+
+    #[test]
+    fn tables_load_files_of_their_width() {
+        let three = temp_file("width3.cnf", "p cnf 4 2\n1 -2 3 0\n-1 4 0\n");
+        let (table, _) = ClauseTable::<3>::load_file(three).unwrap();
+        assert_eq!((table.num_clauses, table.clause_table[1][2].0.var), (2, 0));
+        let four = temp_file("width4.cnf", "p cnf 4 2\n1 -2 3 -4 0\n-1 4 0\n");
+        let (table, _) = ClauseTable::<4>::load_file(four).unwrap();
+        assert_eq!(table.clause_table[0].map(|(term, _)| term), [1, 2, 3, 4].map(|var| Term { var, negated: var % 2 == 0 }));
+        let hard = temp_file("width4.wcnf", "p wcnf 4 3 10\n10 1 -2 3 -4 0\n3 1 2 0\n10 -1 4 0\n");
+        assert_eq!(ClauseTable::<4>::load_wcnf(hard).unwrap().num_clauses, 2);
+        let hard = temp_file("width3.wcnf", "h 1 -2 3 0\n5 1 0\n");
+        assert_eq!(ClauseTable::<3>::load_wcnf(hard).unwrap().num_clauses, 1);
+    }
+
+    #[test]
+    fn wcnf_files_keep_only_their_hard_clauses() {
+        let wcnf = "c partial MaxSAT\np wcnf 5 5 10\n10 1 -2 3 0\n9 -1 -2 -3 0\n12 -3 4 0\n1 5 0\nh 2 -4 0\n";
+        let hard: ClauseTable = ClauseTable::load_wcnf(temp_file("hard_and_soft.wcnf", wcnf)).unwrap();
+        let expected = parse("p cnf 4 3\n1 -2 3 0\n-3 4 0\n2 -4 0\n").unwrap();
+        assert_eq!(hard.clause_table, expected.clause_table);
+        assert_eq!((hard.num_clauses, hard.num_vars, hard.has_empty_clause), (3, 6, false));  // the header still counts var 5 of the soft clause
+        let headerless: ClauseTable = ClauseTable::load_wcnf(temp_file("headerless.wcnf", "h 1 -2 3 0\n7 -1 -2 -3 0\n")).unwrap();
+        assert_eq!(headerless.clause_table, parse("p cnf 3 1\n1 -2 3 0\n").unwrap().clause_table);  // without a top weight only h is hard
+    }
+
+    #[test]
+    fn descriptions_count_clause_lengths_and_var_degrees() {
+        // var 1 is in three clauses (once despite the repeat), 2 and 3 in two, 4 in one and 5 in none
+        let table: ClauseTable = ClauseTable::from_clauses(5, &[vec![1, -2, 3], vec![-1, 2], vec![1, 1, 4], vec![-3]]);
+        let description = ProblemDescription::from_table(&table);
+        assert_eq!((description.num_vars, description.num_clauses), (5, 4));
+        assert_eq!(description.clause_lengths, vec![0, 1, 1, 2]);
+        assert_eq!(description.var_degrees, vec![1, 1, 2, 1]);
+        assert_eq!(description.clause_length_percentiles(), Percentiles { min: 1, median: 2, max: 3 });
+        assert_eq!(description.var_degree_percentiles(), Percentiles { min: 0, median: 2, max: 3 });
+        assert_eq!(ProblemDescription::percentiles(&[0, 0]), Percentiles { min: 0, median: 0, max: 0 });
+    }
+
+    #[test]
+    fn narrower_files_are_padded() {
+        let three = temp_file("narrow.cnf", "p cnf 3 1\n1 -2 3 0\n");
+        let (table, _) = ClauseTable::<4>::load_file(three).unwrap();
+        assert_eq!(table.clause_table, ClauseTable::<4>::from_clauses(3, &[vec![1, -2, 3]]).clause_table);
+        assert_eq!(table.clause_table[0][3].0.var, 0);
+        let hard = temp_file("narrow.wcnf", "p wcnf 3 2 10\n10 1 -2 3 0\n1 1 2 3 -1 0\n");
+        assert_eq!(ClauseTable::<4>::load_wcnf(hard).unwrap().clause_table, table.clause_table);
+        // a file of only short clauses is still a formula, even when one of them is empty
+        let empty = ClauseTable::<3>::from_dimacs_str("p cnf 1 2\n1 0\n0\n").unwrap();
+        assert_eq!((empty.num_clauses, empty.has_empty_clause), (2, true));
+        assert_eq!(empty.clause_table[1].map(|(term, _)| term.var), [0; 3]);
+        let hard = temp_file("empty.wcnf", "h 1 0\nh 0\n");
+        assert!(ClauseTable::<3>::load_wcnf(hard).unwrap().has_empty_clause);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn apply_assignment_sets_the_terms_of_assigned_vars() {
+        let mut table: ClauseTable = ClauseTable::random_with_rng(430, 100, &mut StdRng::seed_from_u64(0));
+        let assignment: Vec<Option<bool>> = (0..=100).map(|var| (var % 4 != 1).then_some(var % 3 == 0)).collect();
+        let expected = |table: &ClauseTable| -> Vec<Vec<TermState>> {
+            table.clause_table.iter().map(|clause| clause.iter().map(|(term, _)| match assignment[term.var as usize] {
+                Some(value) if value != term.negated => TermState::True,
+                Some(_) => TermState::False,
+                None => TermState::Symbolic,
+            }).collect()).collect()
+        };
+        let states = |table: &ClauseTable| -> Vec<Vec<TermState>> {
+            table.clause_table.iter().map(|clause| clause.iter().map(|(_, state)| *state).collect()).collect()
+        };
+        table.apply_assignment(&assignment);
+        assert_eq!(states(&table), expected(&table));
+        table.reset_to_symbolic();
+        assert!(table.clause_table.iter().flatten().all(|(_, state)| *state == TermState::Symbolic));
+        // renumbering the variables has to renumber the transpose with them
+        let communities = (0..=100).map(|var| var % 2).collect();
+        table.reorder_by_community(communities);
+        table.apply_assignment(&assignment);
+        assert_eq!(states(&table), expected(&table));
+    }
+
+    #[test]
+    fn var_lookups_match_the_clauses_of_the_file() {
+        let clauses: [Vec<i32>; 4] = [vec![1, -2, 3], vec![-1, 2], vec![2, -3, 4], vec![1, 1, -4]];
+        let table: ClauseTable = parse("p cnf 4 4\n1 -2 3 0\n-1 2 0\n2 -3 4 0\n1 1 -4 0\n").unwrap();
+        for var in 1..=4 {
+            let expected_terms: Vec<TermLoc> = clauses.iter().enumerate()
+                .flat_map(|(clause, literals)| literals.iter().enumerate().filter(|(_, lit)| lit.unsigned_abs() == var as u32).map(move |(term, _)| TermLoc { clause, term }))
+                .collect();
+            let mut expected_clauses: Vec<ClauseIdx> = expected_terms.iter().map(|loc| loc.clause).collect();
+            expected_clauses.dedup();
+            assert_eq!(table.terms_for_var(var), expected_terms, "terms of {}", var);
+            assert_eq!(table.clauses_for_var(var), expected_clauses, "clauses of {}", var);
+        }
+        assert_eq!(table.clauses_for_var(1), vec![0, 1, 3], "clause 3 holds 1 twice but counts once");
+        assert!(table.terms_for_var(9).is_empty() && table.clauses_for_var(9).is_empty());
+    }
+
+    #[test]
+    fn bitsets_find_unit_and_unsat_clauses() {
+        assert_eq!(encode_clause_state(&[TermState::False, TermState::True, TermState::Symbolic]), 0b10_01_00);
+        let mut table: ClauseTable = ClauseTable::from_clauses(5, &[vec![1, 2, 3], vec![1, -2, 3], vec![1, -2, -4], vec![-1, 3, 4], vec![3, 5, 1]]);
+        table.apply_assignment(&[None, Some(false), Some(true), None, Some(true), None]);
+        // satisfied, unit on 3, all false, satisfied, two symbolic terms left
+        let unit: Vec<bool> = (0..5).map(|clause| table.check_unit_bitset(clause)).collect();
+        let unsat: Vec<bool> = (0..5).map(|clause| table.check_unsat_bitset(clause)).collect();
+        assert_eq!(unit, vec![false, true, false, false, false]);
+        assert_eq!(unsat, vec![false, false, true, false, false]);
+        let bitsets = table.to_bitset_representation();
+        assert_eq!(bitsets.len(), 5);
+        assert_eq!(bitsets[1], 0b10_00_00);
+        assert_eq!(bitsets[2], 0);
+        assert!(!bitset_is_unit(encode_clause_state(&[TermState::Symbolic; 3])) && !bitset_is_unsat(encode_clause_state(&[TermState::Symbolic; 3])));
+    }
+
+    #[test]
+    fn corrupted_terms_break_consistency() {
+        let mut table: ClauseTable = ClauseTable::from_clauses(3, &[vec![1, 2, 3], vec![-1, 2, -3]]);
+        assert_eq!(table.verify_consistency(), Ok(()));
+        table.apply_assignment(&[None, Some(true), None, Some(false)]);
+        assert_eq!(table.verify_consistency(), Ok(()));
+        // -1 set true says var 1 is false, but clause 0 already has it true
+        table.clause_table[1][0].1 = TermState::True;
+        let error = table.verify_consistency().unwrap_err();
+        assert_eq!(error, ConsistencyError { var: 1, location: TermLoc { clause: 1, term: 0 }, value: false, first: TermLoc { clause: 0, term: 0 } });
+        assert_eq!(error.to_string(), "var 1 is false in clause 1 term 0 but true in clause 0 term 0");
+        table.clause_table[1][0].1 = TermState::Symbolic;
+        assert_eq!(table.verify_consistency(), Ok(()), "a term the scan has not reached yet is not a disagreement");
+    }
+
+    #[test]
+    fn resolution_drops_the_pivot_and_rejects_tautologies() {
+        // (A or B) and (-A or C) on A
+        assert_eq!(resolve(&[1, 2], &[-1, 3], 1), Some(vec![2, 3]));
+        assert_eq!(resolve(&[1, 2, 3], &[-1, 2], 1), Some(vec![2, 3]), "shared literals appear once");
+        assert_eq!(resolve(&[1, 2], &[-1, -2], 1), None, "B and -B survive the pivot");
+        assert!(is_tautology(&[1, -1, 2]));
+        assert!(!is_tautology(&[1, 2, -3]));
+    }
+}
@@ -0,0 +1,230 @@
+/*
+Community detection on the variable interaction graph of a SAT problem.
+
+Two variables share an edge if they appear in the same clause. Industrial formulas tend to split into
+groups of variables that interact heavily with each other and rarely with the rest; finding these groups
+lets us renumber the variables so each group is contiguous (see `ClauseTable::reorder_by_community`).
+*/
+
+use std::collections::HashMap;
+
+use super::{clause_table::ClauseTable, util_types::VarId};
+
+/// Weighted adjacency of the variable interaction graph, indexed by variable.
+/// Every clause of n distinct variables adds `pair_weight(n)` to each of its edges.
+fn interaction_graph<const K: usize>(table: &ClauseTable<K>, pair_weight: fn(usize) -> f64) -> Vec<HashMap<usize, f64>> {
+    let mut graph = vec![HashMap::new(); table.num_vars];
+    for clause in table.clause_table.iter() {
+        let mut vars: Vec<usize> = clause.iter().map(|(term, _)| term.var as usize).filter(|&var| var != 0).collect();  // var 0 is padding
+        vars.sort();
+        vars.dedup();
+        if vars.len() < 2 {
+            continue;  // unit clauses link nothing
+        }
+        let weight = pair_weight(vars.len());
+        for (i, &a) in vars.iter().enumerate() {
+            for &b in vars.iter().skip(i + 1) {
+                *graph[a].entry(b).or_insert(0.0) += weight;
+                *graph[b].entry(a).or_insert(0.0) += weight;
+            }
+        }
+    }
+    graph
+}
+
+/// Adjacency list of the variable interaction graph, indexed by variable and sorted.
+/// Var 0 (the padding) never gets an edge.
+pub fn build_vig<const K: usize>(table: &ClauseTable<K>) -> Vec<Vec<VarId>> {
+    build_vig_weighted(table).into_iter().map(|edges| edges.into_iter().map(|(var, _)| var).collect()).collect()
+}
+
+/// Like `build_vig`, with every clause of n distinct variables adding 1/(n-1) to each of its edges, so wide
+/// clauses do not dominate the weights. Repeated pairs (from several clauses) are merged by summing.
+pub fn build_vig_weighted<const K: usize>(table: &ClauseTable<K>) -> Vec<Vec<(VarId, f64)>> {
+    interaction_graph(table, |n| 1.0 / (n - 1) as f64).into_iter().map(|edges| {
+        let mut edges: Vec<(VarId, f64)> = edges.into_iter().map(|(var, weight)| (var as VarId, weight)).collect();
+        edges.sort_by_key(|&(var, _)| var);
+        edges
+    }).collect()
+}
+
+/// Shape of a variable interaction graph; degrees are over variables 1.. (the padding var 0 is left out)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VigStats {
+    pub num_edges: usize,
+    pub avg_degree: f64,
+    pub max_degree: usize,
+    pub clustering_coefficient: f64,  // mean local clustering of the variables with at least 2 neighbors
+}
+
+pub fn vig_stats(vig: &[Vec<VarId>]) -> VigStats {
+    let vars = vig.iter().skip(1);
+    let num_vars = vars.len();
+    let total_degree: usize = vars.clone().map(|edges| edges.len()).sum();
+    let mut clustering = Vec::new();
+    for edges in vars.clone().filter(|edges| edges.len() >= 2) {
+        let mut triangles = 0;
+        for (i, &a) in edges.iter().enumerate() {
+            triangles += edges.iter().skip(i + 1).filter(|b| vig[a as usize].binary_search(b).is_ok()).count();
+        }
+        let pairs = edges.len() * (edges.len() - 1) / 2;
+        clustering.push(triangles as f64 / pairs as f64);
+    }
+    VigStats {
+        num_edges: total_degree / 2,
+        avg_degree: if num_vars == 0 { 0.0 } else { total_degree as f64 / num_vars as f64 },
+        max_degree: vars.map(|edges| edges.len()).max().unwrap_or(0),
+        clustering_coefficient: if clustering.is_empty() { 0.0 } else { clustering.iter().sum::<f64>() / clustering.len() as f64 },
+    }
+}
+
+/// Returns a community id (0..k) for every variable using the Louvain method on the variable interaction graph.
+/// Variables that never interact with another variable (including the padding var 0) end up alone in their community.
+pub fn detect_communities<const K: usize>(table: &ClauseTable<K>) -> Vec<usize> {
+    let mut graph = interaction_graph(table, |_| 1.0);  // an edge weighs the number of clauses both variables share
+    let mut membership: Vec<usize> = (0..graph.len()).collect();  // community of every original variable
+    loop {
+        let communities = local_moving(&graph);
+        let num_communities = communities.iter().max().map_or(0, |&c| c + 1);
+        for community in membership.iter_mut() {
+            *community = communities[*community];
+        }
+        if num_communities == graph.len() {
+            break;  // no node moved so the partition is final
+        }
+        graph = aggregate(&graph, &communities, num_communities);
+    }
+    membership
+}
+
+/// One Louvain level: greedily moves each node to the neighboring community with the best modularity gain
+/// until nothing moves. Returns the community of each node renumbered to 0..k.
+fn local_moving(graph: &Vec<HashMap<usize, f64>>) -> Vec<usize> {
+    let n = graph.len();
+    let degree: Vec<f64> = graph.iter().map(|edges| edges.values().sum()).collect();
+    let total_weight: f64 = degree.iter().sum();  // 2m
+    let mut community: Vec<usize> = (0..n).collect();
+    if total_weight == 0.0 {
+        return community;
+    }
+    let mut community_degree = degree.clone();
+    let mut moved = true;
+    while moved {
+        moved = false;
+        for node in 0..n {
+            let current = community[node];
+            community_degree[current] -= degree[node];
+            let mut links: HashMap<usize, f64> = HashMap::new();
+            for (&neighbor, &weight) in graph[node].iter() {
+                if neighbor != node {
+                    *links.entry(community[neighbor]).or_insert(0.0) += weight;
+                }
+            }
+            let gain = |c: usize, weight: f64| weight - community_degree[c] * degree[node] / total_weight;
+            let mut best = current;
+            let mut best_gain = gain(current, *links.get(&current).unwrap_or(&0.0));
+            let mut candidates: Vec<(usize, f64)> = links.into_iter().collect();
+            candidates.sort_by_key(|&(c, _)| c);  // keep the result deterministic
+            for (c, weight) in candidates {
+                let g = gain(c, weight);
+                if g > best_gain + 1e-12 {
+                    best = c;
+                    best_gain = g;
+                }
+            }
+            community_degree[best] += degree[node];
+            if best != current {
+                community[node] = best;
+                moved = true;
+            }
+        }
+    }
+    // renumber to 0..k in order of first appearance
+    let mut ids = HashMap::new();
+    community.iter().map(|c| {
+        let next = ids.len();
+        *ids.entry(*c).or_insert(next)
+    }).collect()
+}
+
+/// Collapses every community into a single node, summing the edge weights between communities
+fn aggregate(graph: &Vec<HashMap<usize, f64>>, communities: &Vec<usize>, num_communities: usize) -> Vec<HashMap<usize, f64>> {
+    let mut aggregated = vec![HashMap::new(); num_communities];
+    for (node, edges) in graph.iter().enumerate() {
+        for (&neighbor, &weight) in edges.iter() {
+            *aggregated[communities[node]].entry(communities[neighbor]).or_insert(0.0) += weight;
+        }
+    }
+    aggregated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, seq::index::sample, Rng, SeedableRng};
+    use crate::structures::minisat::minisat_table;
+
+    /// Two independent random 3-SAT formulas of 40 clauses, one on the odd and one on the even variables of 1..=20
+    fn interleaved_sub_formulas(seed: u64) -> ClauseTable {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let clauses: Vec<Vec<i32>> = (0..80).map(|i| {
+            let parity = i % 2;
+            sample(&mut rng, 10, 3).iter().map(|index| {
+                let var = (2 * index + 1 + parity) as i32;
+                if rng.random() { -var } else { var }
+            }).collect()
+        }).collect();
+        ClauseTable::from_clauses(20, &clauses)
+    }
+
+    #[test]
+    fn independent_sub_formulas_are_separate_communities() {
+        for seed in 0..5 {
+            let communities = detect_communities(&interleaved_sub_formulas(seed));
+            assert_eq!(communities.len(), 21);
+            let odd = communities[1];
+            let even = communities[2];
+            assert_ne!(odd, even, "seed {}", seed);
+            for (var, &community) in communities.iter().enumerate().skip(1) {
+                assert_eq!(community, if var % 2 == 1 { odd } else { even }, "seed {} var {}", seed, var);
+            }
+            assert!(communities[0] != odd && communities[0] != even, "the padding var interacts with nothing");
+        }
+    }
+
+    #[test]
+    fn reordering_makes_communities_contiguous() {
+        let mut table = interleaved_sub_formulas(0);
+        let satisfiable = minisat_table(&table).0;
+        let communities = detect_communities(&table);
+        let renamed = table.reorder_by_community(communities.clone());
+        assert_eq!(renamed[0], 0, "the padding keeps its place");
+        let mut blocks: Vec<Vec<VarId>> = vec![Vec::new(); 2];
+        for var in 1..=20 {
+            blocks[(communities[var] != communities[1]) as usize].push(renamed[var]);
+        }
+        for block in blocks.iter_mut() {
+            block.sort();
+            assert!(block.windows(2).all(|pair| pair[1] == pair[0] + 1), "{:?} is not contiguous", block);
+        }
+        let after = detect_communities(&table);
+        assert!((1..=10).all(|var| after[var] == after[1]) && (11..=20).all(|var| after[var] == after[11]) && after[1] != after[11]);
+        assert_eq!(minisat_table(&table).0, satisfiable, "renaming variables keeps the formula");
+    }
+
+    #[test]
+    fn a_clause_links_every_pair_of_its_vars() {
+        let table: ClauseTable = ClauseTable::from_clauses(3, &[vec![1, 2, 3]]);
+        let vig = build_vig(&table);
+        assert_eq!(vig, vec![vec![], vec![2, 3], vec![1, 3], vec![1, 2]]);
+        assert_eq!(vig_stats(&vig), VigStats { num_edges: 3, avg_degree: 2.0, max_degree: 2, clustering_coefficient: 1.0 });
+        // (1 or 2) shares its edge with the triangle, and the unit clause (4) links nothing
+        let table: ClauseTable = ClauseTable::from_clauses(4, &[vec![1, 2, 3], vec![1, -2], vec![4]]);
+        let weighted = build_vig_weighted(&table);
+        assert_eq!(weighted[1], vec![(2, 1.5), (3, 0.5)]);
+        assert_eq!(weighted[3], vec![(1, 0.5), (2, 0.5)]);
+        assert!(weighted[0].is_empty() && weighted[4].is_empty());
+        assert_eq!(interaction_graph(&table, |_| 1.0)[1], HashMap::from([(2, 2.0), (3, 1.0)]), "communities count shared clauses");
+        assert_eq!(vig_stats(&build_vig(&table)), VigStats { num_edges: 3, avg_degree: 1.5, max_degree: 2, clustering_coefficient: 1.0 });
+    }
+}
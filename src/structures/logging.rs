@@ -0,0 +1,101 @@
+/*
+Debug output tagged with the simulation time.
+
+`sim_log!(node, clock, level, ...)` prints `[T:<clock> N:<node>] <message>` when `level` is at least the global
+`LOG_LEVEL`, so the output of different nodes can be lined up cycle by cycle. Messages about the whole swarm
+use `-` as their node. A disabled message costs one relaxed load: its arguments are never formatted. Levels below
+`STATIC_MAX_LEVEL` are checked against a constant first, so in release builds the `Trace` and `Debug` calls in the
+clause scans compile away entirely; build with `--features trace_logs` to keep them.
+*/
+
+use std::cell::RefCell;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Trace,      // every cycle: clock ticks, messages in flight, clause scans
+    Debug,      // every decision, conflict and fork
+    Info,
+    Warn,
+    Error,
+}
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trace" => Ok(LogLevel::Trace),
+            "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            _ => Err(format!("unknown log level {}", s)),
+        }
+    }
+}
+
+/// Most verbose level compiled in, whatever `LOG_LEVEL` is set to
+pub const STATIC_MAX_LEVEL: LogLevel = if cfg!(any(debug_assertions, feature = "trace_logs")) { LogLevel::Trace } else { LogLevel::Info };
+
+/// Lowest level that gets printed, stored as `LogLevel as usize`
+pub static LOG_LEVEL: AtomicUsize = AtomicUsize::new(LogLevel::Info as usize);
+
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn enabled(level: LogLevel) -> bool {
+    level as usize >= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+/// The prefix of every `sim_log!` line
+pub fn prefix(clock: impl std::fmt::Display, node: impl std::fmt::Display) -> String {
+    format!("[T:{} N:{}]", clock, node)
+}
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };  // see `capture`
+}
+
+/// Prints a `sim_log!` line, or keeps it if this thread is inside `capture`
+pub fn emit(clock: impl std::fmt::Display, node: impl std::fmt::Display, message: std::fmt::Arguments) {
+    let line = format!("{} {}", prefix(clock, node), message);
+    CAPTURED.with_borrow_mut(|captured| match captured {
+        Some(lines) => lines.push(line),
+        None => println!("{}", line),
+    });
+}
+
+/// Runs `f` and returns the `sim_log!` lines it emitted on this thread instead of printing them
+pub fn capture(f: impl FnOnce()) -> Vec<String> {
+    CAPTURED.set(Some(Vec::new()));
+    f();
+    CAPTURED.take().unwrap_or_default()
+}
+
+macro_rules! sim_log {
+    ($node:expr, $clock:expr, $level:expr, $($arg:tt)+) => {
+        // the constant check goes first so levels that are not compiled in never reach the atomic
+        if $level as usize >= $crate::structures::logging::STATIC_MAX_LEVEL as usize && $crate::structures::logging::enabled($level) {
+            $crate::structures::logging::emit($clock, $node, format_args!($($arg)+));
+        }
+    };
+}
+pub(crate) use sim_log;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_prefixed_with_the_cycle_and_node() {
+        let lines = capture(|| {
+            sim_log!(0, 0, LogLevel::Info, "Node {} activated", 0);
+            sim_log!("-", 12, LogLevel::Warn, "swarm wide");
+            sim_log!(0, 0, LogLevel::Debug, "below the default level");
+        });
+        assert_eq!(lines, ["[T:0 N:0] Node 0 activated", "[T:12 N:-] swarm wide"]);
+    }
+}
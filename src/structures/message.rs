@@ -1,21 +1,38 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 
-use super::{clause_table::{CNFState, ClauseTable}, node::SpeculativeDepth, util_types::{NodeId, VarId, DEBUG_PRINT}};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use super::{clause_table::ClauseTable, node::{ForkOrigin, SpeculativeDepth}, logging::{sim_log, LogLevel}, util_types::{NodeId, VarId, CLAUSE_LENGTH}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum MessageDestination {
     Neighbor(NodeId),
     Broadcast, 
 } 
+// the node a log line is about, see logging::sim_log
+impl std::fmt::Display for MessageDestination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageDestination::Neighbor(id) => write!(f, "{}", id),
+            MessageDestination::Broadcast => write!(f, "*"),
+        }
+    }
+}
 
-pub enum Message {
+#[derive(Serialize, Deserialize)]
+pub enum Message<const K: usize = CLAUSE_LENGTH> {
     Fork {
-        table: ClauseTable,  // CNF assignment buffer state
+        table: ClauseTable<K>,  // CNF assignment buffer state
         assigned_vars: Vec<SpeculativeDepth>,   // List of already assigned variables (later work can make this more complex)
+        byte_size: usize,                       // bytes that have to cross the link (2 per assigned variable: VarId + bool)
+        false_vars: Vec<VarId>,                 // set false before branch_var, a k-way fork sent their true sides elsewhere
+        branch_var: VarId,                      // variable the sender branched on, the receiver takes its true side
+        provenance: Vec<ForkOrigin>,            // forks that led here, ending with this one; debugging only, not in byte_size
     },
     UnfinishedMessage,
     Success,
-} impl Debug for Message {
+} impl<const K: usize> Debug for Message<K> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Message::Fork {..} => {
@@ -31,6 +48,7 @@ pub enum Message {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Watchdog {
     last_update: u64,
     timeout: u64,
@@ -91,17 +109,104 @@ struct CircularBuffer<T, const N: usize> {
         result
     }
 }
-pub struct MessageQueue {
+// serde only implements arrays up to 32 elements, so the slots go through a Vec
+impl<T: Serialize, const N: usize> Serialize for CircularBuffer<T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.buffer.as_slice(), self.head).serialize(serializer)
+    }
+}
+impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for CircularBuffer<T, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (buffer, head): (Vec<Vec<T>>, usize) = Deserialize::deserialize(deserializer)?;
+        let buffer: [Vec<T>; N] = buffer.try_into().map_err(|_| D::Error::custom("wrong number of slots in circular buffer"))?;
+        Ok(CircularBuffer { buffer, head })
+    }
+}
+const QUEUE_DEPTH: usize = 1024;
+
+/// Link latency scaled down by the share of idle nodes, so forks move faster when the network is starved for work
+pub fn compute_adaptive_delay(busy_count: usize, total_nodes: usize, base_delay: usize) -> usize {
+    if total_nodes == 0 {
+        return base_delay;
+    }
+    let idle_count = total_nodes - busy_count.min(total_nodes);
+    (base_delay - base_delay * idle_count / total_nodes).max(1)
+}
+
+/// Longest link latency that still fits a fork of `byte_size` bytes in the queue when links carry `bytes_per_cycle`
+pub fn max_link_delay(bytes_per_cycle: usize, byte_size: usize) -> usize {
+    QUEUE_DEPTH - byte_size.div_ceil(bytes_per_cycle).max(1)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MessageQueue<const K: usize = CLAUSE_LENGTH> {
     last_clock_update: u64,
-    queue: CircularBuffer<(MessageDestination, MessageDestination, Message), 64>
+    fork_delay: usize,                                  // flat link latency used when an edge has no explicit weight
+    link_delays: HashMap<(NodeId, NodeId), usize>,      // per-edge latency (keyed with the smaller id first)
+    bandwidth: Option<usize>,                           // bytes per cycle a link can carry, None = every fork fits in one cycle
+    adaptive_delay: bool,                               // shorten link latencies while nodes are idle, see compute_adaptive_delay
+    busy_nodes: (usize, usize),                         // busy and total nodes at the start of the current cycle
+    fork_delay_cycles: u64,                             // link latency summed over every fork sent
+    forks_sent: u64,
+    in_flight: usize,                                   // messages (and unfinished placeholders) queued but not delivered
+    queue: CircularBuffer<(MessageDestination, MessageDestination, Message<K>), QUEUE_DEPTH>
 }
-impl MessageQueue {
+impl<const K: usize> MessageQueue<K> {
     pub fn new() -> Self {
         MessageQueue {
             last_clock_update: 0,
+            fork_delay: 1,
+            link_delays: HashMap::new(),
+            bandwidth: None,
+            adaptive_delay: false,
+            busy_nodes: (0, 0),
+            fork_delay_cycles: 0,
+            forks_sent: 0,
+            in_flight: 0,
             queue: CircularBuffer::new(),
         }
     }
+
+    /// Sets the latency of the (undirected) link between `a` and `b`, overriding the flat fork delay
+    pub fn set_link_delay(&mut self, a: NodeId, b: NodeId, delay: usize) {
+        assert!(delay > 0 && delay < QUEUE_DEPTH, "Link delay {} out of range", delay);
+        self.link_delays.insert((a.min(b), a.max(b)), delay);
+    }
+
+    /// Latency of the link between `a` and `b`, falling back to the flat fork delay
+    pub fn link_delay(&self, a: NodeId, b: NodeId) -> usize {
+        *self.link_delays.get(&(a.min(b), a.max(b))).unwrap_or(&self.fork_delay)
+    }
+
+    /// Makes forks take time proportional to their size: `bytes_per_cycle` is the capacity of every link
+    pub fn set_bandwidth(&mut self, bytes_per_cycle: usize) {
+        assert!(bytes_per_cycle > 0, "Bandwidth must be positive");
+        self.bandwidth = Some(bytes_per_cycle);
+    }
+
+    /// Makes forks use `compute_adaptive_delay` of their link latency instead of the latency itself
+    pub fn set_adaptive_delay(&mut self, adaptive: bool) {
+        self.adaptive_delay = adaptive;
+    }
+
+    /// Tells the queue how loaded the network is, called once a cycle before any node sends
+    pub fn set_busy_nodes(&mut self, busy_count: usize, total_nodes: usize) {
+        self.busy_nodes = (busy_count, total_nodes);
+    }
+
+    /// Mean link latency of the forks sent so far (before transfer cycles), 0 if none were sent
+    pub fn avg_fork_delay(&self) -> f64 {
+        if self.forks_sent == 0 { 0.0 } else { self.fork_delay_cycles as f64 / self.forks_sent as f64 }
+    }
+
+    /// Cycles needed to push `byte_size` bytes through a link, at least 1
+    fn transfer_cycles(&self, byte_size: usize) -> usize {
+        match self.bandwidth {
+            Some(bandwidth) => byte_size.div_ceil(bandwidth).max(1),
+            None => 1,
+        }
+    }
+
     fn check_clock(&mut self, clock: u64) {
         for _ in self.last_clock_update..clock {
             self.queue.step();
@@ -109,27 +214,41 @@ impl MessageQueue {
         self.last_clock_update = clock;
     }
 
-    pub fn start_message(&mut self, clock: u64, from: MessageDestination, to: MessageDestination, message: Message) {
+    pub fn start_message(&mut self, clock: u64, from: MessageDestination, to: MessageDestination, message: Message<K>) {
         self.check_clock(clock);
-        if DEBUG_PRINT {
-            println!("Sending {:?} from {:?} to {:?}", message, from, to);
-        }
-        let delay = match message {
-            // Message::Fork {..} => (std::mem::size_of::<CNFState>() + std::mem::size_of::<VarId>() - 1) / self.bandwidth + 1,
-            // TODO: if we think that the size of the message is less than can be processed in a clock cycle we can just set the delay to 1
+        sim_log!(from, clock, LogLevel::Trace, "Sending {:?} to {}", message, to);
+        let delay = match (&message, from, to) {
+            // the first byte arrives after the link latency, the rest stream in behind it
+            (Message::Fork {byte_size, ..}, MessageDestination::Neighbor(a), MessageDestination::Neighbor(b)) => {
+                let mut latency = self.link_delay(a, b);
+                if self.adaptive_delay {
+                    latency = compute_adaptive_delay(self.busy_nodes.0, self.busy_nodes.1, latency);
+                }
+                self.fork_delay_cycles += latency as u64;
+                self.forks_sent += 1;
+                latency + self.transfer_cycles(*byte_size) - 1
+            },
             _ => 1,
         };
+        assert!(delay < QUEUE_DEPTH, "{:?} needs {} cycles to deliver but the message queue only holds {}", message, delay, QUEUE_DEPTH);
         for i in 1..delay {
             self.queue.push(i, (from, to, Message::UnfinishedMessage)); 
         }
         self.queue.push(delay, (from, to, message));  // TODO: add more realistic delays
+        self.in_flight += delay;
+    }
+
+    /// Whether a message is still on its way, e.g. a fork to a node that looks idle until it arrives
+    pub fn in_flight(&self) -> bool {
+        self.in_flight > 0
     }
 
-    pub fn pop_message(&mut self, clock: u64) -> Vec<(MessageDestination, MessageDestination, Message)> {
+    pub fn pop_message(&mut self, clock: u64) -> Vec<(MessageDestination, MessageDestination, Message<K>)> {
         self.check_clock(clock);
         let result = self.queue.pop();
-        if DEBUG_PRINT {
-            println!("Popping {:?}", result);
+        self.in_flight -= result.len();
+        for (from, to, message) in result.iter() {
+            sim_log!(to, clock, LogLevel::Trace, "Popping {:?} from {}", message, from);
         }
         return result;
     }
@@ -143,3 +262,47 @@ pub enum TermUpdate {
     False,
     Reset
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fork(byte_size: usize) -> Message {
+        Message::Fork {table: ClauseTable::from_clauses(3, &[vec![1, 2, 3]]), assigned_vars: Vec::new(), byte_size, false_vars: Vec::new(), branch_var: 1, provenance: Vec::new()}
+    }
+
+    /// Cycle a fork sent at cycle 0 over a one-cycle link arrives at
+    fn arrival(queue: &mut MessageQueue, byte_size: usize) -> u64 {
+        queue.start_message(0, MessageDestination::Neighbor(0), MessageDestination::Neighbor(1), fork(byte_size));
+        (1..QUEUE_DEPTH as u64).find(|&clock| queue.pop_message(clock).iter().any(|(_, _, message)| matches!(message, Message::Fork {..}))).unwrap()
+    }
+
+    #[test]
+    fn forks_stream_at_the_link_bandwidth() {
+        let mut queue: MessageQueue = MessageQueue::new();
+        queue.set_bandwidth(10);
+        assert_eq!(queue.transfer_cycles(100), 10, "50 assigned variables at 2 bytes each");
+        assert_eq!(queue.transfer_cycles(101), 11);
+        assert_eq!(queue.transfer_cycles(0), 1);
+        assert_eq!(arrival(&mut queue, 100), 10);
+        assert_eq!(MessageQueue::<3>::new().transfer_cycles(100), 1, "without a bandwidth every fork fits in a cycle");
+    }
+
+    #[test]
+    fn adaptive_delay_scales_with_idle_nodes() {
+        assert_eq!(compute_adaptive_delay(4, 4, 20), 20);
+        assert_eq!(compute_adaptive_delay(1, 4, 20), 5);
+        assert_eq!(compute_adaptive_delay(0, 4, 20), 1, "a link never gets faster than a cycle");
+        assert_eq!(compute_adaptive_delay(0, 0, 20), 20);
+
+        let mut queue: MessageQueue = MessageQueue::new();
+        queue.set_link_delay(0, 1, 20);
+        queue.set_adaptive_delay(true);
+        queue.set_busy_nodes(1, 4);  // 75% idle
+        queue.start_message(0, MessageDestination::Neighbor(0), MessageDestination::Neighbor(1), fork(2));
+        queue.start_message(0, MessageDestination::Neighbor(1), MessageDestination::Neighbor(0), fork(2));
+        assert_eq!(queue.avg_fork_delay(), 5.0);
+        let arrivals: Vec<usize> = (1..=5).map(|clock| queue.pop_message(clock).iter().filter(|(_, _, message)| matches!(message, Message::Fork {..})).count()).collect();
+        assert_eq!(arrivals, vec![0, 0, 0, 0, 2]);
+    }
+}
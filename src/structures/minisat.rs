@@ -1,70 +1,374 @@
 
-use std::fs::OpenOptions;
-use std::io::BufReader;
+use std::io::{BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::time::{Duration, Instant};
-use std::{path::PathBuf};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-use rustsat::solvers::Solve;
+use csv::Writer;
+use rand::{rngs::StdRng, SeedableRng};
+
+use rustsat::solvers::{Interrupt, InterruptSolver, Solve};
 use rustsat::types::{Clause, Lit};
 use rustsat::{instances::SatInstance, solvers::SolverResult};
 use rustsat_minisat::core::Minisat;
-use super::{clause_table::ClauseTable};
+use super::{clause_table::ClauseTable, util_types::CLAUSE_LENGTH};
 
 pub fn minisat_file(path: PathBuf) -> (bool, Duration) {
+    let (result, elapsed) = solve_until(file_solver(path), None);
+    (result.expect("MiniSat is only interrupted by a timeout"), elapsed)
+}
+/// `minisat_file` that gives up after `timeout`, None if it did
+pub fn minisat_file_with_timeout(path: PathBuf, timeout: Duration) -> (Option<bool>, Duration) {
+    solve_until(file_solver(path), Some(timeout))
+}
+pub fn minisat_table<const K: usize>(table: &ClauseTable<K>) -> (bool, Duration) {
+    if let Some(result) = trivial_result(table) {
+        return (result, Duration::ZERO);
+    }
+    let (result, elapsed) = solve_until(table_solver(table), None);
+    (result.expect("MiniSat is only interrupted by a timeout"), elapsed)
+}
+/// `minisat_table` that gives up after `timeout`, None if it did
+pub fn minisat_table_with_timeout<const K: usize>(table: &ClauseTable<K>, timeout: Duration) -> (Option<bool>, Duration) {
+    if let Some(result) = trivial_result(table) {
+        return (Some(result), Duration::ZERO);
+    }
+    solve_until(table_solver(table), Some(timeout))
+}
+
+/// Tables decided without a solver: an empty clause or no clauses left to satisfy
+fn trivial_result<const K: usize>(table: &ClauseTable<K>) -> Option<bool> {
+    if table.has_empty_clause {
+        Some(false)
+    } else if table.is_trivially_satisfied() {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+fn file_solver(path: PathBuf) -> Minisat {
     let file = std::fs::File::open(path).expect("Unable to open file");
     let mut reader = BufReader::new(file);
     let instance: SatInstance = SatInstance::from_dimacs(&mut reader).unwrap();
     let mut solver: Minisat = rustsat_minisat::core::Minisat::default();
     solver.add_cnf(instance.into_cnf().0).unwrap();
-    let now = Instant::now();
-    let res = solver.solve().unwrap();
-    let elapsed = now.elapsed();
-    (res == SolverResult::Sat, elapsed)
+    solver
 }
-pub fn minisat_table(table: &ClauseTable) -> (bool, Duration) {
+
+fn table_solver<const K: usize>(table: &ClauseTable<K>) -> Minisat {
     let mut instance: SatInstance = SatInstance::new();
     for clause in table.clause_table.iter() {
-        let clause: Clause = clause.iter().map(|&(x, _)| Lit::new(x.var as u32, x.negated)).collect();
+        // var 0 is padding and never true, var v is rustsat's v - 1 like in a DIMACS file
+        let clause: Clause = clause.iter().filter(|(x, _)| x.var != 0).map(|&(x, _)| Lit::new(x.var as u32 - 1, x.negated)).collect();
         instance.add_clause(clause);
     }
     let mut solver: Minisat = rustsat_minisat::core::Minisat::default();
     solver.add_cnf(instance.into_cnf().0).unwrap();
+    solver
+}
+
+/// Solves and times `solver`. With a timeout a watchdog thread interrupts MiniSat once it runs out,
+/// and the result is None; the watchdog is woken up and stopped as soon as the solve returns.
+fn solve_until(mut solver: Minisat, timeout: Option<Duration>) -> (Option<bool>, Duration) {
+    let watchdog = timeout.map(|timeout| {
+        let mut interrupter = solver.interrupter();
+        let (finished, wait) = mpsc::channel::<()>();
+        let handle = std::thread::spawn(move || {
+            if wait.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                interrupter.interrupt();
+            }
+        });
+        (finished, handle)
+    });
     let now = Instant::now();
     let res = solver.solve().unwrap();
     let elapsed = now.elapsed();
-    (res == SolverResult::Sat, elapsed)
+    if let Some((finished, handle)) = watchdog {
+        let _ = finished.send(());  // the watchdog is gone already if it fired
+        handle.join().expect("MiniSat watchdog panicked");
+    }
+    match res {
+        SolverResult::Sat => (Some(true), elapsed),
+        SolverResult::Unsat => (Some(false), elapsed),
+        SolverResult::Interrupted => (None, elapsed),
+    }
+}
+
+/// Solves the table with an external DIMACS solver such as CaDiCaL or Kissat, e.g. `cmd = "kissat -q"`.
+/// The CNF is written to the solver's stdin and the answer is read from its `s SATISFIABLE` / `s UNSATISFIABLE` line.
+/// The solver is killed if it runs longer than `timeout`.
+pub fn solve_external<const K: usize>(table: &ClauseTable<K>, cmd: &str, timeout: Duration) -> std::io::Result<(bool, Duration)> {
+    let mut parts = cmd.split_whitespace();
+    let program = parts.next().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Empty solver command"))?;
+    let now = Instant::now();
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // feed and drain the pipes on other threads so a solver that stops reading or writes a lot can't block the timeout
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output)
+    });
+    let stdin = child.stdin.take().unwrap();
+    let mut cnf = Vec::new();
+    table.write_file(&mut cnf)?;
+    std::thread::spawn(move || {
+        let mut stdin = stdin;
+        let _ = stdin.write_all(&cnf);  // a solver that exits early closes the pipe, its exit status tells us what happened
+    });  // dropping stdin closes it so the solver sees EOF
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if now.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(std::io::ErrorKind::TimedOut, format!("{} timed out after {:?}", program, timeout)));
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    };
+    let elapsed = now.elapsed();
+    let output = reader.join().expect("Solver output thread panicked")?;
+    // SAT competition solvers exit with 10 (SAT) or 20 (UNSAT), anything else besides 0 is a failure
+    if !matches!(status.code(), Some(0) | Some(10) | Some(20)) {
+        return Err(std::io::Error::new(std::io::ErrorKind::Other, format!("{} exited with {}", program, status)));
+    }
+    match output.lines().find(|line| line.starts_with("s ")).map(|line| line.trim()) {
+        Some("s SATISFIABLE") => Ok((true, elapsed)),
+        Some("s UNSATISFIABLE") => Ok((false, elapsed)),
+        line => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{} gave no answer: {:?}", program, line))),
+    }
 }
 
-pub fn build_random_testset(clauses: usize, vars: u8, sats: usize, unsats: usize) {
+/// Writes `sats` satisfiable and `unsats` unsatisfiable random instances with `vars` variables and `ratio` clauses
+/// per variable (4.26 is the hard region of random 3-SAT) to `{dir}/sat` and `{dir}/unsat`, named
+/// `{clauses}_{vars}_{i}.cnf`. The instances come from one generator seeded with `seed`, so a run with the same
+/// arguments writes the same files. With `hardness` only instances MiniSat solves within that time window are kept,
+/// which depends on the machine. Each file notes the realized ratio and the seed in `c` comments.
+pub fn build_random_testset(dir: &Path, vars: u8, ratio: f64, sats: usize, unsats: usize, hardness: Option<Range<Duration>>, seed: u64) -> std::io::Result<()> {
+    let clauses = num_clauses_at(ratio, vars as usize);
+    let comments = [
+        format!("random {}-SAT, clause/variable ratio {} ({} clauses, {} variables)", CLAUSE_LENGTH, clauses as f64 / vars as f64, clauses, vars),
+        format!("seed {}", seed),
+    ];
+    std::fs::create_dir_all(dir.join("sat"))?;
+    std::fs::create_dir_all(dir.join("unsat"))?;
+    let mut rng = StdRng::seed_from_u64(seed);
     let mut sats_made = 0;
     let mut unsats_made = 0;
-    // next file is the next file of the form tests/sat/random/{clauses}_{vars}_i.cnf
     while sats_made < sats || unsats_made < unsats {
-        let mut table = ClauseTable::random(clauses, vars);
-        
-        if minisat_table(&table).0 {
-            if sats_made < sats {
-                let file_path = format!("tests/random/sat/{}_{}_{}.cnf", clauses, vars, sats_made);
-                println!("Sat file path: {}", file_path);
-                let f = OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(file_path.clone()).unwrap();
-                let _ = table.write_file(f);
-                sats_made += 1;
+        let table: ClauseTable = ClauseTable::random_with_rng(clauses, vars, &mut rng);
+        let (result, elapsed) = match &hardness {
+            Some(window) => minisat_table_with_timeout(&table, window.end),
+            None => {
+                let (result, elapsed) = minisat_table(&table);
+                (Some(result), elapsed)
+            }
+        };
+        if hardness.as_ref().is_some_and(|window| !window.contains(&elapsed)) {
+            continue;  // too easy, or too hard (a timeout always is)
+        }
+        let (kind, made) = match result {
+            Some(true) if sats_made < sats => ("sat", &mut sats_made),
+            Some(false) if unsats_made < unsats => ("unsat", &mut unsats_made),
+            _ => continue,
+        };
+        let file_path = dir.join(kind).join(format!("{}_{}_{}.cnf", clauses, vars, made));
+        println!("{} file path: {}", if kind == "sat" { "Sat" } else { "Unsat" }, file_path.display());
+        table.write_file_with_comments(std::fs::File::create(file_path)?, &comments)?;
+        *made += 1;
+    }
+    Ok(())
+}
+
+/// Fraction of satisfiable random 3-SAT instances at each clause/variable ratio, see `phase_transition_scan`
+pub struct PhaseTransitionData {
+    pub num_vars: usize,
+    pub samples_per_point: usize,
+    pub points: Vec<(f64, f64)>,    // (ratio, sat fraction)
+} impl PhaseTransitionData {
+    /// The scanned ratio whose sat fraction is closest to 50%, where random instances are hardest
+    pub fn hardest_ratio(&self) -> Option<f64> {
+        self.points.iter()
+            .min_by(|a, b| (a.1 - 0.5).abs().total_cmp(&(b.1 - 0.5).abs()))
+            .map(|&(ratio, _)| ratio)
+    }
+
+    /// Generates `count` fresh instances at the hardest ratio to use as benchmarks
+    pub fn hardest_instances(&self, count: usize) -> Vec<ClauseTable> {
+        match self.hardest_ratio() {
+            Some(ratio) => (0..count).map(|_| ClauseTable::random(num_clauses_at(ratio, self.num_vars), self.num_vars as u8)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn write_csv(&self, path: &str) -> csv::Result<()> {
+        let mut writer = Writer::from_path(path)?;
+        writer.write_record(&["Ratio", "Sat Fraction", "Number of Variables", "Samples"])?;
+        for (ratio, fraction) in self.points.iter() {
+            writer.write_record(&[
+                ratio.to_string(),
+                fraction.to_string(),
+                self.num_vars.to_string(),
+                self.samples_per_point.to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+fn num_clauses_at(ratio: f64, num_vars: usize) -> usize {
+    (ratio * num_vars as f64).round() as usize
+}
+
+/// Solves `samples_per_point` random 3-SAT instances at `steps` evenly spaced clause/variable ratios
+/// between `ratio_lo` and `ratio_hi` (inclusive) and records how many were satisfiable.
+/// For 3-SAT the fraction drops from 1 to 0 around a ratio of 4.27.
+pub fn phase_transition_scan(num_vars: usize, ratio_lo: f64, ratio_hi: f64, steps: usize, samples_per_point: usize) -> PhaseTransitionData {
+    phase_transition_scan_with_rng(num_vars, ratio_lo, ratio_hi, steps, samples_per_point, &mut rand::rng())
+}
+
+/// `phase_transition_scan` drawing the instances from `rng`, so a seeded generator gives the same data every time
+pub fn phase_transition_scan_with_rng(num_vars: usize, ratio_lo: f64, ratio_hi: f64, steps: usize, samples_per_point: usize, rng: &mut impl rand::Rng) -> PhaseTransitionData {
+    assert!(num_vars > 0 && num_vars <= u8::MAX as usize, "ClauseTable::random supports 1 to 255 variables");
+    assert!(steps > 0 && samples_per_point > 0, "Need at least one step and one sample");
+    assert!(ratio_lo <= ratio_hi, "Ratio range is reversed");
+    let mut points = Vec::with_capacity(steps);
+    for step in 0..steps {
+        let ratio = if steps == 1 { ratio_lo } else { ratio_lo + (ratio_hi - ratio_lo) * step as f64 / (steps - 1) as f64 };
+        let num_clauses = num_clauses_at(ratio, num_vars);
+        let sat = (0..samples_per_point)
+            .filter(|_| minisat_table(&ClauseTable::<CLAUSE_LENGTH>::random_with_rng(num_clauses, num_vars as u8, rng)).0)
+            .count();
+        points.push((ratio, sat as f64 / samples_per_point as f64));
+    }
+    PhaseTransitionData { num_vars, samples_per_point, points }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::problems::CnfBuilder;
+
+    /// Executable that reads its input and prints `output` before exiting with `code`
+    fn stub_solver(name: &str, output: &str, code: i32) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!("sat_swarm_{}_{}.sh", std::process::id(), name));
+        std::fs::write(&path, format!("#!/bin/sh\ncat > /dev/null\necho '{}'\nexit {}\n", output, code)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn table() -> ClauseTable {
+        ClauseTable::from_clauses(3, &[vec![1, 2, 3]])
+    }
+
+    /// `holes + 1` pigeons in `holes` holes. Resolution needs exponentially many steps to refute it, so MiniSat
+    /// takes minutes from 11 holes on.
+    fn pigeonhole(holes: usize) -> ClauseTable {
+        let pigeons = holes + 1;
+        let var = |pigeon: usize, hole: usize| (pigeon * holes + hole + 1) as i32;
+        let mut cnf = CnfBuilder::new(pigeons * holes);
+        for pigeon in 0..pigeons {
+            cnf.add_clause((0..holes).map(|hole| var(pigeon, hole)).collect());
+        }
+        for hole in 0..holes {
+            for a in 0..pigeons {
+                for b in a + 1..pigeons {
+                    cnf.add_clause(vec![-var(a, hole), -var(b, hole)]);
+                }
             }
-        } else {
-            if unsats_made < unsats {
-                let file_path = format!("tests/random/unsat/{}_{}_{}.cnf", clauses, vars, unsats_made);
-                println!("Unsat file path: {}", file_path);
-                let f = OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(file_path.clone()).unwrap();
-                let _ = table.write_file(f);
-                unsats_made += 1;
+        }
+        cnf.build()
+    }
+
+    #[test]
+    fn minisat_gives_up_at_its_timeout() {
+        assert_eq!(minisat_table_with_timeout(&pigeonhole(3), Duration::from_secs(10)).0, Some(false));
+        let now = Instant::now();
+        let (result, _) = minisat_table_with_timeout(&pigeonhole(11), Duration::from_millis(100));
+        assert_eq!(result, None);
+        assert!(now.elapsed() < Duration::from_secs(10), "MiniSat ran {:?} past a 100 ms timeout", now.elapsed());
+    }
+
+    #[test]
+    fn random_testsets_are_written_at_the_ratio_and_repeat_per_seed() {
+        let dir = |name: &str| std::env::temp_dir().join(format!("sat_swarm_{}_{}", std::process::id(), name));
+        let (first, second) = (dir("testset_a"), dir("testset_b"));
+        build_random_testset(&first, 20, 4.26, 2, 2, None, 5).unwrap();
+        build_random_testset(&second, 20, 4.26, 2, 2, None, 5).unwrap();
+        for (kind, expected) in [("sat", true), ("unsat", false)] {
+            for i in 0..2 {
+                let name = format!("85_20_{}.cnf", i);
+                let contents = std::fs::read_to_string(first.join(kind).join(&name)).unwrap();
+                assert!(contents.contains("c random 3-SAT, clause/variable ratio 4.25 (85 clauses, 20 variables)\n"), "{}", contents);
+                assert!(contents.contains("c seed 5\n"));
+                assert_eq!(contents, std::fs::read_to_string(second.join(kind).join(&name)).unwrap(), "{} differs between runs", name);
+                let (table, _): (ClauseTable, _) = ClauseTable::load_file(first.join(kind).join(&name)).unwrap();
+                assert_eq!((table.num_clauses, table.number_of_vars()), (85, 20));
+                assert_eq!(minisat_table(&table).0, expected, "{}/{}", kind, name);
             }
+            assert_eq!(std::fs::read_dir(first.join(kind)).unwrap().count(), 2);
+        }
+        std::fs::remove_dir_all(first).unwrap();
+        std::fs::remove_dir_all(second).unwrap();
+    }
+
+    #[test]
+    fn random_3sat_turns_unsat_across_the_phase_transition() {
+        // with 50 variables about 5% of the instances at ratio 5 are still SAT, so an unseeded scan would be flaky
+        let data = phase_transition_scan_with_rng(50, 3.0, 5.0, 2, 100, &mut StdRng::seed_from_u64(2));
+        assert_eq!((data.num_vars, data.samples_per_point, data.points.len()), (50, 100, 2));
+        let [(under, under_sat), (over, over_sat)] = data.points[..] else { unreachable!() };
+        assert_eq!((under, over), (3.0, 5.0));
+        assert!(under_sat >= 0.95, "only {} of the instances at ratio 3 are SAT", under_sat);
+        assert!(over_sat < 0.05, "{} of the instances at ratio 5 are SAT", over_sat);
+    }
+
+    #[test]
+    fn hardest_ratio_is_the_one_closest_to_half_sat() {
+        let data = |points: Vec<(f64, f64)>| PhaseTransitionData { num_vars: 50, samples_per_point: 100, points };
+        assert_eq!(data(vec![(3.5, 0.98), (4.0, 0.81), (4.25, 0.56), (4.5, 0.37), (5.0, 0.03)]).hardest_ratio(), Some(4.25));
+        assert_eq!(data(vec![(4.0, 0.9), (4.5, 0.42)]).hardest_ratio(), Some(4.5));
+        assert_eq!(data(Vec::new()).hardest_ratio(), None);
+    }
+
+    #[test]
+    fn external_solvers_answer_through_their_s_line() {
+        let timeout = Duration::from_secs(10);
+        for (name, output, code, expected) in [("sat", "s SATISFIABLE", 10, true), ("unsat", "s UNSATISFIABLE", 20, false), ("zero", "s SATISFIABLE", 0, true)] {
+            let stub = stub_solver(name, output, code);
+            assert_eq!(solve_external(&table(), stub.to_str().unwrap(), timeout).unwrap().0, expected, "{}", name);
+            std::fs::remove_file(stub).unwrap();
         }
     }
-    
-}
\ No newline at end of file
+
+    #[test]
+    fn external_solver_failures_are_errors() {
+        let timeout = Duration::from_secs(10);
+        let crashed = stub_solver("crashed", "s SATISFIABLE", 1);
+        assert_eq!(solve_external(&table(), crashed.to_str().unwrap(), timeout).unwrap_err().kind(), std::io::ErrorKind::Other);
+        let silent = stub_solver("silent", "c no answer", 0);
+        assert_eq!(solve_external(&table(), silent.to_str().unwrap(), timeout).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(solve_external(&table(), "", timeout).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        assert!(solve_external(&table(), "tests/no_such_solver", timeout).is_err());
+        std::fs::remove_file(crashed).unwrap();
+        std::fs::remove_file(silent).unwrap();
+    }
+
+    #[test]
+    fn slow_external_solvers_are_killed() {
+        let now = Instant::now();
+        let error = solve_external(&table(), "sleep 5", Duration::from_millis(50)).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+        assert!(now.elapsed() < Duration::from_secs(2), "waited {:?} for the solver", now.elapsed());
+    }
+}
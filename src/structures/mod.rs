@@ -1,6 +1,9 @@
 pub mod node;
 pub mod message;
 pub mod clause_table;
+pub mod community;
+pub mod logging;
 pub mod minisat;
+pub mod problems;
 pub mod satswarm;
 pub mod util_types;
\ No newline at end of file
@@ -13,29 +13,136 @@ Message types:
 
 // use stp, fmt::Deug};
 use std::fmt::Debug;
-use crate::structures::clause_table::{Term, TermState};
-use super::{clause_table::ClauseTable, message::{Message, MessageDestination, MessageQueue, TermUpdate, Watchdog}, util_types::{NodeId, VarId, CLAUSE_LENGTH, DEBUG_PRINT}};
+use serde::{Deserialize, Serialize};
+use crate::structures::clause_table::{ClauseIdx, Term, TermState};
+use super::{clause_table::ClauseTable, message::{Message, MessageDestination, MessageQueue, TermUpdate, Watchdog}, logging::{sim_log, LogLevel}, util_types::{EnergyModel, NodeId, VarId, VerifyLevel, CLAUSE_LENGTH}};
 
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Nodes a node can fork to. A dense network links every pair, so there the neighbors are implied instead of stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum Neighborhood {
+    Explicit(Vec<NodeId>),
+    Dense(usize),   // every node in 0..n except ourselves
+}
+
+/// Why a variable was assigned, also used to attribute each busy cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AssignmentCause {
+    UnitPropagation,
+    Decision,       // speculative or forked branch, root assignments and decision stalls
+    Conflict,       // detecting a conflict and flipping the last speculation after it
+}
+
+/// One fork in the chain that handed a node its subproblem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ForkOrigin {
+    pub source: NodeId,     // node that sent the fork
+    pub clock: u64,         // cycle it was sent
+}
+
+/// Decision levels tracked by the conflict histogram
+pub const CONFLICT_LEVELS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 enum NodeState {  
     Busy,
     AwaitingFork,
     RecievingFork,
 }
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SpeculativeDepth { 
     Depth(VarId, bool), // speculative depth 0 = guarenteed, 1 = speculative, 2 = further speculation
     Unassigned
 }
+#[derive(Serialize, Deserialize)]
 struct VarUpdate {
     var_id: VarId,                                  // which variable are we updating
-    clause_index: usize,                            // which clause we are updating in
+    position: usize,                                // how far the scan is, in the order of the clause layout
     assignment: bool,                               // what is the assignment (true/false)
     reset: bool,                                    // should we reset the variables of higher depth
     // speculative: bool,                              // is this a speculative assignment
     depth: VarId,                                   // what is the depth of the assignment
+    cause: AssignmentCause,                         // why the variable was assigned
+    lazy: bool,                                     // scan for every pending assignment at once, terms are read from the assignment vector
+}
+/// How the clauses are laid out in a node's clause memory, which decides the order a scan reaches them in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ClauseLayout {
+    Sequential,                 // one memory in index order
+    Interleaved { banks: usize },   // clause i in bank i % banks, the banks are scanned one after the other
+}
+impl ClauseLayout {
+    /// Clause read at `position` of a scan over `num_clauses` clauses
+    pub fn clause_at(&self, position: usize, num_clauses: usize) -> ClauseIdx {
+        match *self {
+            ClauseLayout::Sequential => position,
+            ClauseLayout::Interleaved { banks } => {
+                // the first num_clauses % banks banks hold one clause more than the rest
+                let (rows, long_banks) = (num_clauses / banks, num_clauses % banks);
+                let long_span = long_banks * (rows + 1);
+                let (bank, row) = if position < long_span {
+                    (position / (rows + 1), position % (rows + 1))
+                } else {
+                    (long_banks + (position - long_span) / rows, (position - long_span) % rows)
+                };
+                row * banks + bank
+            }
+        }
+    }
+
+    /// Inverse of `clause_at`: how far into a scan `clause` is read
+    pub fn position_of(&self, clause: ClauseIdx, num_clauses: usize) -> usize {
+        match *self {
+            ClauseLayout::Sequential => clause,
+            ClauseLayout::Interleaved { banks } => {
+                let (rows, long_banks) = (num_clauses / banks, num_clauses % banks);
+                let (bank, row) = (clause % banks, clause / banks);
+                bank * rows + bank.min(long_banks) + row
+            }
+        }
+    }
 }
+
+/// Clause evaluation hardware of a node. Every `cycles_per_eval` cycles it checks a batch of `parallel_clauses`
+/// clauses for each of up to `depth` assignments in flight, so scanning one assignment takes
+/// `ceil(clauses / parallel_clauses) * cycles_per_eval` cycles but a chain of unit propagations overlaps its scans.
+/// The clauses come in the order of `layout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PipelineModel {
+    pub parallel_clauses: usize,
+    pub cycles_per_eval: u64,
+    pub depth: usize,
+    pub layout: ClauseLayout,
+}
+impl PipelineModel {
+    /// Cycles from the start of a scan until the batch holding `clause` has been evaluated
+    pub fn reach_time(&self, clause: ClauseIdx, num_clauses: usize) -> u64 {
+        (self.layout.position_of(clause, num_clauses) / self.parallel_clauses + 1) as u64 * self.cycles_per_eval
+    }
+
+
+    /// Cycles to scan a single assignment through `num_clauses` clauses
+    pub fn latency(&self, num_clauses: usize) -> u64 {
+        num_clauses.div_ceil(self.parallel_clauses) as u64 * self.cycles_per_eval
+    }
+
+    /// Cycles to scan a chain of `k` propagations, each found in the first batch of the scan before it. A propagation
+    /// enters the pipeline one batch behind the last, or when a slot frees up if all `depth` of them are busy.
+    pub fn cost_for_k_propagations(&self, k: usize, num_clauses: usize) -> u64 {
+        let latency = self.latency(num_clauses);
+        let mut starts: Vec<u64> = Vec::with_capacity(k);
+        for i in 0..k {
+            let mut start = if i == 0 { 0 } else { starts[i - 1] + self.cycles_per_eval };
+            if i >= self.depth {
+                start = start.max(starts[i - self.depth] + latency);
+            }
+            starts.push(start);
+        }
+        starts.last().map_or(0, |start| start + latency)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 struct UnitPropagation {
     speculative_depth: VarId,
     var_id: VarId,
@@ -43,82 +150,385 @@ struct UnitPropagation {
 }
 
 
-pub struct Node {
+#[derive(Serialize, Deserialize)]
+pub struct Node<const K: usize = CLAUSE_LENGTH> {
     /// Unique identifier for the node.
     pub id: NodeId,
     /// List of neighboring nodes that can send fork messages.
-    neighbors: Vec<NodeId>,
+    neighbors: Neighborhood,
     /// Local understanding of the SAT problem state.
-    pub table: ClauseTable,
+    pub table: ClauseTable<K>,
     /// Current state of the node.
     state: NodeState,
 
     /// Message currently being processed.
-    incoming_message: Option<Message>,
+    incoming_message: Option<Message<K>>,
     /// Watchdog to prevent node from getting stuck.
     watchdog: Watchdog,
-    /// Number of clauses checked per clock cycle.
-    parallel_clauses: usize,
-    /// Number of pipeline stages available at a given time.
-    pipeline_size: usize,
+    /// Width, latency and depth of the clause evaluation pipeline.
+    pipeline: PipelineModel,
+    /// Cycles left before the batch being evaluated is done.
+    eval_wait: u64,
+    /// Cycles an idle node needs to wake up before it can start on a fork.
+    wakeup_penalty: u64,
+    /// Cycles left of the current wakeup.
+    wakeup_left: u64,
+    /// Times a fork woke the node up.
+    wakeups: u64,
     /// Variables that have been assigned and their state.
     var_updates: Vec<VarUpdate>,
 
     /// Tracks when each variable was assigned in the SAT solving process.
     /// Each element corresponds to a variable and contains its assignment depth and value.
+    /// Variables are 1-indexed like DIMACS: index 0 is the padding sentinel, assigned false at depth 0 on activation.
     assignment_time: Vec<SpeculativeDepth>,
     /// Tracks the speculative branches of newly assigned variables.
     speculative_branches: Vec<VarId>,
     /// Tracks unit propagation assignments.
     unit_propagation: Vec<UnitPropagation>,
+    /// Extra cycles spent making each branching decision.
+    decision_delay: u64,
+    /// Cycles left before the node can continue processing.
+    stall: u64,
+    /// Last value each variable was assigned, reused when speculating on it again (phase saving).
+    phase: Vec<Option<bool>>,
+    /// Branching decisions made while some node in the network was idle, so a fork was possible with the right links.
+    fork_attempts: u64,
+    /// Branching decisions that found an idle neighbor and forked.
+    fork_successes: u64,
+    /// Forks sent, more than `fork_successes` when a decision forks to several neighbors.
+    forks_sent: u64,
+    /// Most idle neighbors one branching decision forks to.
+    fork_fanout: usize,
+    /// Branches explored so far: decisions made plus models reached.
+    branches_explored: u64,
+    /// `branches_explored` when the fork being worked on arrived, None when working on the root or idle.
+    fork_start: Option<u64>,
+    /// Received forks that explored at least one branch before the node went idle again.
+    forks_productive: u64,
+    /// Forks that led to the current subproblem, oldest first; empty on the root or when idle.
+    provenance: Vec<ForkOrigin>,
+    /// Variables this node branches on before any others (see `SatSwarm::assign_variables_to_nodes`).
+    preferred_vars: Vec<VarId>,
+    /// Conflicts seen at each decision level, anything deeper than the last bucket is counted there.
+    conflict_histogram: Vec<u64>,
+    /// What the current cycle is being spent on.
+    cycle_cause: AssignmentCause,
+    /// Busy cycles spent on each `AssignmentCause`.
+    cause_cycles: [u64; 3],
+    /// Defer clause scans until the next branch so chained assignments share a single scan.
+    lazy_mode: bool,
+    /// Assignments made in lazy mode that no scan has covered yet, merged into one update.
+    pending_scan: Option<VarUpdate>,
+    /// Clauses checked so far, the energy of the node is roughly proportional to it.
+    clause_evaluations: u64,
+    /// Keep searching after a satisfying assignment instead of reporting SAT, counting the models.
+    count_models: bool,
+    /// Satisfying assignments found while counting models.
+    models_found: u64,
+    /// The first of them, kept so it can be verified once the search is over.
+    first_model: Option<Vec<Option<bool>>>,
+    /// Apply the unit propagations found by the clause scans; without it only decisions assign variables.
+    unit_prop: bool,
+    /// Times each clause was checked, None unless access tracking is on (it costs a write per check).
+    clause_accesses: Option<Vec<u64>>,
+    /// Value tried first when speculating on a variable that has never been assigned.
+    default_polarity: bool,
+    /// How often the clause table is checked against the assignment (see `check_table`).
+    verify_level: VerifyLevel,
+    /// Check points passed so far, `VerifyLevel::Sampled` checks every Nth of them.
+    verify_points: u64,
+    /// Cycle of the current clock update, the timestamp of the node's log lines.
+    clock: u64,
+    /// Times each clause was falsified or became unit, the clauses doing the work of the search.
+    clause_activity: Vec<u64>,
+    /// Cost of each operation, None = energy is not tracked.
+    energy_model: Option<EnergyModel>,
+    /// Picojoules spent so far according to the energy model.
+    energy_consumed: f64,
 }
 
 
 // TODO: update SAT to be when all variables are set (this should be a rare case)
-impl Node {
+impl<const K: usize> Node<K> {
     
     /// Creates a new node with given arguments
-    pub fn new(id: NodeId, table: ClauseTable, parallel_clauses: usize) -> Self {
+    pub fn new(id: NodeId, table: ClauseTable<K>, parallel_clauses: usize) -> Self {
         let vars = table.num_vars;
+        let clauses = table.num_clauses;
         Node {
             id,                                                 // My id
-            neighbors: Vec::new(),                              // NodeId of nodes that we can send fork messages to
+            neighbors: Neighborhood::Explicit(Vec::new()),      // NodeId of nodes that we can send fork messages to
             table,                                              // My understanding of the state
             assignment_time: vec![SpeculativeDepth::Unassigned; vars],   // At what speculative depth was each variable assigned (0=unassigned)
             var_updates: Vec::new(),                            // Which clause are we currently processing
-            parallel_clauses,                                   // How many clauses are checked per clock cycle
+            pipeline: PipelineModel { parallel_clauses, cycles_per_eval: 1, depth: 1, layout: ClauseLayout::Sequential },   // How many clauses are checked per clock cycle
             speculative_branches: Vec::new(),                   // What is the speculative of newly assigned variables (some optimizaiton to use this as both a speculative and unit propagation buffer)
             state: NodeState::AwaitingFork,                     // make sure to start at false except for the first node so they don't repeat work
             incoming_message: None,                             // 
             watchdog: Watchdog::new(0, 500),
-            pipeline_size: 1,
+            eval_wait: 0,
+            wakeup_penalty: 0,
+            wakeup_left: 0,
+            wakeups: 0,
             unit_propagation: Vec::new(),
+            decision_delay: 0,
+            stall: 0,
+            phase: vec![None; vars],
+            fork_attempts: 0,
+            fork_successes: 0,
+            forks_sent: 0,
+            fork_fanout: 1,
+            branches_explored: 0,
+            fork_start: None,
+            forks_productive: 0,
+            provenance: Vec::new(),
+            preferred_vars: Vec::new(),
+            conflict_histogram: vec![0; CONFLICT_LEVELS],
+            cycle_cause: AssignmentCause::Decision,
+            cause_cycles: [0; 3],
+            lazy_mode: false,
+            pending_scan: None,
+            clause_evaluations: 0,
+            count_models: false,
+            models_found: 0,
+            first_model: None,
+            unit_prop: true,
+            clause_accesses: None,
+            default_polarity: false,
+            verify_level: VerifyLevel::Off,
+            verify_points: 0,
+            clock: 0,
+            clause_activity: vec![0; clauses],
+            energy_model: None,
+            energy_consumed: 0.0,
         }
     }
 
+    /// Sets how many extra cycles each branching decision costs (0 = decided in the same cycle)
+    pub fn set_decision_delay(&mut self, decision_delay: u64) {
+        self.decision_delay = decision_delay;
+    }
+
+    /// Sets the latency of a clause evaluation: each batch of `parallel_clauses` clauses takes `cycles` cycles
+    pub fn set_cycles_per_eval(&mut self, cycles: u64) {
+        assert!(cycles > 0, "Clause evaluation takes at least a cycle");
+        self.pipeline.cycles_per_eval = cycles;
+    }
+
+    /// Lets up to `depth` assignments be scanned at once, so a unit propagation can start its scan while the scan
+    /// that found it is still running. Decisions still wait for the pipeline to drain, and nothing joins the scan
+    /// of a backtrack until it has reset every clause (see `PipelineModel`)
+    pub fn set_pipeline_depth(&mut self, depth: usize) {
+        assert!(depth > 0, "The pipeline needs at least one stage");
+        self.pipeline.depth = depth;
+    }
+
+    /// Changes the order the clause scans read the clauses in (see `ClauseLayout`)
+    pub fn set_clause_layout(&mut self, layout: ClauseLayout) {
+        assert!(self.var_updates.is_empty(), "Node {} changed clause layout mid scan", self.id);
+        if let ClauseLayout::Interleaved { banks } = layout {
+            assert!(banks > 0, "An interleaved layout needs at least one bank");
+        }
+        self.pipeline.layout = layout;
+    }
+
+    /// Lets one branching decision fork to up to `fanout` idle neighbors, the closest (lowest link latency, then lowest id)
+    /// first. A single fork (the default) goes to the first idle neighbor in list order.
+    /// With j of them the decision takes the next j variables: fork i gets the true side of variable i after the
+    /// false sides of the variables before it, and this node keeps all of them false.
+    pub fn set_fork_fanout(&mut self, fanout: usize) {
+        assert!(fanout > 0, "A fork needs at least one receiver");
+        self.fork_fanout = fanout;
+    }
+
+    /// Makes an idle node spend `cycles` waking up when a fork arrives, modelling the cost of powering it back up
+    pub fn set_wakeup_penalty(&mut self, cycles: u64) {
+        self.wakeup_penalty = cycles;
+    }
+
+    /// Lazy mode only updates the assignment vector when a variable is assigned and scans the clauses for all
+    /// pending assignments in one pass at the next branch, so a burst of unit propagations costs one scan
+    pub fn set_lazy_mode(&mut self, lazy: bool) {
+        assert!(self.var_updates.is_empty() && self.pending_scan.is_none(), "Node {} changed evaluation mode mid scan", self.id);
+        self.lazy_mode = lazy;
+    }
+
+    /// When counting, a satisfying assignment is treated like a conflict: it is counted and the search backtracks
+    /// to the next branch instead of broadcasting success
+    pub fn set_count_models(&mut self, count_models: bool) {
+        self.count_models = count_models;
+    }
+
+    /// Turns unit propagation off to measure what it saves. Conflicts are still found when a clause becomes all
+    /// false, so the search stays complete, it just has to decide every variable
+    pub fn set_unit_prop(&mut self, enabled: bool) {
+        self.unit_prop = enabled;
+    }
+
+    /// Counts the checks of every clause, to study which parts of the table stay hot across decisions
+    pub fn set_track_clause_accesses(&mut self, track: bool) {
+        self.clause_accesses = track.then(|| vec![0; self.table.num_clauses]);
+    }
+
+    /// Sets the value speculation tries first on fresh variables, so nodes can explore differently ordered subtrees
+    pub fn set_default_polarity(&mut self, polarity: bool) {
+        self.default_polarity = polarity;
+    }
+
+    /// Sets how often the node checks its invariants. The check points are every decision taken between scans and
+    /// every model found, each check walks the whole table
+    pub fn set_verify_level(&mut self, level: VerifyLevel) {
+        self.verify_level = level;
+    }
+
+    /// Charges every decision (including the flip after a conflict), unit propagation, fork and idle cycle of the
+    /// node from now on according to `model`. Root assignments are free, they are part of loading the problem
+    pub fn set_energy_model(&mut self, model: Option<EnergyModel>) {
+        self.energy_model = model;
+    }
+
     /// Adds a neighbour to the node, used by the topology to set up the network
     pub fn add_neighbor(&mut self, id: NodeId) {
-        self.neighbors.push(id);
+        match &mut self.neighbors {
+            Neighborhood::Explicit(neighbors) => neighbors.push(id),
+            Neighborhood::Dense(_) => panic!("Node {} is already connected to every node", self.id),
+        }
     }
 
     /// Removes a neighbour from the node, used by the topology to tear down the network (remove certain connections)
     pub fn remove_neighbor(&mut self, id: NodeId) {
-        self.neighbors.retain(|&n| n != id);
+        if let Neighborhood::Dense(_) = self.neighbors {
+            self.neighbors = Neighborhood::Explicit(self.neighbors().collect());  // no longer dense, fall back to the list
+        }
+        if let Neighborhood::Explicit(neighbors) = &mut self.neighbors {
+            neighbors.retain(|&n| n != id);
+        }
     }
 
-    /// Activates the node -- sets it to "busy"
-    pub fn activate(&mut self) {self.state = NodeState::Busy;}
+    /// Variables to branch on before the rest, in order. Only changes the search order, never what is searched.
+    pub fn set_preferred_vars(&mut self, vars: Vec<VarId>) {
+        assert!(vars.iter().all(|&var| (var as usize) < self.assignment_time.len()), "Preferred variable out of range");
+        self.preferred_vars = vars;
+    }
+
+    /// Connects the node to every other node of a `num_nodes` network without storing the O(n) neighbor list
+    pub fn set_dense_neighbors(&mut self, num_nodes: usize) {
+        self.neighbors = Neighborhood::Dense(num_nodes);
+    }
+
+    /// Activates the node -- sets it to "busy" and fixes the sentinel variable 0 to false
+    pub fn activate(&mut self) {
+        self.state = NodeState::Busy;
+        self.substitute(0, false, false, 0, AssignmentCause::Decision);  // a guaranteed assignment, never branched on
+    }
+
+    /// Activates the node with a fixed root assignment so several nodes can start on disjoint parts of the search space
+    pub fn activate_with(&mut self, root: &[(VarId, bool)]) {
+        self.activate();
+        for &(var, assignment) in root {
+            self.substitute(var, assignment, false, 0, AssignmentCause::Decision);
+        }
+    }
 
     // ----- getters ----- //
     /// 
     pub fn busy(&self) -> bool {return self.state != NodeState::AwaitingFork}
+    /// Reserved by a fork that is still streaming over the link or that we are waking up for, so there is nothing to work on yet
+    pub fn waiting_on_fork(&self) -> bool {return self.state == NodeState::RecievingFork && (self.incoming_message.is_none() || self.wakeup_left > 0)}
+    /// Nodes this node can fork work to
+    pub fn neighbors(&self) -> impl Iterator<Item = NodeId> + '_ {
+        let (list, dense) = match &self.neighbors {
+            Neighborhood::Explicit(neighbors) => (neighbors.as_slice(), 0),
+            Neighborhood::Dense(num_nodes) => (&[][..], *num_nodes),
+        };
+        list.iter().copied().chain((0..dense).filter(move |&n| n != self.id))
+    }
+    /// Whether this node can fork to `id`
+    pub fn is_neighbor(&self, id: NodeId) -> bool {
+        match &self.neighbors {
+            Neighborhood::Explicit(neighbors) => neighbors.contains(&id),
+            Neighborhood::Dense(num_nodes) => id < *num_nodes && id != self.id,
+        }
+    }
+    /// Current value of every variable (None = unassigned), indexed by variable
+    pub fn assignment(&self) -> Vec<Option<bool>> {
+        self.assignment_time.iter().map(|depth| match depth {
+            SpeculativeDepth::Depth(_, value) => Some(*value),
+            SpeculativeDepth::Unassigned => None,
+        }).collect()
+    }
+    /// Branching decisions made while some node was idle
+    pub fn fork_attempts(&self) -> u64 {self.fork_attempts}
+    /// Branching decisions that found an idle neighbor
+    pub fn fork_successes(&self) -> u64 {self.fork_successes}
+    /// Forks sent to neighbors
+    pub fn forks_sent(&self) -> u64 {self.forks_sent}
 
+    /// Received forks that explored a branch, counting the one being worked on if it already has
+    pub fn forks_productive(&self) -> u64 {
+        self.forks_productive + self.fork_start.is_some_and(|start| self.branches_explored > start) as u64
+    }
+    /// Where the current subproblem came from: the fork it arrived in last, preceded by the forks of the senders
+    pub fn provenance(&self) -> &[ForkOrigin] {&self.provenance}
+    /// Forks that woke this node up
+    pub fn wakeups(&self) -> u64 {self.wakeups}
+    /// Number of conflicts at each decision level (last bucket = that level or deeper)
+    pub fn conflict_histogram(&self) -> &[u64] {&self.conflict_histogram}
+    /// Satisfying assignments this node found while counting models
+    pub fn models_found(&self) -> u64 {self.models_found}
+    /// First satisfying assignment this node found while counting models
+    pub fn first_model(&self) -> Option<&Vec<Option<bool>>> {self.first_model.as_ref()}
+    /// Clauses this node has checked
+    pub fn clause_evaluations(&self) -> u64 {self.clause_evaluations}
+    /// Checks of each clause by this node, if tracked
+    pub fn clause_accesses(&self) -> Option<&Vec<u64>> {self.clause_accesses.as_ref()}
+    /// Conflicts plus unit propagations found in each clause
+    pub fn clause_activity(&self) -> &[u64] {&self.clause_activity}
+    /// Busy cycles this node spent on `cause`
+    pub fn cause_cycles(&self, cause: AssignmentCause) -> u64 {self.cause_cycles[cause as usize]}
+    /// Attributes the cycle that was just simulated to whatever the node worked on, called for busy cycles only
+    pub fn record_busy_cycle(&mut self) {self.cause_cycles[self.cycle_cause as usize] += 1;}
+    /// Charges the idle energy of a cycle with nothing to do
+    pub fn record_idle_cycle(&mut self) {self.spend(|model| model.idle_per_cycle_pj);}
+    /// Picojoules spent so far, 0 without an energy model
+    pub fn energy_consumed(&self) -> f64 {self.energy_consumed}
 
+    fn spend(&mut self, cost: impl Fn(&EnergyModel) -> f64) {
+        if let Some(model) = &self.energy_model {
+            self.energy_consumed += cost(model);
+        }
+    }
+
+
+    /// Variable to branch on next, never the sentinel variable 0
     fn get_next_var(&self) -> Option<usize>{
-        return self.assignment_time.iter().position(|x| *x == SpeculativeDepth::Unassigned) // For now get the index of the first unassigned variable
+        if let Some(&var) = self.preferred_vars.iter().find(|&&var| var != 0 && self.assignment_time[var as usize] == SpeculativeDepth::Unassigned) {
+            return Some(var as usize);  // branch on our own share of the variables first
+        }
+        return self.assignment_time.iter().skip(1).position(|x| *x == SpeculativeDepth::Unassigned).map(|var| var + 1) // For now get the index of the first unassigned variable
+    }
+
+    /// Up to `count` distinct variables to branch on, in the order `get_next_var` would pick them
+    fn get_next_vars(&self, count: usize) -> Vec<VarId> {
+        let mut vars = Vec::with_capacity(count);
+        for var in self.preferred_vars.iter().copied().chain((1..self.assignment_time.len()).map(|var| var as VarId)) {
+            if vars.len() == count {
+                break;
+            }
+            if var != 0 && self.assignment_time[var as usize] == SpeculativeDepth::Unassigned && !vars.contains(&var) {
+                vars.push(var);
+            }
+        }
+        vars
     }
 
 
+    /// Depth of the node's speculative trail (0 = only guaranteed assignments)
+    pub fn depth(&self) -> VarId {
+        self.get_deepest_speculation()
+    }
+
     fn get_deepest_speculation(&self) -> VarId {
         let mut max = 0;
         for var in self.assignment_time.iter() {
@@ -134,10 +544,16 @@ impl Node {
         return max;
     }
     // ----- clock update ----- //
-    pub fn clock_update(&mut self, clock: u64, network: &mut MessageQueue, busy_nodes: &mut Vec<bool>) { 
+    pub fn clock_update(&mut self, clock: u64, network: &mut MessageQueue<K>, busy_nodes: &mut Vec<bool>) { 
+        self.clock = clock;
+        if self.wakeup_left > 0 {
+            self.wakeup_left -= 1;  // the fork has arrived but we are still waking up
+            self.watchdog.check(clock);
+            return;
+        }
         let msg = std::mem::replace(&mut self.incoming_message, None);
         match (&self.state, msg) {
-            (NodeState::RecievingFork, Some(Message::Fork {table, assigned_vars})) => {
+            (NodeState::RecievingFork, Some(Message::Fork {table, assigned_vars, false_vars, branch_var, provenance, ..})) => {
                 assert!(self.speculative_branches.is_empty(), "Node {} received fork while still processing", self.id);
                 assert!(self.unit_propagation.is_empty(), "Node {} received fork while still processing unit props", self.id);
                 assert!(self.var_updates.is_empty(), "Node {} received fork while still processing var updates", self.id);
@@ -146,38 +562,79 @@ impl Node {
                 self.table = table;
                 assert!(self.assignment_time.len() == assigned_vars.len(), "nodes have different number of variables");
                 self.assignment_time = assigned_vars;
-                let var = self.get_next_var().expect("Forked SAT problem!") as VarId;
-                self.substitute(var, true, false, self.get_deepest_speculation()+1);
+                // rebuild the terms from the assignment in one pass, the sender's table may still hold scans in flight
+                self.table.reset_to_symbolic();
+                self.table.apply_assignment(&self.assignment());
+                assert!(self.assignment_time[branch_var as usize] == SpeculativeDepth::Unassigned, "Forked on an assigned variable");
+                let var = branch_var;  // the sender may prefer different variables than we do
+                self.spend(|model| model.fork_receive_energy_pj);
+                self.fork_start = Some(self.branches_explored);
+                if let Some(origin) = provenance.last() {
+                    sim_log!(self.id, clock, LogLevel::Debug, "Received fork from {} sent at {}", origin.source, origin.clock);
+                }
+                self.provenance = provenance;
+                let depth = self.get_deepest_speculation()+1;
+                for false_var in false_vars {
+                    // a k-way fork gave their true sides to the forks sent before ours, none of this is speculative
+                    assert!(self.assignment_time[false_var as usize] == SpeculativeDepth::Unassigned, "Forked on an assigned variable");
+                    self.substitute(false_var, false, false, depth, AssignmentCause::Decision);
+                }
+                self.substitute(var, true, false, depth, AssignmentCause::Decision);
             },
             (NodeState::Busy, None) => {
-                if DEBUG_PRINT {
-                    println!("Assignment time: {:?}", self.assignment_time);
+                if self.eval_wait > 0 {
+                    self.eval_wait -= 1;  // the last batch of clauses is still being evaluated
+                    self.watchdog.check(clock);
+                    return;
                 }
-                if self.var_updates.len() < self.pipeline_size {
+                sim_log!(self.id, clock, LogLevel::Trace, "Assignment time: {:?}", self.assignment_time);
+                // a backtrack leaves stale terms ahead of its scan, which a newer assignment could read before they are reset
+                let resetting = self.var_updates.iter().any(|var_update| var_update.reset);
+                if self.var_updates.len() < self.pipeline.depth && !resetting {
                     self.branch(clock, network, busy_nodes);
                 }
+                if self.stall > 0 {
+                    self.stall -= 1;  // still paying for the last decision
+                    self.cycle_cause = AssignmentCause::Decision;
+                    return;
+                }
+                if let Some(var_update) = self.var_updates.first() {
+                    self.cycle_cause = var_update.cause;  // the cycle goes to the assignment being scanned
+                }
                 let Self {   // Doing bs to avoid borrowing issues
                     table, 
                     var_updates, 
                     assignment_time, 
                     unit_propagation ,
+                    clause_accesses,
+                    clause_activity,
                     ..
                 } = self;
                 let mut unsat_depth = None;
-                var_updates.retain(|var_update| var_update.clause_index < table.num_clauses);
+                var_updates.retain(|var_update| var_update.position < table.num_clauses);
+                if !var_updates.is_empty() {
+                    self.eval_wait = self.pipeline.cycles_per_eval - 1;
+                }
                 for var_update in var_updates.iter_mut() {
-                    for _ in 0..self.parallel_clauses {
-                        let success = Self::process_clause(table, var_update, assignment_time, unit_propagation);
+                    for _ in 0..self.pipeline.parallel_clauses {
+                        let clause = self.pipeline.layout.clause_at(var_update.position, table.num_clauses);
+                        self.clause_evaluations += 1;
+                        if let Some(accesses) = clause_accesses {
+                            accesses[clause] += 1;
+                        }
+                        let units_found = unit_propagation.len();
+                        let success = Self::process_clause(self.id, clock, table, clause, var_update, assignment_time, unit_propagation);
+                        if !success || unit_propagation.len() > units_found {
+                            clause_activity[clause] += 1;
+                        }
                         if !success {
-                            if DEBUG_PRINT {
-                                let clause_state = table.clause_table[var_update.clause_index].iter().map(|(t, s)| (t.var, t.negated, s)).collect::<Vec<_>>();
-                                println!("Node {} found unsat at depth {} in clause {} with assignments {:?} & clause_state {:?}", self.id, var_update.depth, var_update.clause_index, assignment_time, clause_state);
-                            }
+                            sim_log!(self.id, clock, LogLevel::Debug, "Found unsat at depth {} in clause {} with assignments {:?} & clause_state {:?}", var_update.depth, clause, assignment_time,
+                                table.clause_table[clause].iter().map(|(t, s)| (t.var, t.negated, s)).collect::<Vec<_>>());
                             unsat_depth = Some(var_update.depth);
                             break;
                         }
-                        var_update.clause_index += 1;
-                        if var_update.clause_index >= table.num_clauses || self.state != NodeState::Busy {
+                        var_update.position += 1;
+                        if var_update.position >= table.num_clauses || self.state != NodeState::Busy {
                             break;
                         }
                     }
@@ -187,53 +644,69 @@ impl Node {
                     self.unsat(depth);  // finally can make mutable calls here
                 }
             },
-            (NodeState::AwaitingFork, None) => {
+            (NodeState::AwaitingFork, None) | (NodeState::RecievingFork, None) => {
                 self.watchdog.check(clock);
-            },  // do nothing, keep waiting
+            },  // do nothing, keep waiting (possibly for the rest of a multi-cycle fork)
             (_, m) => panic!("{:?} received unexpected message {:?}", self, m)
         }
     }
 
     // ----- branching ----- //
-    fn branch(&mut self, clock: u64, network: &mut MessageQueue, busy_nodes: &mut Vec<bool>) {
-        if let Some(UnitPropagation{var_id, assignment, speculative_depth}) = self.unit_propagation.pop() {
-            // unit propagation
-            if let SpeculativeDepth::Depth(prev_depth, prev_assign) = self.assignment_time[var_id as usize] {
-                if prev_assign == assignment {
-                    // we are already assigned this value
-                    return;
-                } else if prev_depth > speculative_depth {
-                    // we are already assigned this value
-                    self.unsat(speculative_depth);
-                    return;
-                }
-            } else {
-                self.substitute(var_id, assignment, false, speculative_depth);
-            }
-            if DEBUG_PRINT {
-                println!("Node {} unit propagating var {} to {}", self.id, var_id, assignment);
+    fn branch(&mut self, clock: u64, network: &mut MessageQueue<K>, busy_nodes: &mut Vec<bool>) {
+        if self.pending_scan.is_some() {
+            self.flush_pending_scan();  // a root or forked assignment, scan it before deciding anything else
+            return;
+        }
+        if self.var_updates.is_empty() && self.verify_due() {
+            self.check_table();  // every assignment has been scanned, so the table must agree with them
+        }
+        if !self.unit_prop {
+            self.unit_propagation.clear();  // the scans still find them, they are just never applied
+        }
+        if self.lazy_mode && !self.unit_propagation.is_empty() {
+            // take every unit propagation found by the last scan, the next scan covers all of them
+            while !self.unit_propagation.is_empty() && self.state == NodeState::Busy {
+                self.propagate_unit();
             }
+        } else if !self.unit_propagation.is_empty() {
+            self.propagate_unit();
+        } else if !self.var_updates.is_empty() {
+            // only unit propagations join a running scan, a decision needs every conflict of the last one found
         } else if let Some(var) = self.get_next_var() {
             // branching unknown variable
             let var = var as VarId;
-            if let Some(neighbor_id) = self.neighbors.iter().find(|&&n| !busy_nodes[n as usize]) {
-                if DEBUG_PRINT {
-                    println!("Node {} branching to neighbor {}", self.id, neighbor_id);
-                }
-                // forked work
-                busy_nodes[*neighbor_id as usize] = true;
-                self.partner_branch(clock, network, var, *neighbor_id);
+            if busy_nodes.iter().any(|&busy| !busy) {
+                self.fork_attempts += 1;  // when everybody is busy a failed fork is not the topology's fault
+            }
+            let idle = self.neighbors().filter(|&n| !busy_nodes[n as usize]);
+            let mut idle_neighbors: Vec<NodeId> = if self.fork_fanout == 1 {
+                idle.take(1).collect()  // a single fork keeps the order of the neighbor list
             } else {
-                if DEBUG_PRINT {
-                    println!("Node {} speculating on {}", self.id, var);
+                let mut idle_neighbors: Vec<NodeId> = idle.collect();
+                idle_neighbors.sort_by_key(|&n| (network.link_delay(self.id, n), n));  // closest first, ties go to the lower id
+                idle_neighbors.dedup();  // parallel links (butterfly, 2-wide torus) lead to the same node
+                idle_neighbors.truncate(self.fork_fanout);
+                idle_neighbors
+            };
+            let vars = self.get_next_vars(idle_neighbors.len());
+            idle_neighbors.truncate(vars.len());  // near the leaves there may be fewer variables left than neighbors
+            if !idle_neighbors.is_empty() {
+                sim_log!(self.id, clock, LogLevel::Debug, "Branching to neighbors {:?}", idle_neighbors);
+                // forked work
+                for &neighbor_id in idle_neighbors.iter() {
+                    busy_nodes[neighbor_id as usize] = true;
                 }
+                self.fork_successes += 1;
+                self.partner_branch(clock, network, &vars, &idle_neighbors);
+                self.stall = self.decision_delay;
+            } else {
+                sim_log!(self.id, clock, LogLevel::Debug, "Speculating on {}", var);
                 // speculative work
                 self.speculative_branch(var);
+                self.stall = self.decision_delay;
             }
         } else if self.var_updates.is_empty() {
-            if DEBUG_PRINT {
-                println!("Node {} is SAT", self.id);
-            }
+            sim_log!(self.id, clock, LogLevel::Debug, "SAT");
             // we are done done because there is no more work
             // TODO: check that the unsat substitutes fast enough
             self.sat(clock, network);
@@ -242,30 +715,69 @@ impl Node {
             // println!("WHY ARE WE HERE!");
             // println!()
         }
+        self.flush_pending_scan();
     }
 
-    fn partner_branch(&mut self, clock: u64, network: &mut MessageQueue, var: VarId, neighbor_id: NodeId) {
+    fn propagate_unit(&mut self) {
+        let UnitPropagation{var_id, assignment, speculative_depth} = self.unit_propagation.pop().expect("No unit propagation to apply");
+        if let SpeculativeDepth::Depth(prev_depth, prev_assign) = self.assignment_time[var_id as usize] {
+            if prev_assign == assignment {
+                // we are already assigned this value
+                return;
+            } else if prev_depth > speculative_depth {
+                // we are already assigned this value
+                self.unsat(speculative_depth);
+                return;
+            }
+        } else {
+            self.spend(|model| model.unit_prop_energy_pj);
+            self.substitute(var_id, assignment, false, speculative_depth, AssignmentCause::UnitPropagation);
+        }
+        sim_log!(self.id, self.clock, LogLevel::Debug, "Unit propagating var {} to {}", var_id, assignment);
+    }
+
+    /// Sends the true side of `vars[i]` to `neighbor_ids[i]`, each fork after the false sides of the variables
+    /// before it, and keeps the false side of every variable
+    fn partner_branch(&mut self, clock: u64, network: &mut MessageQueue<K>, vars: &[VarId], neighbor_ids: &[NodeId]) {
         assert!(self.state == NodeState::Busy, "Node {} is not in busy state", self.id);
+        assert!(vars.len() == neighbor_ids.len(), "Node {} forked {} variables to {} neighbors", self.id, vars.len(), neighbor_ids.len());
         
-        // copy the CNF state and send the fork. Then continue with the other branch 
-        let fork_msg = Message::Fork {table: self.table.clone(), assigned_vars: self.assignment_time.clone()};
-        self.send_message(clock, network, MessageDestination::Neighbor(neighbor_id), fork_msg);  
+        // copy the CNF state and send the forks. Then continue with the other branch 
+        let byte_size = self.assignment_time.iter().filter(|depth| **depth != SpeculativeDepth::Unassigned).count() * 2;
+        let mut provenance = self.provenance.clone();
+        provenance.push(ForkOrigin { source: self.id, clock });
+        for (i, (&var, &neighbor_id)) in vars.iter().zip(neighbor_ids).enumerate() {
+            let fork_msg = Message::Fork {table: self.table.clone(), assigned_vars: self.assignment_time.clone(), byte_size: byte_size + 2 * i, false_vars: vars[..i].to_vec(), branch_var: var, provenance: provenance.clone()};
+            self.send_message(clock, network, MessageDestination::Neighbor(neighbor_id), fork_msg);  
+            self.spend(|model| model.fork_send_energy_pj);
+            self.forks_sent += 1;
+        }
 
-        // now substitute the variable here
-        self.substitute(var, false, false, self.get_deepest_speculation()+1);
+        // now substitute the variables here, all at one depth so a conflict in any of their scans stops every one of them
+        self.branches_explored += 1;
+        let depth = self.get_deepest_speculation()+1;
+        for &var in vars {
+            self.spend(|model| model.decision_energy_pj);
+            self.substitute(var, false, false, depth, AssignmentCause::Decision);
+        }
     }
 
     fn speculative_branch(&mut self, var: VarId) {
         assert!(self.state == NodeState::Busy, "Node {} is not in branching state", self.id);
         self.speculative_branches.push(var);  //  I think this can be removedd
-        self.substitute(var, false, false, self.get_deepest_speculation()+1);
+        let assignment = self.phase[var as usize].unwrap_or(self.default_polarity);  // retry the last value we had for this variable
+        self.branches_explored += 1;
+        self.spend(|model| model.decision_energy_pj);
+        self.substitute(var, assignment, false, self.get_deepest_speculation()+1, AssignmentCause::Decision);
     }
 
     // ----- processing ----- //
-    fn substitute(&mut self, var: VarId, assignment: bool, reset: bool, speculative_depth: VarId) {
+    fn substitute(&mut self, var: VarId, assignment: bool, reset: bool, speculative_depth: VarId, cause: AssignmentCause) {
         assert!(self.state == NodeState::Busy || self.state == NodeState::RecievingFork, "Node {} is not in branching state", self.id);
         self.state = NodeState::Busy;
+        self.cycle_cause = cause;
         self.assignment_time[var as usize] = SpeculativeDepth::Depth(speculative_depth, assignment);
+        self.phase[var as usize] = Some(assignment);
         if reset {
             self.assignment_time.iter_mut().for_each(|x| {
                 if let SpeculativeDepth::Depth(depth, _) = x {
@@ -275,47 +787,67 @@ impl Node {
                 }
             });
         }
-        self.var_updates.push(VarUpdate {
+        let update = VarUpdate {
             var_id: var,                    // which variable are we updating
-            clause_index: 0,                // start at the beginning
+            position: 0,                    // start at the beginning
             assignment,                     // what is the assignment (true/false) 
             reset,                          // should we reset the variables of higher depth
             // speculative: false,             
             depth: speculative_depth,         // what is the depth of the assignment
-        });
+            cause,                            // why the variable was assigned
+            lazy: self.lazy_mode,
+        };
+        if !self.lazy_mode {
+            self.var_updates.push(update);
+        } else if let Some(pending) = &mut self.pending_scan {
+            // the scan reads the assignment vector, so it only has to know the latest depth
+            pending.var_id = var;
+            pending.assignment = assignment;
+            pending.reset |= reset;
+            pending.depth = speculative_depth;
+        } else {
+            self.pending_scan = Some(update);
+        }
+    }
+
+    /// Starts the scan of the assignments deferred in lazy mode
+    fn flush_pending_scan(&mut self) {
+        if let Some(update) = self.pending_scan.take() {
+            self.var_updates.push(update);
+        }
     }
     
-    fn mask(table: &ClauseTable, update_buffer: &mut Vec<SpeculativeDepth>, var_update: &VarUpdate) -> [TermUpdate; CLAUSE_LENGTH] {
-        let mut iter = table.clause_table[var_update.clause_index].iter()
+    fn mask(table: &ClauseTable<K>, clause: ClauseIdx, update_buffer: &mut Vec<SpeculativeDepth>, var_update: &VarUpdate) -> [TermUpdate; K] {
+        let mut iter = table.clause_table[clause].iter()
             .map(|(Term { var, negated }, _)| {
-                if *var == var_update.var_id {
+                if var_update.lazy {
+                    // every term is brought up to date, covering all the assignments since the last scan
+                    match update_buffer[*var as usize] {
+                        SpeculativeDepth::Depth(_, value) if value != *negated => TermUpdate::True,
+                        SpeculativeDepth::Depth(..) => TermUpdate::False,
+                        SpeculativeDepth::Unassigned => TermUpdate::Reset,
+                    }
+                } else if *var == var_update.var_id {
                     if *negated == !var_update.assignment {
                         TermUpdate::True
                     } else {
                         TermUpdate::False
                     }
                 } else if let SpeculativeDepth::Unassigned = update_buffer[*var as usize] {
-                    // if DEBUG_PRINT {
-                    //     println!("Node {} (depth: {}) resetting var {} in clause {}", var_update.var_id, var_depth, var, var_update.clause_index);
-                    // }
                     TermUpdate::Reset
                 } else {
                     TermUpdate::Unchanged
                 }
         });
 
-        [
-            iter.next().expect("Iterator did not yield enough elements"),
-            iter.next().expect("Iterator did not yield enough elements"),
-            iter.next().expect("Iterator did not yield enough elements"),
-        ]
+        std::array::from_fn(|_| iter.next().expect("Iterator did not yield enough elements"))
     }
 
-    fn process_clause(clause_table: &mut ClauseTable, var_update: &VarUpdate, update_buffer: &mut Vec<SpeculativeDepth>, unit_props: &mut Vec<UnitPropagation>) -> bool {
-        assert!(var_update.clause_index < clause_table.clause_table.len(), "reading past the end of the clause");
+    fn process_clause(id: NodeId, clock: u64, clause_table: &mut ClauseTable<K>, clause: ClauseIdx, var_update: &VarUpdate, update_buffer: &mut Vec<SpeculativeDepth>, unit_props: &mut Vec<UnitPropagation>) -> bool {
+        assert!(clause < clause_table.clause_table.len(), "reading past the end of the clause");
         // later optimizations mean we can fast forward through tautologies
-        let mask = Self::mask(clause_table, update_buffer, var_update);
-        let current_clause = &mut clause_table.clause_table[var_update.clause_index];
+        let mask = Self::mask(clause_table, clause, update_buffer, var_update);
+        let current_clause = &mut clause_table.clause_table[clause];
 
         // assign the variable
         for ((t, term), result) in current_clause.iter_mut().zip(mask) {
@@ -343,9 +875,7 @@ impl Node {
         } else if current_clause.iter().filter(|(_, state)| *state == TermState::Symbolic).count() == 1 {
             let (term, sym) = current_clause.iter().find(|(_, state)| *state == TermState::Symbolic).unwrap();
             assert!(*sym == TermState::Symbolic, "Found non-symbolic term in unit propagation");
-            if DEBUG_PRINT {
-                println!("Node {} found unit propagation in clause {} with term {:?}", var_update.var_id, var_update.clause_index, term);
-            }
+            sim_log!(id, clock, LogLevel::Trace, "Scan of var {} found unit propagation in clause {} with term {:?}", var_update.var_id, clause, term);
             unit_props.push(UnitPropagation {
                 speculative_depth: var_update.depth,
                 var_id: term.var,
@@ -355,18 +885,57 @@ impl Node {
         return true;
     }
 
+    // ----- verification ----- //
+    /// Counts a check point and says whether the verify level wants it checked
+    fn verify_due(&mut self) -> bool {
+        self.verify_points += 1;
+        match self.verify_level {
+            VerifyLevel::Off => false,
+            VerifyLevel::Sampled(every) => self.verify_points.is_multiple_of(every),
+            VerifyLevel::Full => true,
+        }
+    }
+
+    /// Panics unless every term of the table matches the assignment and no clause is falsified.
+    /// Only holds once the scans of every assignment have finished.
+    pub fn check_table(&self) {
+        for (i, clause) in self.table.clause_table.iter().enumerate() {
+            for (term, state) in clause.iter() {
+                let expected = match self.assignment_time[term.var as usize] {
+                    SpeculativeDepth::Depth(_, value) if value != term.negated => TermState::True,
+                    SpeculativeDepth::Depth(..) => TermState::False,
+                    SpeculativeDepth::Unassigned => TermState::Symbolic,
+                };
+                assert!(*state == expected, "Node {} has {:?} for var {} in clause {} but its assignment gives {:?}", self.id, state, term.var, i, expected);
+            }
+            assert!(clause.iter().any(|(_, state)| *state != TermState::False), "Node {} missed the conflict in clause {}", self.id, i);
+        }
+    }
+
     // ----- termination ----- //
     fn clear_state(&mut self) {
-        if DEBUG_PRINT {
-            println!("Node {} clearing state", self.id);
-        }
+        sim_log!(self.id, self.clock, LogLevel::Debug, "Clearing state");
         self.state = NodeState::AwaitingFork; 
+        if self.fork_start.take().is_some_and(|start| self.branches_explored > start) {
+            self.forks_productive += 1;
+        }
+        self.provenance.clear();
         self.var_updates.clear();
+        self.pending_scan = None;
         // self.update.clear();
         self.unit_propagation.clear();
         self.speculative_branches.clear();
     }
     fn unsat(&mut self, speculative_depth: VarId) {
+        let level = self.get_deepest_speculation() as usize;
+        self.conflict_histogram[level.min(CONFLICT_LEVELS - 1)] += 1;
+        self.cycle_cause = AssignmentCause::Conflict;
+        #[cfg(debug_assertions)]
+        if !self.var_updates.iter().chain(self.pending_scan.iter()).any(|var_update| var_update.reset) {
+            if let Err(e) = self.table.verify_consistency() {
+                panic!("Node {} has an inconsistent clause table at a conflict: {}", self.id, e);
+            }
+        }
         self.var_updates.retain(|var_update| var_update.depth < speculative_depth);
         if self.speculative_branches.is_empty() { 
             self.clear_state();
@@ -378,40 +947,61 @@ impl Node {
     fn backtrack(&mut self) {
         self.unit_propagation.clear();
         let var = self.speculative_branches.pop().expect("No branches to backtrack");
-        let (current_depth, assignment) = if let Some(spec) = self.speculative_branches.last() {
-            if let SpeculativeDepth::Depth(depth, assignment) = self.assignment_time[*spec as usize] {
-                (depth, !assignment)
-            } else {
-                panic!("Speculating on unassigned variable");
-            }
-        } else {
-            match self.assignment_time[var as usize] {
-                SpeculativeDepth::Depth(depth, assignment) => (0, !assignment),
-                _ => panic!("Were speculating on unassigned variable"),
-            }
+        // the other value of the variable is no longer speculative, so it lives at the depth of the previous decision.
+        // That decision may be a fork (or the branch we received), which is not in speculative_branches but must survive the reset
+        let (current_depth, assignment) = match self.assignment_time[var as usize] {
+            SpeculativeDepth::Depth(depth, assignment) => (depth - 1, !assignment),
+            _ => panic!("Were speculating on unassigned variable"),
         };
 
-        if DEBUG_PRINT {
-            println!("Node {} backtracking to var {} at depth {}", self.id, var, current_depth);
-        }
-        self.substitute(var, assignment, true,  current_depth);
+        sim_log!(self.id, self.clock, LogLevel::Debug, "Backtracking to var {} at depth {}", var, current_depth);
+        self.spend(|model| model.decision_energy_pj);  // flipping the speculation is a decision too
+        self.substitute(var, assignment, true,  current_depth, AssignmentCause::Conflict);
     }
 
-    fn sat(&mut self, clock: u64, network: &mut MessageQueue) {
+    fn sat(&mut self, clock: u64, network: &mut MessageQueue<K>) {
+        if self.verify_due() {
+            assert!(self.table.is_satisfied_by(&self.assignment()), "Node {} found a model that falsifies a clause", self.id);
+        }
+        self.branches_explored += 1;  // a model is a leaf of its own, even when unit propagation alone reached it
+        if self.count_models {
+            // every variable is assigned, so each leaf is a distinct model
+            self.models_found += 1;
+            if self.first_model.is_none() {
+                self.first_model = Some(self.assignment());
+            }
+            if self.speculative_branches.is_empty() {
+                self.clear_state();
+            } else {
+                self.backtrack();
+            }
+            return;
+        }
         println!("Node {} is SAT", self.id);
         // self.state = NodeState::AwaitingFork;
         self.send_message(clock, network, MessageDestination::Broadcast, Message::Success);
     }
 
     // ----- Networking interface ----- //
-    pub fn recieve_message(&mut self, from: MessageDestination, message: Message) {
+    pub fn recieve_message(&mut self, from: MessageDestination, message: Message<K>) {
         match from {
             MessageDestination::Neighbor(id) => {
-                assert!(self.neighbors.contains(&id), "Node {:?} received message from non-neighbor", self);
+                assert!(self.is_neighbor(id), "Node {:?} received message from non-neighbor", self);
             },
             _ => panic!("{:?} received unexpected message source", self)
         }
+        if let Message::UnfinishedMessage = message {
+            // the fork is still streaming over the link, hold this node so nobody else forks to it
+            if self.state == NodeState::AwaitingFork {
+                self.state = NodeState::RecievingFork;
+            }
+            return;
+        }
         assert!(self.incoming_message.is_none(), "Node received multiple messages in one cycle");
+        if let Message::Fork {..} = message {
+            self.wakeups += 1;
+            self.wakeup_left = self.wakeup_penalty;
+        }
         self.incoming_message = Some(message);
         if self.state == NodeState::AwaitingFork {
             match self.incoming_message {
@@ -426,16 +1016,91 @@ impl Node {
         }
     }
 
-    fn send_message(&self, clock: u64, network: &mut MessageQueue, dest: MessageDestination, message: Message) {
-        if DEBUG_PRINT {
-            println!("Node {} sending message {:?} to {:?}", self.id, message, dest);
-        }
+    fn send_message(&self, clock: u64, network: &mut MessageQueue<K>, dest: MessageDestination, message: Message<K>) {
+        sim_log!(self.id, clock, LogLevel::Debug, "Sending message {:?} to {}", message, dest);
         network.start_message(clock, MessageDestination::Neighbor(self.id), dest, message);
     }
 } 
-impl Debug for Node {
+impl<const K: usize> Debug for Node<K> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Node id: {}, state: {:?}, neighbors: {:?}", self.id, self.state, self.neighbors)
     }
     
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline(parallel_clauses: usize, cycles_per_eval: u64, depth: usize) -> PipelineModel {
+        PipelineModel { parallel_clauses, cycles_per_eval, depth, layout: ClauseLayout::Sequential }
+    }
+
+    #[test]
+    fn scans_take_a_batch_of_clauses_per_evaluation() {
+        let model = pipeline(8, 2, 1);
+        assert_eq!(model.latency(100), 26, "13 batches of 8 clauses, the last one partly empty");
+        assert_eq!(model.latency(96), 24);
+        assert_eq!([0, 7, 8, 99].map(|clause| model.reach_time(clause, 100)), [2, 2, 4, 26]);
+    }
+
+    #[test]
+    fn propagation_chains_overlap_up_to_the_pipeline_depth() {
+        let latency = pipeline(8, 2, 1).latency(100);
+        assert_eq!(pipeline(8, 2, 1).cost_for_k_propagations(4, 100), 4 * latency, "one scan at a time");
+        assert_eq!(pipeline(8, 2, 4).cost_for_k_propagations(4, 100), 3 * 2 + latency, "each scan one batch behind the last");
+        // the third scan waits for the first to leave the pipeline, the fourth is a batch behind it
+        assert_eq!(pipeline(8, 2, 2).cost_for_k_propagations(4, 100), latency + 2 + latency);
+        assert_eq!(pipeline(8, 2, 2).cost_for_k_propagations(0, 100), 0);
+    }
+
+    #[test]
+    fn interleaved_banks_change_when_a_clause_is_reached() {
+        let layout = ClauseLayout::Interleaved { banks: 4 };
+        // 10 clauses over 4 banks: banks 0 and 1 hold 3 clauses, banks 2 and 3 hold 2
+        assert_eq!((0..10).map(|position| layout.clause_at(position, 10)).collect::<Vec<_>>(), vec![0, 4, 8, 1, 5, 9, 2, 6, 3, 7]);
+        assert!((0..10).all(|clause| layout.clause_at(layout.position_of(clause, 10), 10) == clause));
+        let model = PipelineModel { layout, ..pipeline(1, 1, 1) };
+        assert_eq!([0, 1, 9, 7].map(|clause| model.reach_time(clause, 10)), [1, 4, 6, 10]);
+        assert_eq!(model.latency(10), pipeline(1, 1, 1).latency(10), "the layout only reorders the scan");
+    }
+
+    #[test]
+    #[should_panic(expected = "Node 0 has True for var 2 in clause 0 but its assignment gives Symbolic")]
+    fn check_table_catches_a_corrupted_term() {
+        let mut node: Node = Node::new(0, ClauseTable::from_clauses(3, &[vec![1, 2, 3], vec![-1, 2, -3]]), 100);
+        node.check_table();  // nothing assigned, nothing set
+        node.table.clause_table[0][1].1 = TermState::True;
+        node.check_table();
+    }
+
+    #[test]
+    fn var_0_is_never_a_decision() {
+        let mut node: Node = Node::new(0, ClauseTable::from_clauses(3, &[vec![1, 2, 3], vec![-1, 2, -3]]), 100);
+        assert_eq!(node.assignment_time[0], SpeculativeDepth::Unassigned, "the padding var is never assigned");
+        assert_eq!(node.get_next_var(), Some(1));
+        assert_eq!(node.get_next_vars(5), vec![1, 2, 3]);
+        node.set_preferred_vars(vec![0, 3]);
+        assert_eq!(node.get_next_var(), Some(3), "a preferred var 0 is skipped");
+        assert_eq!(node.get_next_vars(5), vec![3, 1, 2]);
+        for var in 1..=3 {
+            node.assignment_time[var] = SpeculativeDepth::Depth(var as VarId, false);
+        }
+        assert_eq!(node.get_next_var(), None, "only var 0 is left unassigned");
+        assert!(node.get_next_vars(5).is_empty());
+    }
+
+    #[test]
+    fn sampled_verification_checks_every_nth_point() {
+        let mut node: Node = Node::new(0, ClauseTable::from_clauses(3, &[vec![1, 2, 3]]), 100);
+        let mut due = |node: &mut Node, level| {
+            node.set_verify_level(level);
+            node.verify_points = 0;
+            (0..6).map(|_| node.verify_due()).collect::<Vec<bool>>()
+        };
+        assert_eq!(due(&mut node, VerifyLevel::Off), vec![false; 6]);
+        assert_eq!(due(&mut node, VerifyLevel::Sampled(3)), vec![false, false, true, false, false, true]);
+        assert_eq!(due(&mut node, VerifyLevel::Sampled(1)), vec![true; 6]);
+        assert_eq!(due(&mut node, VerifyLevel::Full), vec![true; 6]);
+    }
+}
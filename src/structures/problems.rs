@@ -0,0 +1,263 @@
+/*
+Encoders that turn combinatorial problems into 3-SAT, used to build crafted benchmarks, and generators for
+random formulas with more structure than uniform random 3-SAT.
+
+Clauses are collected in DIMACS form (1-indexed, negative = negated) and any clause longer than
+CLAUSE_LENGTH is split with fresh Tseitin variables before it is loaded into a `ClauseTable`.
+*/
+
+use rand::{rngs::StdRng, seq::index::sample, Rng, SeedableRng};
+
+use super::{clause_table::ClauseTable, minisat::minisat_table, util_types::{VarId, CLAUSE_LENGTH}};
+
+/// Collects clauses over a growing set of variables, keeping every clause at most CLAUSE_LENGTH long
+pub(super) struct CnfBuilder {
+    num_vars: usize,
+    clauses: Vec<Vec<i32>>,
+} impl CnfBuilder {
+    pub(super) fn new(num_vars: usize) -> Self {
+        CnfBuilder { num_vars, clauses: Vec::new() }
+    }
+
+    fn new_var(&mut self) -> i32 {
+        self.num_vars += 1;
+        self.num_vars as i32
+    }
+
+    /// Adds a clause, chaining long ones: (a b c d) becomes (a b t) (-t c d)
+    pub(super) fn add_clause(&mut self, mut literals: Vec<i32>) {
+        while literals.len() > CLAUSE_LENGTH {
+            let link = self.new_var();
+            let rest = literals.split_off(CLAUSE_LENGTH - 1);
+            literals.push(link);
+            self.clauses.push(literals);
+            literals = std::iter::once(-link).chain(rest).collect();
+        }
+        self.clauses.push(literals);
+    }
+
+    pub(super) fn build(self) -> ClauseTable {
+        ClauseTable::from_clauses(self.num_vars, &self.clauses)
+    }
+}
+
+/// Variable saying vertex `v` is at position `i` of the path
+fn order_var(v: usize, i: usize, num_vertices: usize) -> i32 {
+    (v * num_vertices + i + 1) as i32
+}
+
+/// Variables of `hamiltonian_path_sat` on `n` vertices: the order variables plus the Tseitin links that split
+/// the 2n "somewhere on the path" / "position taken" clauses of length n
+fn hamiltonian_vars(n: usize) -> usize {
+    n * n + 2 * n * n.saturating_sub(CLAUSE_LENGTH).div_ceil(CLAUSE_LENGTH - 2)
+}
+
+/// Encodes "the undirected graph has a Hamiltonian path" with order variables x(v, i) = vertex v is i-th on the path.
+/// Every vertex takes exactly one position, every position holds exactly one vertex and consecutive positions
+/// must be joined by an edge. Variables 1..=n*n are the order variables, anything above is a Tseitin link.
+/// The variables have to fit in a `VarId`, which limits the graph to 10 vertices (240 variables); panics above that.
+pub fn hamiltonian_path_sat(edges: &[(usize, usize)], num_vertices: usize) -> ClauseTable {
+    let n = num_vertices;
+    assert!(hamiltonian_vars(n) < VarId::MAX as usize, "A Hamiltonian path over {} vertices needs {} variables, more than a VarId can hold (at most 10 vertices)", n, hamiltonian_vars(n));
+    let x = |v: usize, i: usize| order_var(v, i, n);
+    let mut adjacent = vec![vec![false; n]; n];
+    for &(a, b) in edges {
+        assert!(a < n && b < n, "Edge ({}, {}) out of range", a, b);
+        adjacent[a][b] = true;
+        adjacent[b][a] = true;
+    }
+    let mut cnf = CnfBuilder::new(n * n);
+    for v in 0..n {
+        cnf.add_clause((0..n).map(|i| x(v, i)).collect());  // v is somewhere on the path
+        for i in 0..n {
+            for j in i + 1..n {
+                cnf.add_clause(vec![-x(v, i), -x(v, j)]);  // but only once
+            }
+        }
+    }
+    for i in 0..n {
+        cnf.add_clause((0..n).map(|v| x(v, i)).collect());  // position i is taken
+        for v in 0..n {
+            for u in v + 1..n {
+                cnf.add_clause(vec![-x(v, i), -x(u, i)]);  // by a single vertex
+            }
+        }
+    }
+    // neighbors on the path must be neighbors in the graph
+    for i in 0..n.saturating_sub(1) {
+        for v in 0..n {
+            for u in 0..n {
+                if u != v && !adjacent[v][u] {
+                    cnf.add_clause(vec![-x(v, i), -x(u, i + 1)]);
+                }
+            }
+        }
+    }
+    cnf.build()
+}
+
+/// Reads the path back out of a model of `hamiltonian_path_sat` (indexed by variable, var 0 is padding).
+/// Returns None if some position is not held by exactly one vertex.
+pub fn decode_path(model: &[Option<bool>], num_vertices: usize) -> Option<Vec<usize>> {
+    let n = num_vertices;
+    (0..n).map(|i| {
+        let mut at_position = (0..n).filter(|&v| model.get(order_var(v, i, n) as usize).copied().flatten() == Some(true));
+        match (at_position.next(), at_position.next()) {
+            (Some(v), None) => Some(v),
+            _ => None,
+        }
+    }).collect()
+}
+
+/// Random 3-SAT with planted community structure, closer to industrial instances than uniform random 3-SAT.
+/// Community c owns variables c*vars_per_community+1 ..= (c+1)*vars_per_community. Each community gets
+/// `intra_clauses` clauses over its own variables, and `inter_clauses` binary clauses join one variable of each
+/// of two different communities. `inter_clauses / (intra_clauses * num_communities)` controls how modular the
+/// formula is: with 0 the communities are independent sub-formulas.
+pub fn community_sat(num_communities: usize, vars_per_community: usize, intra_clauses: usize, inter_clauses: usize, seed: u64) -> ClauseTable {
+    assert!(vars_per_community >= CLAUSE_LENGTH, "A community needs at least {} variables", CLAUSE_LENGTH);
+    assert!(inter_clauses == 0 || num_communities >= 2, "Inter-community clauses need two communities");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut literal = |community: usize, index: usize, rng: &mut StdRng| {
+        let var = (community * vars_per_community + index + 1) as i32;
+        if rng.random() { -var } else { var }
+    };
+    let mut clauses = Vec::with_capacity(num_communities * intra_clauses + inter_clauses);
+    for community in 0..num_communities {
+        for _ in 0..intra_clauses {
+            let vars = sample(&mut rng, vars_per_community, CLAUSE_LENGTH);
+            clauses.push(vars.iter().map(|index| literal(community, index, &mut rng)).collect());
+        }
+    }
+    for _ in 0..inter_clauses {
+        let communities = sample(&mut rng, num_communities, 2);
+        clauses.push(communities.iter().map(|community| {
+            let index = rng.random_range(0..vars_per_community);
+            literal(community, index, &mut rng)
+        }).collect());
+    }
+    ClauseTable::from_clauses(num_communities * vars_per_community, &clauses)
+}
+
+/// `community_sat` with the number of intra-community clauses picked so that about half of the formulas with
+/// this shape are satisfiable (the hard region), keeping `inter_clauses = inter_ratio * intra_clauses * num_communities`.
+/// The SAT fraction is estimated with MiniSat over `samples` formulas per candidate clause count.
+pub fn community_sat_near_threshold(num_communities: usize, vars_per_community: usize, inter_ratio: f64, samples: usize, seed: u64) -> ClauseTable {
+    let inter = |intra: usize| (inter_ratio * (intra * num_communities) as f64).round() as usize;
+    let sat_fraction = |intra: usize| {
+        let sat = (0..samples as u64)
+            .filter(|&sample| minisat_table(&community_sat(num_communities, vars_per_community, intra, inter(intra), seed.wrapping_add(sample + 1))).0)
+            .count();
+        sat as f64 / samples.max(1) as f64
+    };
+    // fewer clauses means more models, so the SAT fraction falls as intra grows
+    let (mut lo, mut hi) = (1, 10 * vars_per_community);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if sat_fraction(mid) > 0.5 {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    community_sat(num_communities, vars_per_community, lo, inter(lo), seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::{community::detect_communities, satswarm::SatSwarm};
+
+    /// Variables of every clause, without the padding
+    fn clause_vars(table: &ClauseTable) -> Vec<Vec<usize>> {
+        table.clause_table.iter().map(|clause| clause.iter().map(|(term, _)| term.var as usize).filter(|&var| var != 0).collect()).collect()
+    }
+
+    /// Solves `hamiltonian_path_sat` on the simulator and decodes its model, checking MiniSat agrees it is SAT
+    fn solve_path(edges: &[(usize, usize)], num_vertices: usize) -> Vec<usize> {
+        let table = hamiltonian_path_sat(edges, num_vertices);
+        assert!(clause_vars(&table).iter().all(|vars| vars.len() <= 3), "a clause has more than 3 literals");
+        assert!(minisat_table(&table).0, "MiniSat finds no path");
+        let result = SatSwarm::dense(table, 4, 100).test_satisfiability().unwrap();
+        assert!(result.simulated_result, "the simulator finds no path");
+        decode_path(&result.model.expect("SAT without a verified model"), num_vertices).expect("model is not a path")
+    }
+
+    #[test]
+    fn complete_graph_has_a_hamiltonian_path() {
+        let edges: Vec<(usize, usize)> = (0..5).flat_map(|a| (a + 1..5).map(move |b| (a, b))).collect();
+        let mut path = solve_path(&edges, 5);
+        path.sort();
+        assert_eq!(path, vec![0, 1, 2, 3, 4], "every vertex appears once");
+    }
+
+    #[test]
+    fn path_graph_decodes_to_its_only_path() {
+        let path = solve_path(&[(0, 1), (1, 2)], 3);
+        assert!(path == vec![0, 1, 2] || path == vec![2, 1, 0], "{:?} is not 0-1-2", path);  // the same path either way
+    }
+
+    #[test]
+    fn single_vertex_is_its_own_path() {
+        assert_eq!(solve_path(&[], 1), vec![0]);
+    }
+
+    #[test]
+    fn ten_vertices_is_the_largest_hamiltonian_encoding() {
+        let ring: Vec<(usize, usize)> = (0..10).map(|v| (v, (v + 1) % 10)).collect();
+        assert_eq!(hamiltonian_path_sat(&ring, 10).number_of_vars(), 240);
+    }
+
+    #[test]
+    #[should_panic(expected = "more than a VarId can hold")]
+    fn eleven_vertices_do_not_fit_in_a_var_id() {
+        hamiltonian_path_sat(&[], 11);
+    }
+
+    #[test]
+    fn community_sat_has_the_requested_shape() {
+        let table = community_sat(3, 8, 20, 5, 1);
+        assert_eq!((table.num_clauses, table.number_of_vars()), (3 * 20 + 5, 24));
+        let community = |var: usize| (var - 1) / 8;
+        let vars = clause_vars(&table);
+        for (i, clause) in vars.iter().enumerate() {
+            if i < 60 {
+                assert_eq!(clause.len(), CLAUSE_LENGTH, "intra clause {}", i);
+                assert!(clause.iter().all(|&var| community(var) == i / 20), "intra clause {} leaves community {}: {:?}", i, i / 20, clause);
+            } else {
+                assert_eq!(clause.len(), 2, "inter clause {}", i);
+                assert_ne!(community(clause[0]), community(clause[1]), "inter clause {} stays in one community", i);
+            }
+        }
+    }
+
+    #[test]
+    fn community_sat_follows_the_seed() {
+        assert_eq!(community_sat(3, 8, 20, 5, 7).clause_table, community_sat(3, 8, 20, 5, 7).clause_table);
+        assert_ne!(community_sat(3, 8, 20, 5, 7).clause_table, community_sat(3, 8, 20, 5, 8).clause_table);
+        let near = |seed| community_sat_near_threshold(2, 8, 0.1, 4, seed);
+        assert_eq!(near(3).clause_table, near(3).clause_table);
+    }
+
+    #[test]
+    fn independent_communities_are_detected() {
+        // without inter clauses every community is a sub-formula of its own
+        let communities = detect_communities(&community_sat(3, 8, 30, 0, 2));
+        for block in 0..3 {
+            let first = communities[block * 8 + 1];
+            assert!((block * 8 + 1..=block * 8 + 8).all(|var| communities[var] == first), "community {} was split: {:?}", block, communities);
+        }
+        assert!(communities[1] != communities[9] && communities[9] != communities[17] && communities[1] != communities[17]);
+    }
+
+    #[test]
+    fn near_threshold_keeps_the_inter_ratio() {
+        let table = community_sat_near_threshold(2, 8, 0.25, 4, 0);
+        let inter = clause_vars(&table).iter().filter(|vars| vars.len() == 2).count();
+        let intra = table.num_clauses - inter;
+        assert_eq!(intra % 2, 0, "both communities get the same number of clauses");
+        assert_eq!(inter, (0.25 * intra as f64).round() as usize);
+        assert_eq!(table.number_of_vars(), 16);
+    }
+}
+
@@ -1,30 +1,111 @@
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
 
-use crate::{structures::clause_table::{Term, TermState}, TestConfig, TestResult, Topology};
+use indicatif::ProgressBar;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use super::{clause_table::ClauseTable, message::{Message, MessageDestination, MessageQueue}, node::Node, util_types::{NodeId, VarId, DEBUG_PRINT}};
+use crate::{TestConfig, TestResult, Topology};
 
+use super::{clause_table::ClauseTable, message::{Message, MessageDestination, MessageQueue}, node::{AssignmentCause, ForkOrigin, Node, CONFLICT_LEVELS}, logging::{sim_log, LogLevel}, util_types::{EnergyModel, ForkPolicy, NodeId, SearchMode, VarId, VerifyLevel, CLAUSE_LENGTH}};
 
-struct Arena {
-    nodes: Vec<Node>,
-} impl Arena {
+
+/// Why an edge list file could not be turned into a network
+#[derive(Debug)]
+pub enum EdgeListError {
+    Io(std::io::Error),
+    BadLine { line: usize, text: String },                 // anything but two node ids
+    SelfLoop { line: usize, node: NodeId },
+    DuplicateEdge { line: usize, a: NodeId, b: NodeId },   // either direction of an edge already listed
+    MissingNode(NodeId),                                   // node ids have to be contiguous from 0
+} impl std::fmt::Display for EdgeListError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EdgeListError::Io(e) => write!(f, "{}", e),
+            EdgeListError::BadLine { line, text } => write!(f, "line {}: expected two node ids, got {:?}", line, text),
+            EdgeListError::SelfLoop { line, node } => write!(f, "line {}: node {} is linked to itself", line, node),
+            EdgeListError::DuplicateEdge { line, a, b } => write!(f, "line {}: edge {}-{} is listed twice", line, a, b),
+            EdgeListError::MissingNode(node) => write!(f, "node {} has no edges, ids must be contiguous from 0", node),
+        }
+    }
+} impl std::error::Error for EdgeListError {}
+impl From<std::io::Error> for EdgeListError {
+    fn from(e: std::io::Error) -> Self {
+        EdgeListError::Io(e)
+    }
+}
+
+/// Why a network can't be simulated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimulationError {
+    NoNodes,    // e.g. Dense(0) or an empty edge list, there is nobody to search
+    BadEdgeList { path: PathBuf, reason: String },   // the edge list of a Custom topology can't be read, see read_edge_list
+} impl std::fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulationError::NoNodes => write!(f, "the network has no nodes to search with"),
+            SimulationError::BadEdgeList { path, reason } => write!(f, "invalid edge list {:?}: {}", path, reason),
+        }
+    }
+} impl std::error::Error for SimulationError {}
+
+/// Reads an undirected network from a file with one `u v` pair of node ids per line (blank lines and `#` comments
+/// are skipped). Returns the number of nodes and the edges.
+pub fn read_edge_list(path: &Path) -> Result<(usize, Vec<(NodeId, NodeId)>), EdgeListError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut edges = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let bad_line = || EdgeListError::BadLine { line: index + 1, text: text.to_string() };
+        let ids = text.split_whitespace().map(|token| token.parse::<NodeId>()).collect::<Result<Vec<_>, _>>().map_err(|_| bad_line())?;
+        let &[a, b] = ids.as_slice() else { return Err(bad_line()) };
+        if a == b {
+            return Err(EdgeListError::SelfLoop { line: index + 1, node: a });
+        }
+        if !seen.insert((a.min(b), a.max(b))) {
+            return Err(EdgeListError::DuplicateEdge { line: index + 1, a, b });
+        }
+        edges.push((a, b));
+    }
+    let num_nodes = edges.iter().map(|&(a, b)| a.max(b) + 1).max().unwrap_or(0);
+    let mut linked = vec![false; num_nodes];
+    for &(a, b) in edges.iter() {
+        linked[a] = true;
+        linked[b] = true;
+    }
+    if let Some(node) = linked.iter().position(|&l| !l) {
+        return Err(EdgeListError::MissingNode(node));
+    }
+    Ok((num_nodes, edges))
+}
+
+struct Arena<const K: usize = CLAUSE_LENGTH> {
+    nodes: Vec<Node<K>>,
+} impl<const K: usize> Arena<K> {
     pub fn new() -> Self {
         Arena {
             nodes: Vec::new()
         }
     }
 
-    pub fn from_nodes(nodes: Vec<Node>) -> Self {
+    pub fn from_nodes(nodes: Vec<Node<K>>) -> Self {
         Arena {
             nodes
         }
     }
 
 
-    pub fn get_node(&self, id: NodeId) -> &Node {self.nodes.get(id).expect("Node not found")}
-    pub fn get_node_mut(&mut self, id: NodeId) -> &mut Node {self.nodes.get_mut(id).expect("Node not found")}
-    pub fn get_node_opt(&self, id: NodeId) -> Option<&Node> {self.nodes.get(id)}
-    pub fn get_node_mut_opt(&mut self, id: NodeId) -> Option<&mut Node> {self.nodes.get_mut(id)}
+    pub fn get_node(&self, id: NodeId) -> &Node<K> {self.nodes.get(id).expect("Node not found")}
+    pub fn get_node_mut(&mut self, id: NodeId) -> &mut Node<K> {self.nodes.get_mut(id).expect("Node not found")}
+    pub fn get_node_opt(&self, id: NodeId) -> Option<&Node<K>> {self.nodes.get(id)}
+    pub fn get_node_mut_opt(&mut self, id: NodeId) -> Option<&mut Node<K>> {self.nodes.get_mut(id)}
 
     pub fn add_neighbor(&mut self, node_id: NodeId, neighbor_id: NodeId) {
         let n1 = self.nodes.get_mut(node_id).expect("Node not found");
@@ -40,42 +121,477 @@ struct Arena {
         let n2 = self.nodes.get_mut(neighbor_id).expect("Neighbor not found");
         n2.remove_neighbor(node_id);
     }
+
+    /// Nodes in breadth first order from `start`, so nodes close in the list are close in the network.
+    /// Nodes that can't be reached are appended at the end.
+    fn bfs_order(&self, start: NodeId) -> Vec<NodeId> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut order = Vec::with_capacity(self.nodes.len());
+        for root in std::iter::once(start).chain(0..self.nodes.len()) {
+            if seen[root] {
+                continue;
+            }
+            seen[root] = true;
+            let mut queue = std::collections::VecDeque::from([root]);
+            while let Some(id) = queue.pop_front() {
+                order.push(id);
+                for neighbor in self.get_node(id).neighbors() {
+                    if !seen[neighbor] {
+                        seen[neighbor] = true;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+        order
+    }
+
+    // ----- network analysis ----- //
+    /// Number of links between every pair of nodes (parallel links, e.g. on a 2-wide torus, count separately)
+    fn link_counts(&self) -> Vec<Vec<usize>> {
+        let n = self.nodes.len();
+        let mut links = vec![vec![0; n]; n];
+        for node in self.nodes.iter() {
+            for neighbor in node.neighbors() {
+                links[node.id][neighbor] += 1;
+            }
+        }
+        links
+    }
+
+    fn cut_size(links: &Vec<Vec<usize>>, side: &Vec<bool>) -> usize {
+        let mut cut = 0;
+        for a in 0..side.len() {
+            for b in (a + 1)..side.len() {
+                if side[a] != side[b] {
+                    cut += links[a][b];
+                }
+            }
+        }
+        cut
+    }
+
+    /// Minimum number of links cut when the nodes are split into two halves (sizes differ by at most one).
+    /// Exact for up to 20 nodes, otherwise the best of a few Kernighan-Lin refinements.
+    pub fn bisection_width(&self) -> usize {
+        let n = self.nodes.len();
+        if n < 2 {
+            return 0;
+        }
+        let links = self.link_counts();
+        if n <= 20 {
+            // node 0 is always on the first side, which halves the search
+            let mut best = usize::MAX;
+            for mask in 0..(1u32 << (n - 1)) {
+                let size = mask.count_ones() as usize + 1;
+                if size != n / 2 && size != (n + 1) / 2 {
+                    continue;
+                }
+                let side: Vec<bool> = (0..n).map(|i| i == 0 || (mask >> (i - 1)) & 1 == 1).collect();
+                best = best.min(Self::cut_size(&links, &side));
+            }
+            return best;
+        }
+        let starts: [Vec<bool>; 2] = [
+            (0..n).map(|i| i < n / 2).collect(),        // contiguous ids, a good guess for grids and tori
+            (0..n).map(|i| i % 2 == 0).collect(),       // interleaved ids
+        ];
+        starts.into_iter()
+            .map(|mut side| {
+                Self::kernighan_lin(&links, &mut side);
+                Self::cut_size(&links, &side)
+            })
+            .min()
+            .unwrap()
+    }
+
+    /// Total bandwidth across the bisection given the capacity of a single link
+    pub fn bisection_bandwidth(&self, bytes_per_link_per_cycle: usize) -> usize {
+        self.bisection_width() * bytes_per_link_per_cycle
+    }
+
+    /// Largest number of hops between any two nodes, None if the network is disconnected
+    pub fn diameter(&self) -> Option<usize> {
+        let n = self.nodes.len();
+        let mut diameter = 0;
+        for start in 0..n {
+            let mut distance = vec![usize::MAX; n];
+            distance[start] = 0;
+            let mut queue = std::collections::VecDeque::from([start]);
+            while let Some(id) = queue.pop_front() {
+                for neighbor in self.get_node(id).neighbors() {
+                    if distance[neighbor] == usize::MAX {
+                        distance[neighbor] = distance[id] + 1;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            diameter = diameter.max(*distance.iter().max().unwrap_or(&0));
+        }
+        (diameter != usize::MAX).then_some(diameter)
+    }
+
+    /// Betweenness of every node: the shortest paths between pairs of other nodes that run through it, a pair with
+    /// several shortest paths splitting its share evenly. Pairs are unordered, so the center of a star with n nodes
+    /// gets (n-1)(n-2)/2. High values mark the nodes forks pile up behind. Brandes' algorithm, O(V*E).
+    pub fn betweenness_centrality(&self) -> Vec<f64> {
+        self.brandes(0..self.nodes.len())
+    }
+
+    /// `betweenness_centrality` estimated from the shortest paths out of `samples` random sources, scaled up to all
+    /// of them. Exact when `samples` covers every node.
+    pub fn approx_betweenness(&self, samples: usize, seed: u64) -> Vec<f64> {
+        let n = self.nodes.len();
+        if samples >= n {
+            return self.betweenness_centrality();
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let scale = n as f64 / samples as f64;
+        self.brandes(rand::seq::index::sample(&mut rng, n, samples).into_iter())
+            .into_iter()
+            .map(|b| b * scale)
+            .collect()
+    }
+
+    /// Brandes' accumulation of the dependencies of every node on the shortest paths out of `sources`
+    fn brandes(&self, sources: impl Iterator<Item = NodeId>) -> Vec<f64> {
+        let n = self.nodes.len();
+        let adjacency: Vec<Vec<NodeId>> = self.nodes.iter().map(|node| {
+            let mut neighbors: Vec<NodeId> = node.neighbors().collect();
+            neighbors.sort();
+            neighbors.dedup();  // parallel links don't make a second path
+            neighbors
+        }).collect();
+        let mut betweenness = vec![0.0; n];
+        for source in sources {
+            let mut stack = Vec::with_capacity(n);
+            let mut predecessors: Vec<Vec<NodeId>> = vec![Vec::new(); n];
+            let mut paths = vec![0.0; n];       // shortest paths from the source
+            let mut distance = vec![usize::MAX; n];
+            paths[source] = 1.0;
+            distance[source] = 0;
+            let mut queue = std::collections::VecDeque::from([source]);
+            while let Some(v) = queue.pop_front() {
+                stack.push(v);
+                for &w in adjacency[v].iter() {
+                    if distance[w] == usize::MAX {
+                        distance[w] = distance[v] + 1;
+                        queue.push_back(w);
+                    }
+                    if distance[w] == distance[v] + 1 {
+                        paths[w] += paths[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+            let mut dependency = vec![0.0; n];
+            while let Some(w) = stack.pop() {
+                for &v in predecessors[w].iter() {
+                    dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+                }
+                if w != source {
+                    betweenness[w] += dependency[w];
+                }
+            }
+        }
+        betweenness.iter().map(|b| b / 2.0).collect()  // every pair was counted from both ends
+    }
+
+    /// Refines a balanced partition in place by swapping pairs of nodes until no pass improves the cut
+    fn kernighan_lin(links: &Vec<Vec<usize>>, side: &mut Vec<bool>) {
+        let n = side.len();
+        loop {
+            // D[v] = external cost - internal cost
+            let mut d: Vec<i64> = (0..n).map(|v| {
+                (0..n).map(|u| if side[u] != side[v] { links[v][u] as i64 } else { -(links[v][u] as i64) }).sum()
+            }).collect();
+            let mut locked = vec![false; n];
+            let mut swaps = Vec::new();
+            let mut gains = Vec::new();
+            for _ in 0..(n / 2) {
+                // greedily take the best node on the first side, then its best partner on the other side
+                let a = (0..n).filter(|&v| side[v] && !locked[v]).max_by_key(|&v| d[v]);
+                let Some(a) = a else { break };
+                let b = (0..n).filter(|&v| !side[v] && !locked[v]).max_by_key(|&v| d[v] - 2 * links[a][v] as i64);
+                let Some(b) = b else { break };
+                gains.push(d[a] + d[b] - 2 * links[a][b] as i64);
+                swaps.push((a, b));
+                locked[a] = true;
+                locked[b] = true;
+                for v in 0..n {
+                    if locked[v] {
+                        continue;
+                    }
+                    let (toward, away) = if side[v] { (a, b) } else { (b, a) };
+                    d[v] += 2 * links[v][toward] as i64 - 2 * links[v][away] as i64;
+                }
+            }
+            // apply the prefix of swaps with the largest total gain
+            let (mut best_k, mut best_gain, mut total) = (0, 0, 0);
+            for (k, gain) in gains.iter().enumerate() {
+                total += gain;
+                if total > best_gain {
+                    best_gain = total;
+                    best_k = k + 1;
+                }
+            }
+            if best_k == 0 {
+                break;
+            }
+            for &(a, b) in swaps.iter().take(best_k) {
+                side[a] = false;
+                side[b] = true;
+            }
+        }
+    }
 }
-pub struct SatSwarm {
-    arena: Arena,
-    clauses: ClauseTable,
-    messages: MessageQueue,
+pub struct SatSwarm<const K: usize = CLAUSE_LENGTH> {
+    arena: Arena<K>,
+    clauses: ClauseTable<K>,
+    messages: MessageQueue<K>,
     start_time: u64,
     done: bool,
+    sat_node: Option<NodeId>,
     idle_cycles: u64,
     busy_cycles: u64,
+    progress: Option<ProgressBar>,
+    start_nodes: Vec<NodeId>,
+    clock: u64,
+    checkpoint: Option<(u64, PathBuf)>,
+    fork_policy: ForkPolicy,
+    search_mode: SearchMode,
+    timeout: Option<u64>,       // cycles after which the search gives up, None = run until done
+    timed_out: bool,
+    interrupt: Option<Arc<AtomicBool>>,   // set from outside (Ctrl-C) to stop the search like a timeout
+}
+/// Shape of a network, printed by `--topology_stats`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologyStats {
+    pub nodes: usize,
+    pub links: usize,                           // parallel links count separately
+    pub diameter: Option<usize>,                // None if the network is disconnected
+    pub bisection_width: usize,
+    pub top_betweenness: Vec<(NodeId, f64)>,    // the most central nodes, most central first
+    pub betweenness_exact: bool,                // false if it was estimated from sampled sources
+} impl std::fmt::Display for TopologyStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Nodes: {}", self.nodes)?;
+        writeln!(f, "Links: {}", self.links)?;
+        writeln!(f, "Diameter: {}", self.diameter.map_or(String::from("disconnected"), |d| d.to_string()))?;
+        writeln!(f, "Bisection width: {}", self.bisection_width)?;
+        writeln!(f, "Highest betweenness{}:", if self.betweenness_exact { "" } else { " (sampled)" })?;
+        for (node, betweenness) in self.top_betweenness.iter() {
+            writeln!(f, "  node {}: {:.1}", node, betweenness)?;
+        }
+        Ok(())
+    }
 }
-impl SatSwarm {
-    fn build(arena: Arena, clause_table: ClauseTable) -> Self {
+/// Networks larger than this get their betweenness estimated from `BETWEENNESS_SAMPLES` sources
+const EXACT_BETWEENNESS_NODES: usize = 1024;
+const BETWEENNESS_SAMPLES: usize = 32;
+
+/// How often `test_satisfiability` looks at the interrupt flag
+const INTERRUPT_CHECK_INTERVAL: u64 = 100_000;
+/// Give up on a simulation after this many cycles unless configured otherwise
+pub const DEFAULT_TIMEOUT_CYCLES: u64 = 150_000_000;
+impl<const K: usize> SatSwarm<K> {
+    fn build(arena: Arena<K>, clause_table: ClauseTable<K>) -> Self {
         SatSwarm {
             arena,
             clauses: clause_table,
             messages: MessageQueue::new(),
             done: false,
+            sat_node: None,
             start_time: 0,
             idle_cycles: 0,
             busy_cycles: 0,
+            progress: None,
+            start_nodes: vec![0],
+            clock: 0,
+            checkpoint: None,
+            fork_policy: ForkPolicy::FirstNeighbor,
+            search_mode: SearchMode::FirstModel,
+            timeout: Some(DEFAULT_TIMEOUT_CYCLES),
+            timed_out: false,
+            interrupt: None,
         }
     }
 
-    pub fn _blank(clause_table: ClauseTable) -> Self {
+    /// Kickstarts the search from several nodes at once. Each start node fixes a different polarity of the
+    /// first log2(n) variables so together they still cover the whole search space.
+    pub fn set_start_nodes(&mut self, start_nodes: Vec<NodeId>) {
+        assert!(start_nodes.len().is_power_of_two(), "Number of start nodes must be a power of two");
+        assert!(start_nodes.iter().all(|&id| id < self.arena.nodes.len()), "Start node out of range");
+        self.start_nodes = start_nodes;
+    }
+
+    /// Splits the variables between the nodes by community (see `community::detect_communities`) so that nodes
+    /// working on the same community are neighbors. Communities are laid out largest first over a breadth first
+    /// walk of the network, each getting a contiguous run of nodes proportional to its size (at least one, nodes on
+    /// a boundary get both communities). Returns the variables of each node.
+    pub fn assign_variables_to_nodes(&self, communities: &[usize]) -> Vec<Vec<VarId>> {
+        let num_nodes = self.arena.nodes.len();
+        if num_nodes == 0 {
+            return Vec::new();
+        }
+        let mut members: HashMap<usize, Vec<VarId>> = HashMap::new();
+        for (var, &community) in communities.iter().enumerate().skip(1) {  // var 0 is the padding
+            members.entry(community).or_default().push(var as VarId);
+        }
+        let mut groups: Vec<Vec<VarId>> = members.into_values().collect();
+        groups.sort_by_key(|vars| (std::cmp::Reverse(vars.len()), vars[0]));
+        let total: usize = groups.iter().map(|vars| vars.len()).sum();
+
+        // lay the variables out along the walk: node p covers [p, p+1) * total / n and gets every group overlapping it
+        let order = self.arena.bfs_order(self.start_nodes[0]);
+        let mut partition = vec![Vec::new(); num_nodes];
+        let mut lo = 0;
+        for vars in groups.iter() {
+            let hi = lo + vars.len();
+            for (p, &id) in order.iter().enumerate() {
+                if lo * num_nodes < (p + 1) * total && hi * num_nodes > p * total {
+                    partition[id].extend(vars.iter().copied());
+                }
+            }
+            lo = hi;
+        }
+        partition
+    }
+
+    /// Makes each node branch on its share of the variables first, see `assign_variables_to_nodes`
+    pub fn set_variable_partition(&mut self, partition: Vec<Vec<VarId>>) {
+        assert!(partition.len() == self.arena.nodes.len(), "Need a variable list for every node");
+        for (node, vars) in self.arena.nodes.iter_mut().zip(partition) {
+            node.set_preferred_vars(vars);
+        }
+    }
+
+    /// Sets which busy node claims an idle neighbor first when several could fork to it
+    pub fn set_fork_policy(&mut self, fork_policy: ForkPolicy) {
+        self.fork_policy = fork_policy;
+    }
+
+    /// Whether the search stops at the first model or keeps going to count them (see `SearchMode`)
+    pub fn set_search_mode(&mut self, search_mode: SearchMode) {
+        self.search_mode = search_mode;
+        for node in self.arena.nodes.iter_mut() {
+            node.set_count_models(search_mode != SearchMode::FirstModel);
+        }
+    }
+
+    /// Stops `test_satisfiability` soon after `flag` is set, reporting the run as timed out
+    pub fn set_interrupt_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.interrupt = Some(flag);
+    }
+
+    /// Gives every node a default polarity drawn from an RNG seeded with `seed`, so the same seed repeats the run
+    pub fn randomize_polarity(&mut self, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for node in self.arena.nodes.iter_mut() {
+            node.set_default_polarity(rng.random());
+        }
+    }
+
+    /// Turns unit propagation on or off for every node (see `Node::set_unit_prop`)
+    pub fn set_unit_prop(&mut self, enabled: bool) {
+        for node in self.arena.nodes.iter_mut() {
+            node.set_unit_prop(enabled);
+        }
+    }
+
+    /// Stops `test_satisfiability` after `timeout` cycles with `TestResult::timed_out` set (None = no limit)
+    pub fn set_timeout(&mut self, timeout: Option<u64>) {
+        self.timeout = timeout;
+    }
+
+    /// Reports the simulated clock to `progress` while `test_satisfiability` runs
+    pub fn set_progress(&mut self, progress: ProgressBar) {
+        self.progress = Some(progress);
+    }
+
+    /// Saves a checkpoint to `path` every `interval` cycles while `test_satisfiability` runs
+    pub fn set_checkpointing(&mut self, interval: u64, path: PathBuf) {
+        assert!(interval > 0, "Checkpoint interval must be positive");
+        self.checkpoint = Some((interval, path));
+    }
+
+    /// Writes the full simulation state (nodes, in-flight messages, clock and counters) to `path`.
+    /// The original clause table is not saved, it is handed back to `load_checkpoint`.
+    pub fn save_checkpoint(&self, path: &Path) -> std::io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        let state = (&self.arena.nodes, &self.messages, self.clock, self.start_time, self.done, self.sat_node, self.idle_cycles, self.busy_cycles, &self.start_nodes, self.fork_policy, self.search_mode);
+        bincode::serialize_into(writer, &state).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+    }
+
+    /// Restores a simulation saved by `save_checkpoint`; `test_satisfiability` then continues from the saved clock
+    pub fn load_checkpoint(path: &Path, clause_table: ClauseTable<K>) -> std::io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let (nodes, messages, clock, start_time, done, sat_node, idle_cycles, busy_cycles, start_nodes, fork_policy, search_mode): (Vec<Node<K>>, MessageQueue<K>, u64, u64, bool, Option<NodeId>, u64, u64, Vec<NodeId>, ForkPolicy, SearchMode) =
+            bincode::deserialize_from(reader).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let mut swarm = SatSwarm::build(Arena::from_nodes(nodes), clause_table);
+        swarm.messages = messages;
+        swarm.clock = clock;
+        swarm.start_time = start_time;
+        swarm.done = done;
+        swarm.sat_node = sat_node;
+        swarm.idle_cycles = idle_cycles;
+        swarm.busy_cycles = busy_cycles;
+        swarm.start_nodes = start_nodes;
+        swarm.fork_policy = fork_policy;
+        swarm.search_mode = search_mode;
+        Ok(swarm)
+    }
+
+    pub fn _blank(clause_table: ClauseTable<K>) -> Self {
         SatSwarm::build(Arena { nodes: Vec::new() }, clause_table)
     }
-    pub fn generate(clause_table: ClauseTable, config: &TestConfig) -> Self {
+    /// Builds the network of `config.topology` and applies the rest of `config`. Fails only if the edge list of a
+    /// Custom topology can't be read, `TestConfig::validate` catches that before a workload starts.
+    pub fn generate(clause_table: ClauseTable<K>, config: &TestConfig) -> Result<Self, SimulationError> {
         let mut swarm = match config.topology {
             Topology::Grid(rows, cols) => SatSwarm::grid(clause_table, rows, cols, config.node_bandwidth),
-            Topology::Torus(rows, cols) => SatSwarm::torus(clause_table, rows, cols, config.node_bandwidth),
+            Topology::Torus(rows, cols) => {
+                let mut swarm = SatSwarm::torus(clause_table, rows, cols, config.node_bandwidth);
+                swarm.set_wrap_delay(rows, cols, config.wrap_delay);
+                swarm
+            },
             Topology::Dense(num_nodes) => SatSwarm::dense(clause_table, num_nodes, config.node_bandwidth),
+            Topology::Ring(num_nodes) => SatSwarm::ring(clause_table, num_nodes, config.node_bandwidth),
+            Topology::Butterfly(stages) => SatSwarm::butterfly(clause_table, stages, config.node_bandwidth),
+            Topology::Custom(ref path) => SatSwarm::from_edge_list(clause_table, path, config.node_bandwidth)
+                .map_err(|e| SimulationError::BadEdgeList { path: path.clone(), reason: e.to_string() })?,
         };
-        // swarm.messages.set_bandwidth(config.node_bandwidth);
-        swarm
+        swarm.messages.set_bandwidth(config.fork_bandwidth_bytes_per_cycle);
+        swarm.messages.set_adaptive_delay(config.adaptive_fork_delay);
+        for node in swarm.arena.nodes.iter_mut() {
+            node.set_decision_delay(config.decision_delay);
+            node.set_cycles_per_eval(config.cycles_per_eval);
+            node.set_pipeline_depth(config.pipeline_depth);
+            node.set_clause_layout(config.clause_layout);
+            node.set_wakeup_penalty(config.wakeup_penalty);
+            node.set_fork_fanout(config.fork_fanout);
+        }
+        swarm.set_fork_policy(config.fork_policy);
+        swarm.set_search_mode(config.search_mode);
+        swarm.set_lazy_mode(config.lazy_evaluation);
+        swarm.set_timeout(config.simulation_timeout_cycles);
+        swarm.set_unit_prop(config.enable_unit_prop);
+        swarm.set_track_clause_accesses(config.track_clause_accesses);
+        swarm.set_verify_level(config.verify_level);
+        swarm.set_energy_model(config.energy_model);
+        if config.random_polarity {
+            swarm.randomize_polarity(config.seed);
+        }
+        if let Some(partition) = &config.variable_partition {
+            swarm.set_variable_partition(partition.clone());
+        }
+        let num_nodes = swarm.arena.nodes.len();
+        if num_nodes > 0 {  // an empty network has nowhere to start, test_satisfiability returns an error
+            swarm.set_start_nodes((0..config.start_nodes).map(|i| i * num_nodes / config.start_nodes).collect());  // spread them out
+        }
+        Ok(swarm)
     }
-    pub fn grid(clause_table: ClauseTable, rows: usize, cols: usize, node_bandwidth: usize)  -> Self {
+    pub fn grid(clause_table: ClauseTable<K>, rows: usize, cols: usize, node_bandwidth: usize)  -> Self {
         let mut arena = Arena { nodes: Vec::with_capacity(rows * cols) };
         for i in 0..rows {
             for j in 0..cols {
@@ -92,7 +608,7 @@ impl SatSwarm {
         SatSwarm::build(arena, clause_table)
     }
 
-    pub fn torus(clause_table: ClauseTable, rows: usize, cols: usize, node_bandwidth: usize)  -> Self {
+    pub fn torus(clause_table: ClauseTable<K>, rows: usize, cols: usize, node_bandwidth: usize)  -> Self {
         let mut arena = Arena { nodes: Vec::with_capacity(rows * cols) };
         for row_index in 0..rows {
             for col_index in 0..cols {
@@ -123,76 +639,349 @@ impl SatSwarm {
         SatSwarm::build(arena, clause_table)
     }
 
-    pub fn dense(clause_table: ClauseTable, num_nodes: usize, node_bandwidth: usize) -> Self {
+    pub fn dense(clause_table: ClauseTable<K>, num_nodes: usize, node_bandwidth: usize) -> Self {
         let mut arena = Arena { nodes: Vec::with_capacity(num_nodes) };
         for id in 0..num_nodes {
             arena.nodes.push(Node::new(id, clause_table.clone(), node_bandwidth));
         }
-        for i in 0..num_nodes {
-            for j in (i + 1)..num_nodes {
-                arena.add_neighbor(i, j);
+        for node in arena.nodes.iter_mut() {
+            node.set_dense_neighbors(num_nodes);  // every pair is linked, no need to list them
+        }
+        SatSwarm::build(arena, clause_table)
+    }
+
+    /// Nodes in a cycle, node i linked to i - 1 and i + 1 (mod n). Every node has two neighbors (one if n = 2),
+    /// so the bisection is only two links wide.
+    pub fn ring(clause_table: ClauseTable<K>, num_nodes: usize, node_bandwidth: usize) -> Self {
+        let mut arena = Arena { nodes: Vec::with_capacity(num_nodes) };
+        for id in 0..num_nodes {
+            arena.nodes.push(Node::new(id, clause_table.clone(), node_bandwidth));
+            if id > 0 {
+                arena.add_neighbor(id, id - 1);
+            }
+        }
+        if num_nodes > 2 {
+            arena.add_neighbor(num_nodes - 1, 0);  // close the ring, with two nodes the link already exists
+        }
+        SatSwarm::build(arena, clause_table)
+    }
+
+    /// Folded butterfly with 2^stages nodes: the output of node i in stage s goes to i XOR 2^s in stage s + 1. Folding
+    /// every column onto the same nodes keeps the input and output links of a stage apart, so each pair of partners is
+    /// joined by two parallel links and a node has degree 2 * stages (log2(n) diameter).
+    pub fn butterfly(clause_table: ClauseTable<K>, stages: u32, node_bandwidth: usize) -> Self {
+        assert!(stages < usize::BITS, "Too many butterfly stages");
+        let num_nodes = 1usize << stages;
+        let mut arena = Arena { nodes: Vec::with_capacity(num_nodes) };
+        for id in 0..num_nodes {
+            arena.nodes.push(Node::new(id, clause_table.clone(), node_bandwidth));
+        }
+        for id in 0..num_nodes {
+            for stage in 0..stages {
+                // the output of id, which is also the input of its partner, and then its partner's output
+                arena.add_neighbor(id, id ^ (1 << stage));
             }
         }
         SatSwarm::build(arena, clause_table)
     }
 
+    /// Builds an arbitrary network from an edge list file (see `read_edge_list`)
+    pub fn from_edge_list(clause_table: ClauseTable<K>, path: &Path, node_bandwidth: usize) -> Result<Self, EdgeListError> {
+        let (num_nodes, edges) = read_edge_list(path)?;
+        let mut arena = Arena { nodes: Vec::with_capacity(num_nodes) };
+        for id in 0..num_nodes {
+            arena.nodes.push(Node::new(id, clause_table.clone(), node_bandwidth));
+        }
+        for (a, b) in edges {
+            arena.add_neighbor(a, b);
+        }
+        Ok(SatSwarm::build(arena, clause_table))
+    }
+
+    /// Overrides the fork latency of the link between two neighboring nodes (default is the flat fork delay)
+    pub fn set_link_delay(&mut self, a: NodeId, b: NodeId, delay: usize) {
+        assert!(self.arena.get_node(a).is_neighbor(b), "Nodes {} and {} are not neighbors", a, b);
+        self.messages.set_link_delay(a, b, delay);
+    }
+
+    /// Applies `delay` to the wrap-around links of a rows x cols torus so the cost of the long edges can be compared.
+    /// A dimension of 1 or 2 has no long edges (its wrap link joins a node to itself or to its ordinary neighbor).
+    pub fn set_wrap_delay(&mut self, rows: usize, cols: usize, delay: usize) {
+        if rows > 2 {
+            for col_index in 0..cols {
+                self.set_link_delay((rows - 1) * cols + col_index, col_index, delay);
+            }
+        }
+        if cols > 2 {
+            for row_index in 0..rows {
+                self.set_link_delay(row_index * cols + cols - 1, row_index * cols, delay);
+            }
+        }
+    }
+
     fn clock_update(&mut self, clock: u64) {
-        if DEBUG_PRINT {println!("Clock TICK: {}", clock);}
+        sim_log!("-", clock, LogLevel::Trace, "Clock TICK");
+        if clock % 10_000 == 0 {
+            if let Some(progress) = &self.progress {
+                if progress.length().is_some_and(|length| clock > length) {
+                    progress.set_length(clock);  // ran past the estimate
+                }
+                progress.set_position(clock);
+            }
+        }
         // print clock every 100,000 cycles
         if clock % 100_000 == 0 {
             // print clock and late_update of all nodes
             // for node in self.arena.nodes.iter() {
             //     print!("Node {} @ {}, ", node.id, node.last_update );
             // }
-            if clock - self.start_time >= 150_000_000 {
-                self.done = true;
-                println!("Timeout after 150_000_000 cycles");
+            if self.progress.is_none() {
+                sim_log!("-", clock, LogLevel::Debug, "Clock: {}", clock);
             }
-            println!("Clock: {}", clock);
         }
         for (from, to, msg) in self.messages.pop_message(clock) {
-            if DEBUG_PRINT {println!("Message: {:?} from {:?} to {:?}", msg, from, to);}
+            sim_log!(to, clock, LogLevel::Trace, "Message: {:?} from {}", msg, from);
             self.distribute_message(from, to, msg);
         }
 
         let mut busy_nodes: Vec<bool> = self.arena.nodes.iter()
             .map(|node| node.busy())
             .collect();
-        // Then, apply the updates
-        for node in self.arena.nodes.iter_mut() {
-            // let node = self.arena.get_node_mut(node_id);
+        self.messages.set_busy_nodes(busy_nodes.iter().filter(|&&busy| busy).count(), busy_nodes.len());
+        // Then, apply the updates. Nodes claim idle neighbors through busy_nodes, so the update order decides who forks first
+        let order: Vec<NodeId> = match self.fork_policy {
+            ForkPolicy::FirstNeighbor => (0..self.arena.nodes.len()).collect(),
+            ForkPolicy::DeepestNeighbor => {
+                let mut order: Vec<NodeId> = (0..self.arena.nodes.len()).collect();
+                order.sort_by_key(|&id| std::cmp::Reverse(self.arena.get_node(id).depth()));  // stable, ties stay in id order
+                order
+            },
+        };
+        for id in order {
+            let node = self.arena.get_node_mut(id);
             // assert!(busy_nodes[node.id] == node.busy(), "Node in {} but expected {}", node.busy(), busy_nodes[node.id]);
-            if busy_nodes[node.id] {
+            let busy = busy_nodes[node.id] && !node.waiting_on_fork();
+            if busy {
                 self.busy_cycles += 1;
             } else {
                 self.idle_cycles += 1;
+                node.record_idle_cycle();
             }
             node.clock_update(clock, &mut self.messages, &mut busy_nodes);
+            if busy {
+                node.record_busy_cycle();
+            }
         }
         self.invariants();
     }
 
-    pub fn test_satisfiability(&mut self) -> TestResult {
-        let mut clock = 0;
-        self.arena.get_node_mut(0).activate();
-        while !self.done && self.arena.nodes.iter().any(|node| node.busy()) {
-            self.clock_update(clock);
-            clock += 1;
+    /// Runs the search to the end, an error if the network has no nodes to search with
+    pub fn test_satisfiability(&mut self) -> Result<TestResult, SimulationError> {
+        if self.arena.nodes.is_empty() {
+            return Err(SimulationError::NoNodes);
+        }
+        Ok(self.search())
+    }
+
+    fn search(&mut self) -> TestResult {
+        if self.clauses.has_empty_clause {
+            // an empty clause can never be satisfied so there is nothing to search
+            return self.settled_result(false);
+        }
+        if self.search_mode == SearchMode::FirstModel && self.clauses.is_trivially_satisfied() {
+            // no clause (or only tautologies), nothing can fail either. Counting still has to enumerate the models
+            return self.settled_result(true);
+        }
+        if self.clock == 0 {  // otherwise we are resuming from a checkpoint
+            let prefix_len = self.start_nodes.len().trailing_zeros() as usize;
+            assert!(prefix_len < self.clauses.num_vars, "More start nodes than assignments of the first variables");
+            for (i, &id) in self.start_nodes.iter().enumerate() {
+                // variable 0 is padding, so the prefix starts at variable 1
+                let root: Vec<(VarId, bool)> = (0..prefix_len).map(|bit| ((bit + 1) as VarId, (i >> bit) & 1 == 1)).collect();
+                self.arena.get_node_mut(id).activate_with(&root);
+            }
+        }
+        // a fork in flight is work too, even if every node has gone idle by the time it arrives
+        while !self.done && (self.arena.nodes.iter().any(|node| node.busy()) || self.messages.in_flight()) {
+            if self.timeout.is_some_and(|timeout| self.clock - self.start_time >= timeout) {
+                self.timed_out = true;
+                sim_log!("-", self.clock, LogLevel::Info, "Timeout after {} cycles", self.clock - self.start_time);
+                break;
+            }
+            if self.clock.is_multiple_of(INTERRUPT_CHECK_INTERVAL) && self.interrupt.as_ref().is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+                self.timed_out = true;
+                sim_log!("-", self.clock, LogLevel::Info, "Interrupted after {} cycles", self.clock - self.start_time);
+                break;
+            }
+            self.clock_update(self.clock);
+            self.clock += 1;
+            if let SearchMode::AllModels(cap) = self.search_mode {
+                self.done = self.models_found() >= cap;
+            }
+            if let Some((interval, path)) = &self.checkpoint {
+                if self.clock % interval == 0 {
+                    if let Err(e) = self.save_checkpoint(path) {
+                        sim_log!("-", self.clock, LogLevel::Warn, "Failed to save checkpoint {:?}: {}", path, e);
+                    }
+                }
+            }
+        }
+        let time = self.clock;
+        if self.search_mode != SearchMode::FirstModel {
+            // nobody broadcasts success while counting, the formula is SAT if any node found a model
+            self.done = self.models_found() > 0;
+            self.sat_node = self.arena.nodes.iter().position(|node| node.first_model().is_some());
         }
-        let time = clock;
-        if true {
-            println!("Done: {}", self.done);
-            println!("Busy cycles: {}", self.busy_cycles);
-            println!("Idle cycles: {}", self.idle_cycles);
+        sim_log!("-", self.clock, LogLevel::Debug, "Done: {}, busy cycles: {}, idle cycles: {}", self.done, self.busy_cycles, self.idle_cycles);
+        let model = self.recover_satisfying_assignment().map(|model| {
+            (0..self.clauses.num_vars).map(|var| model.get(&(var as VarId)).copied()).collect()
+        });
+        if let (Some(id), None) = (self.sat_node, &model) {
+            sim_log!(id, self.clock, LogLevel::Error, "Reported SAT but its assignment does not satisfy the formula");
         }
+        sim_log!(self.sat_node.map_or(String::from("-"), |id| id.to_string()), self.clock, LogLevel::Debug, "Model: {:?}", model);
         TestResult {
             simulated_result: self.done,
             simulated_cycles: time,
             cycles_busy: self.busy_cycles,
             cycles_idle: self.idle_cycles,
+            fork_attempts: self.arena.nodes.iter().map(|node| node.fork_attempts()).sum(),
+            fork_successes: self.arena.nodes.iter().map(|node| node.fork_successes()).sum(),
+            wakeups: self.arena.nodes.iter().map(|node| node.wakeups()).sum(),
+            forks_total: self.arena.nodes.iter().map(|node| node.forks_sent()).sum(),
+            forks_productive: self.arena.nodes.iter().map(|node| node.forks_productive()).sum(),
+            avg_fork_delay: self.messages.avg_fork_delay(),
+            model,
+            conflict_histogram: self.conflict_histogram(),
+            cycles_unit_prop: self.cause_cycles(AssignmentCause::UnitPropagation),
+            cycles_decision: self.cause_cycles(AssignmentCause::Decision),
+            cycles_conflict: self.cause_cycles(AssignmentCause::Conflict),
+            clause_evaluations: self.clause_evaluations(),
+            models_found: if self.search_mode == SearchMode::FirstModel { self.done as u64 } else { self.models_found() },
+            search_leaves: self.conflict_histogram().iter().sum::<u64>() + self.models_found() + (self.search_mode == SearchMode::FirstModel && self.done) as u64,
+            timed_out: self.timed_out,
+            clause_accesses: self.clause_accesses(),
+            total_energy_pj: self.energy_consumed(),
+            clause_activity: self.clause_activity(),
+        }
+    }
+
+    /// Models found by all nodes while counting
+    pub fn models_found(&self) -> u64 {
+        self.arena.nodes.iter().map(|node| node.models_found()).sum()
+    }
+
+    /// Clauses checked by all nodes, a proxy for the energy spent
+    pub fn clause_evaluations(&self) -> u64 {
+        self.arena.nodes.iter().map(|node| node.clause_evaluations()).sum()
+    }
+
+    /// Checks of each clause summed over all nodes, None unless tracking is on
+    pub fn clause_accesses(&self) -> Option<Vec<u64>> {
+        let mut total = vec![0; self.clauses.num_clauses];
+        for node in self.arena.nodes.iter() {
+            for (sum, count) in total.iter_mut().zip(node.clause_accesses()?) {
+                *sum += count;
+            }
+        }
+        Some(total)
+    }
+
+    /// Conflicts and unit propagations found in each clause, summed over all nodes
+    pub fn clause_activity(&self) -> Vec<u64> {
+        let mut total = vec![0; self.clauses.num_clauses];
+        for node in self.arena.nodes.iter() {
+            for (sum, count) in total.iter_mut().zip(node.clause_activity()) {
+                *sum += count;
+            }
+        }
+        total
+    }
+
+    /// Counts how often every node checks each clause (see `Node::set_track_clause_accesses`)
+    pub fn set_track_clause_accesses(&mut self, track: bool) {
+        for node in self.arena.nodes.iter_mut() {
+            node.set_track_clause_accesses(track);
+        }
+    }
+
+    /// Sets how often every node checks its clause table (see `Node::set_verify_level`)
+    pub fn set_verify_level(&mut self, level: VerifyLevel) {
+        for node in self.arena.nodes.iter_mut() {
+            node.set_verify_level(level);
+        }
+    }
+
+    /// Makes every node track its energy with `model` (see `Node::set_energy_model`)
+    pub fn set_energy_model(&mut self, model: Option<EnergyModel>) {
+        for node in self.arena.nodes.iter_mut() {
+            node.set_energy_model(model);
+        }
+    }
+
+    /// Picojoules spent by all nodes, including their idle cycles
+    pub fn energy_consumed(&self) -> f64 {
+        self.arena.nodes.iter().map(|node| node.energy_consumed()).sum()
+    }
+
+    /// Switches every node between eager and lazy clause evaluation (see `Node::set_lazy_mode`)
+    pub fn set_lazy_mode(&mut self, lazy: bool) {
+        for node in self.arena.nodes.iter_mut() {
+            node.set_lazy_mode(lazy);
         }
     }
-    fn distribute_message(&mut self, from: MessageDestination, to: MessageDestination, message: Message) {
+
+    /// Busy cycles spent on `cause` summed over all nodes
+    pub fn cause_cycles(&self, cause: AssignmentCause) -> u64 {
+        self.arena.nodes.iter().map(|node| node.cause_cycles(cause)).sum()
+    }
+
+    /// Conflicts at each decision level summed over all nodes
+    pub fn conflict_histogram(&self) -> Vec<u64> {
+        let mut histogram = vec![0; CONFLICT_LEVELS];
+        for node in self.arena.nodes.iter() {
+            for (total, count) in histogram.iter_mut().zip(node.conflict_histogram()) {
+                *total += count;
+            }
+        }
+        histogram
+    }
+
+    /// Size, diameter, bisection width and the 5 nodes with the highest betweenness of the network
+    pub fn topology_stats(&self) -> TopologyStats {
+        let nodes = self.arena.nodes.len();
+        let betweenness_exact = nodes <= EXACT_BETWEENNESS_NODES;
+        let betweenness = if betweenness_exact {
+            self.arena.betweenness_centrality()
+        } else {
+            self.arena.approx_betweenness(BETWEENNESS_SAMPLES, 0)
+        };
+        let mut top_betweenness: Vec<(NodeId, f64)> = betweenness.into_iter().enumerate().collect();
+        top_betweenness.sort_by(|a, b| b.1.total_cmp(&a.1));  // stable, so ties stay in id order
+        top_betweenness.truncate(5);
+        TopologyStats {
+            nodes,
+            links: self.arena.nodes.iter().map(|node| node.neighbors().count()).sum::<usize>() / 2,
+            diameter: self.arena.diameter(),
+            bisection_width: self.arena.bisection_width(),
+            top_betweenness,
+            betweenness_exact,
+        }
+    }
+
+    /// Chain of forks that handed `node` its current subproblem, oldest first (empty for the root or an idle node)
+    pub fn fork_provenance(&self, node: NodeId) -> &[ForkOrigin] {
+        self.arena.get_node(node).provenance()
+    }
+
+    /// Fraction of each node's branching decisions that found an idle neighbor to fork to (0 if it never tried, like
+    /// `TestResult::fork_success_rate`). Low rates mean the node is starved of idle neighbors by the topology.
+    pub fn fork_success_rates(&self) -> Vec<f64> {
+        self.arena.nodes.iter().map(|node| {
+            if node.fork_attempts() == 0 { 0.0 } else { node.fork_successes() as f64 / node.fork_attempts() as f64 }
+        }).collect()
+    }
+    fn distribute_message(&mut self, from: MessageDestination, to: MessageDestination, message: Message<K>) {
         match to {
             MessageDestination::Neighbor(id) => {
                 self.arena.get_node_mut(id).recieve_message(from, message);
@@ -204,54 +993,7 @@ impl SatSwarm {
                 match (message, from) {
                     (Message::Success, MessageDestination::Neighbor(id)) => {
                         self.done = true;
-                        // print in sorted order of keys
-                        let node: &Node = self.arena.get_node(id);
-                        let model = self.recover_model(id);
-                        let mut labels: Vec<_> = model.clone().into_iter().collect();
-                        labels.sort_by_key(|&(var, _)| var);
-                        println!("Model: {:?}", labels);
-                        
-                        for clause in self.clauses.clause_table.iter() {
-                            let mut found_true = false;
-                            let mut clause_str = String::from("|\t");
-                            for (term, term_state) in clause.iter() {
-                                let term_str = match term {
-                                    Term {var, negated} => {
-                                        let val = match model.get(var) {
-                                            Some(val) => val,
-                                            None => panic!("Variable {} not found in model", var)
-                                        };
-                                        let term_val = if *negated { !val } else { *val };
-                                        if term_val {
-                                            found_true = true;
-                                        }
-                                        match term_state {
-                                            TermState::True => {
-                                                assert!(term_val, "Term {:?} is not consistent with term state {:?}", term, term_state);
-                                            },
-                                            TermState::False => {
-                                                assert!(!term_val, "Term {:?} is not consistent with term state {:?}", term, term_state);
-                                            },
-                                            TermState::Symbolic => {
-                                            }
-                                            
-                                        }
-                                        // assert!(if term_val { *term_state == TermState::True } else { *term_state == TermState::False }, "{:?} is not consistent with term state {:?}", term, term_state);
-
-                                        if *negated {
-                                            format!("!{} ({})", var, !val)
-                                        } else {
-                                            format!("{} ({})", var, val)
-                                        }
-                                    }
-                                };
-                                clause_str.push_str(&term_str);
-                                clause_str.push_str("\t|\t");
-                            }
-                            println!("Clause: {}", clause_str);
-                            assert!(found_true, "Clause is not satisfied");
-                        }
-
+                        self.sat_node = Some(id);  // its assignment is checked once the simulation ends
                     },
                     _ => panic!("Broadcast message from unexpected source")
                 };
@@ -261,26 +1003,921 @@ impl SatSwarm {
     fn invariants(&self) {
         // possible add invariants here to check for correctness
     }
-    fn recover_model(&self, id: NodeId) -> HashMap<VarId, bool> {
-        let mut model = HashMap::new();
-        model.insert(0, false);  // first variable is always false
-        
-        for clause in self.arena.get_node(id).table.clause_table.iter() {
-            for (term, state) in clause.iter() {
-                match *state {
-                    TermState::True => {
-                        model.insert(term.var, !term.negated);
-                    },
-                    TermState::False => {
-                        model.insert(term.var, term.negated);
-                    },
-                    _ => {
-                        model.insert(term.var, false);
-                    }     
+    /// Result of an instance decided before any node starts: UNSAT for an empty clause, which refutes the root,
+    /// and SAT for a trivially satisfied table. Its model is empty, every variable left unassigned, since any
+    /// assignment satisfies the table
+    fn settled_result(&self, simulated_result: bool) -> TestResult {
+        TestResult {
+            simulated_result,
+            simulated_cycles: 0,
+            cycles_busy: 0,
+            cycles_idle: 0,
+            fork_attempts: 0,
+            fork_successes: 0,
+            wakeups: 0,
+            forks_total: 0,
+            forks_productive: 0,
+            avg_fork_delay: 0.0,
+            model: simulated_result.then(|| vec![None; self.clauses.num_vars]),
+            conflict_histogram: vec![0; CONFLICT_LEVELS],
+            cycles_unit_prop: 0,
+            cycles_decision: 0,
+            cycles_conflict: 0,
+            clause_evaluations: 0,
+            models_found: simulated_result as u64,
+            search_leaves: 1,   // the root is the only leaf
+            timed_out: false,
+            clause_accesses: self.clause_accesses(),
+            total_energy_pj: self.energy_consumed(),
+            clause_activity: self.clause_activity(),
+        }
+    }
+
+    /// The assignment of the node that reported SAT, checked against the original formula.
+    /// None if no node reported SAT, the node left a variable unassigned or the assignment does not satisfy every clause.
+    pub fn recover_satisfying_assignment(&self) -> Option<HashMap<VarId, bool>> {
+        let node = self.arena.get_node(self.sat_node?);
+        let assignment = node.first_model().cloned().unwrap_or_else(|| node.assignment());  // counting nodes moved on since
+        if assignment.iter().any(|value| value.is_none()) || !self.clauses.is_satisfied_by(&assignment) {
+            return None;
+        }
+        Some(assignment.into_iter().enumerate().skip(1).map(|(var, value)| (var as VarId, value.unwrap())).collect())  // var 0 is only padding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::{clause_table::{Term, TermState}, community::detect_communities, minisat::minisat_table, node::ClauseLayout, problems::community_sat};
+
+    /// Satisfying assignments of every variable, by trying them all
+    fn count_models(table: &ClauseTable) -> u64 {
+        let num_vars = table.num_vars - 1;
+        (0u32..1 << num_vars).filter(|bits| {
+            let assignment: Vec<Option<bool>> = (0..=num_vars).map(|var| Some(var > 0 && bits >> (var - 1) & 1 == 1)).collect();
+            table.is_satisfied_by(&assignment)
+        }).count() as u64
+    }
+
+    /// UNSAT under every assignment of variables 1..=10, so the search has to walk a full tree of depth 10
+    fn depth_10_tree() -> ClauseTable {
+        let mut clauses: Vec<Vec<i32>> = (0..8).map(|signs| (0..3).map(|bit| if signs >> bit & 1 == 1 { 11 + bit } else { -(11 + bit) }).collect()).collect();
+        clauses.push(vec![1, 2, 3]);
+        ClauseTable::from_clauses(13, &clauses)
+    }
+
+    fn with_fanout(mut swarm: SatSwarm, fanout: usize) -> SatSwarm {
+        for node in swarm.arena.nodes.iter_mut() {
+            node.set_fork_fanout(fanout);
+        }
+        swarm
+    }
+
+    #[test]
+    fn four_way_forks_fill_a_dense_network_faster() {
+        let mut swarm = with_fanout(SatSwarm::dense(depth_10_tree(), 5, 100), 4);
+        swarm.arena.get_node_mut(0).activate_with(&[]);
+        let mut most_busy = 0;
+        while swarm.arena.nodes.iter().any(|node| node.busy()) || swarm.messages.in_flight() {
+            swarm.clock_update(swarm.clock);
+            swarm.clock += 1;
+            most_busy = most_busy.max(swarm.arena.nodes.iter().filter(|node| node.busy() && !node.waiting_on_fork()).count());
+        }
+        assert_eq!(most_busy, 5, "every node searches at once");
+        let one_way = with_fanout(SatSwarm::dense(depth_10_tree(), 5, 100), 1).test_satisfiability().unwrap();
+        let four_way = with_fanout(SatSwarm::dense(depth_10_tree(), 5, 100), 4).test_satisfiability().unwrap();
+        assert!(!one_way.simulated_result && !four_way.simulated_result);
+        assert!(four_way.simulated_cycles < one_way.simulated_cycles, "4-way {} cycles, 1-way {}", four_way.simulated_cycles, one_way.simulated_cycles);
+    }
+
+    #[test]
+    fn two_way_forks_go_to_the_closest_idle_neighbors() {
+        // 0 lists its neighbors as 1, 2 (wrap), 3 and 6 (wrap); with 0 - 1 at 5 cycles the closest two are 3 and 1
+        let mut swarm = with_fanout(SatSwarm::torus(depth_10_tree(), 3, 3, 100), 2);
+        swarm.set_wrap_delay(3, 3, 7);
+        swarm.set_link_delay(0, 1, 5);
+        assert_eq!(swarm.arena.get_node(0).neighbors().collect::<Vec<_>>(), vec![1, 2, 3, 6]);
+        swarm.arena.get_node_mut(0).activate_with(&[]);
+        while swarm.arena.get_node(0).forks_sent() == 0 {
+            assert!(swarm.clock < 20, "0 never forked");
+            swarm.clock_update(swarm.clock);
+            swarm.clock += 1;
+        }
+        assert_eq!(swarm.arena.get_node(0).forks_sent(), 2);
+        let first_fork = ForkOrigin { source: 0, clock: swarm.clock - 1 };
+        while swarm.clock <= first_fork.clock + 5 {
+            swarm.clock_update(swarm.clock);
+            swarm.clock += 1;
+        }
+        let receivers: Vec<NodeId> = (1..9).filter(|&id| swarm.fork_provenance(id) == [first_fork]).collect();
+        assert_eq!(receivers, vec![1, 3]);
+    }
+
+    #[test]
+    fn two_way_forks_skip_parallel_links() {
+        // on a 2-wide torus both links from 0 to 1 (and to 2) are idle, a 2-way fork still reaches two nodes
+        let mut swarm = with_fanout(SatSwarm::torus(depth_10_tree(), 2, 2, 100), 2);
+        assert_eq!(swarm.arena.get_node(0).neighbors().filter(|&n| n == 1).count(), 2);
+        swarm.arena.get_node_mut(0).activate_with(&[]);
+        while swarm.fork_provenance(1).is_empty() || swarm.fork_provenance(2).is_empty() {
+            assert!(swarm.clock < 20, "0 did not fork to both neighbors");
+            swarm.clock_update(swarm.clock);
+            swarm.clock += 1;
+        }
+        assert_eq!(swarm.fork_provenance(1), swarm.fork_provenance(2));
+    }
+
+    #[test]
+    fn forks_in_flight_keep_the_run_going() {
+        // every node goes idle while the fork with the only models is still on its way
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/random/sat/51_10_1.cnf")).unwrap();
+        assert!(minisat_table(&table).0);
+        let result = SatSwarm::dense(table, 4, 100).test_satisfiability().unwrap();
+        assert!(result.simulated_result);
+        assert!(result.forks_total > 0);
+    }
+
+    #[test]
+    fn backtracks_keep_the_branch_a_node_received() {
+        // flipping a decision must not reset the forked assignment below it, or the node searches its partner's half too
+        let table = depth_10_tree();
+        let alone = SatSwarm::dense(table.clone(), 1, 100).test_satisfiability().unwrap();
+        for num_nodes in [2, 4] {
+            let result = SatSwarm::dense(table.clone(), num_nodes, 100).test_satisfiability().unwrap();
+            assert!(!result.simulated_result);
+            assert!(result.forks_total > 0);
+            assert_eq!(result.search_leaves, alone.search_leaves, "{} nodes", num_nodes);
+        }
+    }
+
+    #[test]
+    fn k_way_forks_cover_every_assignment_once() {
+        // counting every model finds each one exactly once only if the forks and the sender split the space exactly
+        let table: ClauseTable = ClauseTable::random_with_rng(40, 14, &mut StdRng::seed_from_u64(3));
+        let models = count_models(&table);
+        assert!(models > 0);
+        for fanout in [2, 3, 4] {
+            let mut swarm = with_fanout(SatSwarm::dense(table.clone(), 5, 100), fanout);
+            swarm.set_search_mode(SearchMode::Exhaustive);
+            let result = swarm.test_satisfiability().unwrap();
+            assert!(result.forks_total > 0);
+            assert_eq!(result.models_found, models, "fanout {}", fanout);
+        }
+    }
+
+    #[test]
+    fn two_start_nodes_agree_with_one() {
+        let start = |table: &ClauseTable, start_nodes: Vec<NodeId>, search_mode| {
+            let mut swarm = SatSwarm::grid(table.clone(), 2, 2, 100);
+            swarm.set_start_nodes(start_nodes);
+            swarm.set_search_mode(search_mode);
+            swarm.test_satisfiability().unwrap()
+        };
+        for file in ["tests/satlib/sat/uf20-01.cnf", "tests/satlib/sat/uf20-0100.cnf", "tests/satlib/unsat/uuf50-01.cnf"] {
+            let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from(file)).unwrap();
+            let single = start(&table, vec![0], SearchMode::FirstModel);
+            let corners = start(&table, vec![0, 3], SearchMode::FirstModel);
+            assert_eq!(corners.simulated_result, single.simulated_result, "{}", file);
+            assert_eq!(corners.simulated_result, minisat_table(&table).0, "{}", file);
+        }
+        // the two halves of the first variable together cover every assignment exactly once
+        let table: ClauseTable = ClauseTable::random_with_rng(40, 14, &mut StdRng::seed_from_u64(3));
+        let models = count_models(&table);
+        assert_eq!(start(&table, vec![0], SearchMode::Exhaustive).models_found, models);
+        assert_eq!(start(&table, vec![0, 3], SearchMode::Exhaustive).models_found, models);
+    }
+
+    #[test]
+    fn one_node_enumerates_every_model() {
+        // (1 or 2) and (-2 or 3) over 4 vars: 2 models with 2 set (3 true, 1 free) and 2 without (1 true, 3 free), times both values of 4
+        let table: ClauseTable = ClauseTable::from_clauses(4, &[vec![1, 2], vec![-2, 3]]);
+        assert_eq!(count_models(&table), 8);
+        let run = |search_mode| {
+            let mut swarm = SatSwarm::dense(table.clone(), 1, 100);
+            swarm.set_search_mode(search_mode);
+            swarm.test_satisfiability().unwrap()
+        };
+        let exhaustive = run(SearchMode::Exhaustive);
+        assert!(exhaustive.simulated_result);
+        assert_eq!(exhaustive.models_found, 8);
+        let capped = run(SearchMode::AllModels(2));
+        assert!(capped.simulated_result);
+        assert_eq!(capped.models_found, 2, "the search stops at the cap");
+        assert!(capped.simulated_cycles < exhaustive.simulated_cycles);
+        assert_eq!(run(SearchMode::AllModels(10)).models_found, 8, "a cap above the count finds them all");
+    }
+
+    #[test]
+    fn conflicts_fill_the_levels_of_a_depth_5_tree() {
+        // 1..=4 are free and 5 refutes itself through 6, so every path decides 5 variables before unit propagation
+        // on 6 conflicts. A flip lives one level up, so a tree of depth d has C(d, j) conflicts at level j
+        let clauses = [vec![5, 6], vec![5, -6], vec![-5, 6], vec![-5, -6]];
+        let mut swarm: SatSwarm = SatSwarm::dense(ClauseTable::from_clauses(6, &clauses), 1, 100);
+        let result = swarm.test_satisfiability().unwrap();
+        assert!(!result.simulated_result);
+        assert_eq!(result.conflict_histogram.len(), CONFLICT_LEVELS);
+        assert_eq!(result.conflict_histogram[..6], [1, 5, 10, 10, 5, 1]);
+        assert!(result.conflict_histogram[6..].iter().all(|&count| count == 0), "{:?}", result.conflict_histogram);
+        assert_eq!(result.search_leaves, 32);
+    }
+
+    #[test]
+    fn deepest_neighbor_wins_the_idle_node() {
+        // every decision satisfies the clauses, so each node goes one level deeper every time it decides
+        let clauses: Vec<Vec<i32>> = (1..20).map(|var| vec![-var, -(var + 1)]).collect();
+        let first_fork = |fork_policy| {
+            let table: ClauseTable = ClauseTable::from_clauses(20, &clauses);
+            let mut swarm = SatSwarm::build(Arena::from_nodes((0..3).map(|id| Node::new(id, table.clone(), 100)).collect()), table);
+            swarm.set_fork_policy(fork_policy);
+            let run_until = |swarm: &mut SatSwarm, clock| while swarm.clock < clock {
+                swarm.clock_update(swarm.clock);
+                swarm.clock += 1;
+            };
+            // 1 gets a head start, then both decide in the same cycles with 1 three levels deeper
+            swarm.arena.get_node_mut(1).activate_with(&[]);
+            run_until(&mut swarm, 6);
+            swarm.arena.get_node_mut(0).activate_with(&[]);
+            run_until(&mut swarm, 10);
+            assert_eq!((swarm.arena.get_node(0).depth(), swarm.arena.get_node(1).depth()), (1, 4));
+            // 2 is idle and both of them can reach it
+            swarm.arena.add_neighbor(0, 2);
+            swarm.arena.add_neighbor(1, 2);
+            while swarm.fork_provenance(2).is_empty() {
+                assert!(swarm.clock < 20, "nobody forked to 2");
+                swarm.clock_update(swarm.clock);
+                swarm.clock += 1;
+            }
+            swarm.fork_provenance(2)[0]
+        };
+        assert_eq!(first_fork(ForkPolicy::DeepestNeighbor), ForkOrigin { source: 1, clock: 10 });
+        assert_eq!(first_fork(ForkPolicy::FirstNeighbor), ForkOrigin { source: 0, clock: 10 }, "without the policy the lower id gets there first");
+    }
+
+    #[test]
+    fn nodes_that_never_tried_to_fork_have_a_zero_rate() {
+        let mut alone = SatSwarm::dense(depth_10_tree(), 1, 100);
+        let result = alone.test_satisfiability().unwrap();
+        assert_eq!((result.fork_attempts, result.fork_success_rate()), (0, 0.0));
+        assert_eq!(alone.fork_success_rates(), vec![0.0], "no NaN for the CSV");
+        let mut pair = SatSwarm::dense(depth_10_tree(), 2, 100);
+        pair.test_satisfiability().unwrap();
+        assert!(pair.fork_success_rates().iter().all(|rate| (0.0..=1.0).contains(rate)));
+    }
+
+    #[test]
+    fn forked_nodes_record_the_neighbor_they_came_from() {
+        // on the line 0 - 1 - 2 work can only reach 2 through 1
+        let table = depth_10_tree();
+        let mut swarm = SatSwarm::build(Arena::from_nodes((0..3).map(|id| Node::new(id, table.clone(), 100)).collect()), table);
+        swarm.arena.add_neighbor(0, 1);
+        swarm.arena.add_neighbor(1, 2);
+        swarm.arena.get_node_mut(0).activate_with(&[]);
+        while swarm.fork_provenance(2).is_empty() {
+            assert!(swarm.clock < 100, "no fork reached 2");
+            swarm.clock_update(swarm.clock);
+            swarm.clock += 1;
+        }
+        let from_0 = swarm.fork_provenance(1).to_vec();
+        assert_eq!(from_0.len(), 1);
+        assert_eq!(from_0[0].source, 0);
+        let chain = swarm.fork_provenance(2);
+        assert_eq!(chain.len(), 2, "{:?}", chain);
+        assert_eq!(chain[0], from_0[0], "2 inherits the fork that handed 1 its subproblem");
+        assert_eq!(chain[1].source, 1);
+        assert!(chain[1].clock > chain[0].clock);
+        assert!(swarm.fork_provenance(0).is_empty(), "the root was never forked");
+    }
+
+    #[test]
+    fn retried_branches_reuse_their_saved_phase() {
+        // 1 = false fails whatever 2 is, so 2 was last flipped to true when 1 backtracks to true and 2 is decided again
+        let clauses = [vec![1, 2, 3], vec![1, 2, -3], vec![1, -2, 3], vec![1, -2, -3]];
+        let mut swarm: SatSwarm = SatSwarm::dense(ClauseTable::from_clauses(4, &clauses), 1, 100);
+        let result = swarm.test_satisfiability().unwrap();
+        assert!(result.simulated_result);
+        assert_eq!(result.search_leaves, 3, "two conflicts and the model");
+        let model = swarm.recover_satisfying_assignment().unwrap();
+        assert!(model[&1]);
+        assert!(model[&2], "the retried decision takes the saved true, not the default false");
+        assert!(!model[&4], "a variable never assigned before takes the default polarity");
+    }
+
+    #[test]
+    fn dense_networks_find_idle_neighbors_more_often() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/unsat/uuf50-01.cnf")).unwrap();
+        let grid = SatSwarm::grid(table.clone(), 4, 4, 100).test_satisfiability().unwrap();
+        let dense = SatSwarm::dense(table, 16, 100).test_satisfiability().unwrap();
+        assert!(grid.fork_attempts > 0 && dense.fork_attempts > 0);
+        assert!(grid.fork_successes <= grid.fork_attempts && dense.fork_successes <= dense.fork_attempts);
+        assert!(dense.fork_success_rate() > grid.fork_success_rate(), "dense {:.3}, grid {:.3}", dense.fork_success_rate(), grid.fork_success_rate());
+    }
+
+    #[test]
+    fn implied_dense_neighbors_fork_like_listed_ones() {
+        // the same network with every link stored, in the id order the implied neighbors come in
+        let listed = |table: &ClauseTable, num_nodes: usize| {
+            let mut arena = Arena::from_nodes((0..num_nodes).map(|id| Node::new(id, table.clone(), 100)).collect());
+            for a in 0..num_nodes {
+                for b in a + 1..num_nodes {
+                    arena.add_neighbor(a, b);
                 }
             }
+            SatSwarm::build(arena, table.clone())
+        };
+        for file in ["tests/satlib/sat/uf20-01.cnf", "tests/satlib/unsat/uuf50-01.cnf"] {
+            let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from(file)).unwrap();
+            let (mut implied, mut stored) = (SatSwarm::dense(table.clone(), 6, 100), listed(&table, 6));
+            for (a, b) in implied.arena.nodes.iter().zip(stored.arena.nodes.iter()) {
+                assert_eq!(a.neighbors().collect::<Vec<_>>(), b.neighbors().collect::<Vec<_>>());
+            }
+            let (a, b) = (implied.test_satisfiability().unwrap(), stored.test_satisfiability().unwrap());
+            assert!(a.forks_total > 0, "{}", file);
+            assert_eq!((a.simulated_result, a.simulated_cycles, a.search_leaves), (b.simulated_result, b.simulated_cycles, b.search_leaves), "{}", file);
+            let forks = |swarm: &SatSwarm| -> Vec<(u64, u64, u64, u64)> {
+                swarm.arena.nodes.iter().map(|node| (node.fork_attempts(), node.fork_successes(), node.forks_sent(), node.wakeups())).collect()
+            };
+            assert_eq!(forks(&implied), forks(&stored), "{}", file);
+        }
+    }
+
+    #[test]
+    fn busy_cycles_split_by_cause() {
+        for file in ["tests/satlib/sat/uf20-01.cnf", "tests/satlib/unsat/uuf50-01.cnf"] {
+            let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from(file)).unwrap();
+            for (num_nodes, topology, decision_delay) in [(1, Topology::Dense(1), 0), (4, Topology::Grid(2, 2), 0), (4, Topology::Grid(2, 2), 3)] {
+                let config = TestConfig { num_nodes, topology, decision_delay, ..crate::tests::test_config() };
+                let result = SatSwarm::generate(table.clone(), &config).unwrap().test_satisfiability().unwrap();
+                assert!(result.cycles_unit_prop > 0 && result.cycles_decision > 0, "{}", file);
+                assert_eq!(result.cycles_unit_prop + result.cycles_decision + result.cycles_conflict, result.cycles_busy, "{} on {}", file, config.topology);
+            }
         }
-        model
-        // node.model.clone()
+    }
+
+    #[test]
+    fn search_without_unit_propagation_is_still_correct() {
+        let load = |file| -> ClauseTable { ClauseTable::load_file(PathBuf::from(file)).unwrap().0 };
+        let unsat: ClauseTable = ClauseTable::random_with_rng(100, 12, &mut StdRng::seed_from_u64(1));
+        assert!(!minisat_table(&unsat).0);
+        let run = |table: &ClauseTable, enable_unit_prop| {
+            let mut swarm = SatSwarm::generate(table.clone(), &TestConfig { enable_unit_prop, ..crate::tests::test_config() }).unwrap();
+            let result = swarm.test_satisfiability().unwrap();
+            assert!(!result.simulated_result || swarm.recover_satisfying_assignment().is_some());
+            result
+        };
+        for table in [load("tests/satlib/sat/uf20-01.cnf"), load("tests/satlib/sat/uf20-010.cnf"), unsat.clone()] {
+            let (with, without) = (run(&table, true), run(&table, false));
+            assert_eq!(without.simulated_result, minisat_table(&table).0);
+            assert_eq!(without.simulated_result, with.simulated_result);
+            assert_eq!(without.cycles_unit_prop, 0);
+            assert_ne!(without.simulated_cycles, with.simulated_cycles);
+        }
+        // every variable is decided, so the unsat search has to explore more of the tree
+        assert!(run(&unsat, false).search_leaves > run(&unsat, true).search_leaves);
+    }
+
+    #[test]
+    fn clause_accesses_sum_to_the_evaluations() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/unsat/uuf50-01.cnf")).unwrap();
+        for lazy in [false, true] {
+            let mut swarm = SatSwarm::grid(table.clone(), 2, 2, 100);
+            swarm.set_lazy_mode(lazy);
+            swarm.set_track_clause_accesses(true);
+            let result = swarm.test_satisfiability().unwrap();
+            let accesses = result.clause_accesses.as_ref().unwrap();
+            assert_eq!(accesses.len(), table.num_clauses);
+            assert!(result.clause_evaluations > 0);
+            assert_eq!(accesses.iter().sum::<u64>(), result.clause_evaluations, "lazy {}", lazy);
+            let (min, max, mean) = result.clause_access_stats().unwrap();
+            assert!(min as f64 <= mean && mean <= max as f64);
+        }
+        assert_eq!(SatSwarm::grid(table, 2, 2, 100).test_satisfiability().unwrap().clause_accesses, None, "untracked by default");
+    }
+
+    /// One node on (1 or 2 or 3), (-1 or 2) and (4 or 5), stopped once it has decided var 1 false and then given a
+    /// corrupted True for the var 1 term of clause 0. Resetting scans would set a term of an unassigned var back to Symbolic
+    fn corrupted_swarm(level: VerifyLevel) -> SatSwarm {
+        let mut swarm = SatSwarm::dense(ClauseTable::from_clauses(5, &[vec![1, 2, 3], vec![-1, 2], vec![4, 5]]), 1, 100);
+        swarm.set_verify_level(level);
+        swarm.arena.get_node_mut(0).activate_with(&[]);
+        while swarm.arena.get_node_mut(0).assignment()[1].is_none() {
+            swarm.clock_update(swarm.clock);
+            swarm.clock += 1;
+        }
+        let node = swarm.arena.get_node_mut(0);
+        assert_eq!((node.assignment()[1], node.table.clause_table[0][0]), (Some(false), (Term { var: 1, negated: false }, TermState::False)));
+        node.table.clause_table[0][0].1 = TermState::True;
+        swarm
+    }
+
+    #[test]
+    #[should_panic(expected = "Node 0 has True for var 1 in clause 0 but its assignment gives False")]
+    fn full_verification_stops_a_corrupted_run() {
+        corrupted_swarm(VerifyLevel::Full).test_satisfiability().unwrap();  // resumes where corrupted_swarm stopped
+    }
+
+    #[test]
+    fn unverified_runs_miss_a_corrupted_term() {
+        for level in [VerifyLevel::Off, VerifyLevel::Sampled(1000)] {
+            let result = corrupted_swarm(level).test_satisfiability().unwrap();
+            assert!(result.simulated_result, "{:?}", level);
+        }
+    }
+
+    #[test]
+    fn the_clause_that_keeps_failing_is_the_most_active() {
+        // without unit propagation every assignment of 1..=8 that the other clauses let through goes on to decide 9,
+        // and 9 = false empties clause 0 each time. (1 or 2 or 3) and (-4 or 5) each cut off a few whole subtrees
+        let table: ClauseTable = ClauseTable::from_clauses(9, &[vec![9], vec![1, 2, 3], vec![-4, 5]]);
+        let mut swarm = SatSwarm::dense(table, 1, 100);
+        swarm.set_unit_prop(false);
+        swarm.set_search_mode(SearchMode::Exhaustive);
+        let result = swarm.test_satisfiability().unwrap();
+        assert!(result.conflict_histogram.iter().sum::<u64>() >= 100);
+        let activity = &result.clause_activity;
+        assert!(activity[1..].iter().all(|&other| activity[0] > other), "{:?}", activity);
+        assert_eq!(result.top_k_active_clauses(2), vec![0, 2]);
+        assert_eq!(result.top_k_active_clauses(10).len(), 3, "only clauses that did something are listed");
+    }
+
+    #[test]
+    fn lazy_evaluation_agrees_with_eager() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir("tests/satlib/sat").unwrap().map(|entry| entry.unwrap().path()).collect();
+        files.sort();
+        files.truncate(20);
+        files.extend(["tests/satlib/unsat/uuf50-01.cnf", "tests/satlib/unsat/uuf50-010.cnf"].map(PathBuf::from));
+        for file in files {
+            let (table, _): (ClauseTable, _) = ClauseTable::load_file(file.clone()).unwrap();
+            let expected = minisat_table(&table).0;
+            for num_nodes in [1, 4] {
+                let run = |lazy| {
+                    let mut swarm = SatSwarm::dense(table.clone(), num_nodes, 100);
+                    swarm.set_lazy_mode(lazy);
+                    swarm.test_satisfiability().unwrap()
+                };
+                let (eager, lazy) = (run(false), run(true));
+                assert_eq!((eager.simulated_result, lazy.simulated_result), (expected, expected), "{:?} on {} nodes", file, num_nodes);
+                // a lazy scan applies its unit propagations in another order, which can change the saved phases and so
+                // lead to a different model. Either way it has to satisfy the formula (the model is only kept if it does)
+                assert_eq!((eager.model.is_some(), lazy.model.is_some()), (expected, expected), "{:?} on {} nodes", file, num_nodes);
+            }
+        }
+        // without conflicts there is no phase to differ in: each false decision propagates the next variable to true
+        let chain: ClauseTable = ClauseTable::from_clauses(30, &(1..30).map(|var| vec![var, var + 1]).collect::<Vec<_>>());
+        let model = |lazy| {
+            let mut swarm = SatSwarm::dense(chain.clone(), 1, 100);
+            swarm.set_lazy_mode(lazy);
+            swarm.test_satisfiability().unwrap().model.expect("the chain is SAT")
+        };
+        assert_eq!(model(true), model(false));
+    }
+
+    #[test]
+    fn random_polarity_follows_the_seed() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/sat/uf20-01.cnf")).unwrap();
+        let run = |seed| {
+            let result = SatSwarm::generate(table.clone(), &TestConfig { random_polarity: true, seed, ..crate::tests::test_config() }).unwrap().test_satisfiability().unwrap();
+            assert!(result.simulated_result);
+            (result.simulated_cycles, result.search_leaves, result.model)
+        };
+        assert_eq!(run(7), run(7));
+        // the first value each node speculates decides which part of the tree it walks, and so which model it finds
+        let searches: Vec<_> = (0..8).map(run).collect();
+        assert!(searches.iter().any(|search| *search != searches[0]), "every seed searched the same way");
+    }
+
+    #[test]
+    fn wakeups_wait_out_their_penalty() {
+        for penalty in [0, 1, 7] {
+            let mut swarm = SatSwarm::dense(depth_10_tree(), 3, 100);
+            swarm.arena.nodes.iter_mut().for_each(|node| node.set_wakeup_penalty(penalty));
+            swarm.arena.get_node_mut(0).activate_with(&[]);
+            let mut last_fork: Vec<Option<ForkOrigin>> = vec![None; 3];
+            let mut forks_started = 0;
+            while swarm.arena.nodes.iter().any(|node| node.busy()) || swarm.messages.in_flight() {
+                swarm.clock_update(swarm.clock);
+                for node in swarm.arena.nodes.iter() {
+                    let origin = node.provenance().last().copied();
+                    if let Some(origin) = origin.filter(|&origin| Some(origin) != last_fork[node.id]) {
+                        // one cycle over the link, then the penalty before the node starts on the fork
+                        assert_eq!(swarm.clock - origin.clock, 1 + penalty, "fork from {} to {}", origin.source, node.id);
+                        forks_started += 1;
+                    }
+                    last_fork[node.id] = origin;
+                }
+                swarm.clock += 1;
+            }
+            assert!(forks_started > 0);
+            assert_eq!(swarm.arena.nodes.iter().map(|node| node.wakeups()).sum::<u64>(), forks_started);
+        }
+    }
+
+    #[test]
+    fn forks_that_only_conflict_are_wasted() {
+        // the first fork sends 1 = true, which unit propagation refutes before the receiver decides anything
+        let table: ClauseTable = ClauseTable::from_clauses(4, &[vec![-1, 2], vec![-1, -2]]);
+        let mut swarm = SatSwarm::dense(table, 2, 100);
+        swarm.set_search_mode(SearchMode::Exhaustive);
+        let result = swarm.test_satisfiability().unwrap();
+        assert_eq!(result.models_found, 8);
+        assert_eq!((result.forks_total, result.forks_productive), (3, 2));
+        assert_eq!(result.fork_efficiency(), 2.0 / 3.0);
+        // every fork of the tree still has the 10 levels below it to explore
+        let result = SatSwarm::dense(depth_10_tree(), 4, 100).test_satisfiability().unwrap();
+        assert!(result.forks_total > 0);
+        assert_eq!(result.forks_productive, result.forks_total);
+        assert_eq!(result.fork_efficiency(), 1.0);
+        assert_eq!(SatSwarm::dense(depth_10_tree(), 1, 100).test_satisfiability().unwrap().fork_efficiency(), 0.0, "a lone node never forks");
+    }
+
+    #[test]
+    fn clause_layouts_agree_on_the_result() {
+        for file in ["tests/satlib/sat/uf20-01.cnf", "tests/satlib/sat/uf20-010.cnf", "tests/satlib/unsat/uuf50-01.cnf"] {
+            let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from(file)).unwrap();
+            // a scan takes several batches of 8 clauses, so the order the clauses come in decides when a conflict is seen
+            let run = |clause_layout| SatSwarm::generate(table.clone(), &TestConfig { node_bandwidth: 8, clause_layout, ..crate::tests::test_config() }).unwrap().test_satisfiability().unwrap();
+            let sequential = run(ClauseLayout::Sequential);
+            let banked = run(ClauseLayout::Interleaved { banks: 4 });
+            assert_eq!(banked.simulated_result, sequential.simulated_result, "{}", file);
+            assert_eq!(banked.simulated_result, minisat_table(&table).0, "{}", file);
+            assert_ne!(banked.simulated_cycles, sequential.simulated_cycles, "{}", file);
+            assert_eq!(run(ClauseLayout::Interleaved { banks: 4 }).simulated_cycles, banked.simulated_cycles, "{}", file);
+        }
+    }
+
+    #[test]
+    fn community_partitions_keep_the_results() {
+        for seed in 0..4 {
+            let table = community_sat(2, 10, 40, 0, seed);
+            let communities = detect_communities(&table);
+            let dense: SatSwarm = SatSwarm::dense(table.clone(), 2, 100);
+            let mut partition = dense.assign_variables_to_nodes(&communities);
+            // each node gets one of the two sub-formulas
+            partition.iter_mut().for_each(|vars| vars.sort());
+            partition.sort();
+            assert_eq!(partition, vec![(1..=10).collect::<Vec<VarId>>(), (11..=20).collect()], "seed {}", seed);
+
+            let run = |variable_partition, search_mode| {
+                let config = TestConfig { num_nodes: 2, topology: Topology::Dense(2), variable_partition, search_mode, ..crate::tests::test_config() };
+                SatSwarm::generate(table.clone(), &config).unwrap().test_satisfiability().unwrap()
+            };
+            assert_eq!(run(Some(partition.clone()), SearchMode::FirstModel).simulated_result, minisat_table(&table).0, "seed {}", seed);
+            assert_eq!(run(Some(partition), SearchMode::Exhaustive).models_found, run(None, SearchMode::Exhaustive).models_found, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn nodes_sharing_a_community_are_neighbors() {
+        let table = community_sat(2, 10, 40, 0, 0);
+        let grid: SatSwarm = SatSwarm::grid(table.clone(), 2, 2, 100);
+        let partition = grid.assign_variables_to_nodes(&detect_communities(&table));
+        assert!(partition.iter().all(|vars| vars.len() == 10));
+        let sharing: Vec<(NodeId, NodeId)> = (0..4).flat_map(|a| (a + 1..4).map(move |b| (a, b))).filter(|&(a, b)| partition[a] == partition[b]).collect();
+        assert_eq!(sharing.len(), 2, "two nodes for each community");
+        for (a, b) in sharing {
+            assert!(grid.arena.get_node(a).is_neighbor(b), "nodes {} and {} share a community", a, b);
+        }
+    }
+
+    /// Edge list `contents` in a temporary file, removed after `check` has read it
+    fn with_edge_list<T>(name: &str, contents: &str, check: impl FnOnce(&Path) -> T) -> T {
+        let path = std::env::temp_dir().join(format!("sat_swarm_{}_{}.txt", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        let result = check(&path);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn edge_lists_build_the_listed_network() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/unsat/uuf50-01.cnf")).unwrap();
+        let mut cycle: SatSwarm = with_edge_list("cycle", "# a 4-cycle\n0 1\n1 2\n\n2 3\n3 0\n", |path| SatSwarm::from_edge_list(table.clone(), path, 100)).unwrap();
+        assert_eq!(cycle.arena.nodes.len(), 4);
+        for (a, b) in [(0, 1), (1, 2), (2, 3), (3, 0)] {
+            assert!(cycle.arena.get_node(a).is_neighbor(b) && cycle.arena.get_node(b).is_neighbor(a));
+        }
+        assert!(!cycle.arena.get_node(0).is_neighbor(2));
+        let ring = SatSwarm::ring(table, 4, 100).test_satisfiability().unwrap();
+        let listed = cycle.test_satisfiability().unwrap();
+        assert_eq!((listed.simulated_result, listed.simulated_cycles), (ring.simulated_result, ring.simulated_cycles));
+    }
+
+    #[test]
+    fn malformed_edge_lists_are_rejected() {
+        let read = |name, contents| with_edge_list(name, contents, read_edge_list);
+        assert!(matches!(read("three", "0 1\n0 1 2\n"), Err(EdgeListError::BadLine { line: 2, .. })));
+        assert!(matches!(read("word", "0 x\n"), Err(EdgeListError::BadLine { line: 1, .. })));
+        assert!(matches!(read("negative", "0 -1\n"), Err(EdgeListError::BadLine { line: 1, .. })));
+        assert!(matches!(read("loop", "0 1\n1 1\n"), Err(EdgeListError::SelfLoop { line: 2, node: 1 })));
+        assert!(matches!(read("twice", "0 1\n1 2\n1 0\n"), Err(EdgeListError::DuplicateEdge { line: 3, a: 1, b: 0 })));
+        assert!(matches!(read("gap", "0 2\n"), Err(EdgeListError::MissingNode(1))));
+        assert!(matches!(read_edge_list(Path::new("tests/no_such_edges.txt")), Err(EdgeListError::Io(_))));
+        assert_eq!(read("empty", "# nothing\n").unwrap(), (0, Vec::new()));
+    }
+
+    #[test]
+    fn bisection_widths_match_the_known_cuts() {
+        let table = || -> ClauseTable { ClauseTable::from_clauses(3, &[vec![1, 2, 3]]) };
+        assert_eq!(SatSwarm::grid(table(), 4, 4, 3).arena.bisection_width(), 4);
+        assert_eq!(SatSwarm::torus(table(), 4, 4, 3).arena.bisection_width(), 8);
+        assert_eq!(SatSwarm::dense(table(), 8, 3).arena.bisection_width(), 16, "4 x 4 links of K8 cross the cut");
+        assert_eq!(SatSwarm::ring(table(), 4, 3).arena.bisection_width(), 2);
+        assert_eq!(SatSwarm::grid(table(), 4, 4, 3).arena.bisection_bandwidth(10), 40);
+    }
+
+    #[test]
+    fn forks_take_the_delay_of_their_link() {
+        let mut swarm = SatSwarm::dense(depth_10_tree(), 3, 100);
+        swarm.set_link_delay(0, 1, 5);
+        swarm.set_link_delay(0, 2, 20);
+        swarm.set_link_delay(1, 2, 40);
+        swarm.arena.get_node_mut(0).activate_with(&[]);
+        let mut last_fork: Vec<Option<ForkOrigin>> = vec![None; 3];
+        let mut weights_seen = Vec::new();
+        while swarm.arena.nodes.iter().any(|node| node.busy()) || swarm.messages.in_flight() {
+            swarm.clock_update(swarm.clock);
+            for node in swarm.arena.nodes.iter() {
+                let origin = node.provenance().last().copied();
+                if let Some(origin) = origin.filter(|&origin| Some(origin) != last_fork[node.id]) {
+                    // the node starts on the fork the cycle it arrives
+                    let weight = swarm.messages.link_delay(origin.source, node.id);
+                    assert_eq!(swarm.clock - origin.clock, weight as u64, "fork from {} to {}", origin.source, node.id);
+                    weights_seen.push(weight);
+                }
+                last_fork[node.id] = origin;
+            }
+            swarm.clock += 1;
+        }
+        weights_seen.sort();
+        weights_seen.dedup();
+        assert_eq!(weights_seen, vec![5, 20, 40]);
+    }
+
+    #[test]
+    fn wrap_delay_only_touches_long_edges() {
+        let table = || -> ClauseTable { ClauseTable::from_clauses(3, &[vec![1, 2, 3]]) };
+        for (rows, cols) in [(1, 4), (4, 1), (2, 2)] {
+            SatSwarm::torus(table(), rows, cols, 3).set_wrap_delay(rows, cols, 7);
+        }
+        let mut swarm = SatSwarm::torus(table(), 2, 3, 3);
+        swarm.set_wrap_delay(2, 3, 7);
+        assert_eq!(swarm.messages.link_delay(0, 3), 1, "with two rows the column wrap is the ordinary link");
+        assert_eq!(swarm.messages.link_delay(0, 1), 1);
+        assert_eq!(swarm.messages.link_delay(0, 2), 7);
+        assert_eq!(swarm.messages.link_delay(3, 5), 7);
+    }
+
+    #[test]
+    fn zero_node_networks_are_an_error() {
+        let table = || -> ClauseTable { ClauseTable::from_clauses(3, &[vec![1, 2, 3]]) };
+        assert!(matches!(SatSwarm::dense(table(), 0, 3).test_satisfiability(), Err(SimulationError::NoNodes)));
+        assert!(matches!(SatSwarm::ring(table(), 0, 3).test_satisfiability(), Err(SimulationError::NoNodes)));
+        let config = TestConfig { num_nodes: 0, topology: Topology::Dense(0), ..crate::tests::test_config() };
+        assert!(matches!(SatSwarm::generate(table(), &config).unwrap().test_satisfiability(), Err(SimulationError::NoNodes)));
+    }
+
+    #[test]
+    fn resumed_checkpoint_matches_an_uninterrupted_run() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/unsat/uuf50-01.cnf")).unwrap();
+        let swarm = || -> SatSwarm {
+            let mut swarm = SatSwarm::grid(table.clone(), 1, 2, 100);
+            swarm.arena.nodes.iter_mut().for_each(|node| node.set_cycles_per_eval(12));  // slow enough to outlast the interrupt
+            swarm
+        };
+        let uninterrupted = swarm().test_satisfiability().unwrap();
+        assert!(uninterrupted.simulated_cycles > 50_000);
+
+        let mut interrupted = swarm();
+        interrupted.set_timeout(Some(50_000));
+        assert!(interrupted.test_satisfiability().unwrap().timed_out);
+        let path = std::env::temp_dir().join(format!("sat_swarm_{}_resume.ckpt", std::process::id()));
+        interrupted.save_checkpoint(&path).unwrap();
+        let mut resumed: SatSwarm = SatSwarm::load_checkpoint(&path, table.clone()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.clock, 50_000);
+        assert_eq!(resumed.messages.in_flight(), interrupted.messages.in_flight());
+        let resumed = resumed.test_satisfiability().unwrap();
+
+        assert!(!resumed.timed_out);
+        assert_eq!(resumed.simulated_result, uninterrupted.simulated_result);
+        assert_eq!(resumed.simulated_cycles, uninterrupted.simulated_cycles);
+        assert_eq!(resumed.cycles_busy, uninterrupted.cycles_busy);
+        assert_eq!(resumed.cycles_idle, uninterrupted.cycles_idle);
+        assert_eq!(resumed.fork_successes, uninterrupted.fork_successes);
+        assert_eq!(resumed.search_leaves, uninterrupted.search_leaves);
+        assert_eq!(resumed.clause_evaluations, uninterrupted.clause_evaluations);
+    }
+
+    #[test]
+    fn adaptive_delay_shortens_a_starved_torus() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/unsat/uuf50-01.cnf")).unwrap();
+        // only the wrap links are longer than a cycle, the rest cannot get any shorter
+        let config = |adaptive| TestConfig { num_nodes: 16, topology: Topology::Torus(4, 4), wrap_delay: 40, adaptive_fork_delay: adaptive, ..crate::tests::test_config() };
+        let fixed = SatSwarm::generate(table.clone(), &config(false)).unwrap().test_satisfiability().unwrap();
+        let adaptive = SatSwarm::generate(table.clone(), &config(true)).unwrap().test_satisfiability().unwrap();
+        assert_eq!(adaptive.simulated_result, fixed.simulated_result);
+        assert!(adaptive.avg_fork_delay < fixed.avg_fork_delay);
+        assert!(adaptive.simulated_cycles < fixed.simulated_cycles, "adaptive {} cycles, fixed {}", adaptive.simulated_cycles, fixed.simulated_cycles);
+    }
+
+    #[test]
+    fn decision_delay_stalls_every_decision() {
+        // a single node searches the same tree whatever its timing, so each decision adds the same stall
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/unsat/uuf50-01.cnf")).unwrap();
+        let run = |decision_delay| {
+            let config = TestConfig { num_nodes: 1, topology: Topology::Dense(1), decision_delay, ..crate::tests::test_config() };
+            SatSwarm::generate(table.clone(), &config).unwrap().test_satisfiability().unwrap()
+        };
+        let (base, one, three) = (run(0), run(1), run(3));
+        assert_eq!(base.search_leaves, three.search_leaves);
+        let decisions = one.cycles_busy - base.cycles_busy;
+        assert!(decisions > 0);
+        assert_eq!(three.cycles_busy - base.cycles_busy, 3 * decisions);
+        assert_eq!(three.cycles_decision - base.cycles_decision, 3 * decisions);
+        assert_eq!(three.simulated_cycles - base.simulated_cycles, 3 * decisions);
+    }
+
+    #[test]
+    fn evaluation_latency_scales_busy_cycles_linearly() {
+        // the search is the same at any latency, so each extra cycle per batch adds the same number of busy cycles
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/unsat/uuf50-01.cnf")).unwrap();
+        let runs: Vec<TestResult> = (1..=4).map(|cycles_per_eval| {
+            let config = TestConfig { num_nodes: 1, topology: Topology::Dense(1), cycles_per_eval, ..crate::tests::test_config() };
+            SatSwarm::generate(table.clone(), &config).unwrap().test_satisfiability().unwrap()
+        }).collect();
+        let step = runs[1].cycles_busy - runs[0].cycles_busy;
+        assert!(step > 0);
+        for (extra, run) in runs.iter().enumerate() {
+            assert_eq!(run.search_leaves, runs[0].search_leaves);
+            assert_eq!(run.cycles_busy, runs[0].cycles_busy + extra as u64 * step, "{} cycles per evaluation", extra + 1);
+        }
+    }
+
+    /// Arena of `num_nodes` nodes linked by `edges`, for checking graph measures on hand-built shapes
+    fn arena_with_edges(num_nodes: usize, edges: &[(NodeId, NodeId)]) -> Arena {
+        let table: ClauseTable = ClauseTable::from_clauses(3, &[vec![1, 2, 3]]);
+        let mut arena = Arena::from_nodes((0..num_nodes).map(|id| Node::new(id, table.clone(), 100)).collect());
+        for &(a, b) in edges {
+            arena.add_neighbor(a, b);
+        }
+        arena
+    }
+
+    #[test]
+    fn betweenness_peaks_where_shortest_paths_meet() {
+        // 1 and 2 each sit on the paths of two pairs: (0, 2) and (0, 3) for 1, (0, 3) and (1, 3) for 2
+        let path = arena_with_edges(4, &[(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(path.betweenness_centrality(), vec![0.0, 2.0, 2.0, 0.0]);
+        // every pair of the 5 leaves goes through the center
+        let star = arena_with_edges(6, &[(0, 1), (0, 2), (0, 3), (0, 4), (0, 5)]);
+        let betweenness = star.betweenness_centrality();
+        assert_eq!(betweenness[0], (5 * 4 / 2) as f64);
+        assert!(betweenness[1..].iter().all(|&b| b == 0.0));
+        assert_eq!(star.approx_betweenness(6, 0), betweenness, "sampling every source is exact");
+    }
+
+    #[test]
+    fn four_node_ring() {
+        let ring = SatSwarm::ring(depth_10_tree(), 4, 3);
+        for node in ring.arena.nodes.iter() {
+            let mut neighbors: Vec<NodeId> = node.neighbors().collect();
+            neighbors.sort();
+            let mut expected = vec![(node.id + 3) % 4, (node.id + 1) % 4];
+            expected.sort();
+            assert_eq!(neighbors, expected);
+        }
+        let stats = ring.topology_stats();
+        assert_eq!((stats.nodes, stats.links, stats.diameter), (4, 4, Some(2)));
+    }
+
+    #[test]
+    fn ring_is_slower_than_dense_on_a_hard_instance() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/unsat/uuf50-01.cnf")).unwrap();
+        let ring = SatSwarm::ring(table.clone(), 16, 100).test_satisfiability().unwrap();
+        let dense = SatSwarm::dense(table, 16, 100).test_satisfiability().unwrap();
+        assert!(!ring.simulated_result && !dense.simulated_result);
+        assert!(ring.simulated_cycles > dense.simulated_cycles, "ring {} cycles, dense {}", ring.simulated_cycles, dense.simulated_cycles);
+    }
+
+    #[test]
+    fn three_stage_butterfly_has_8_nodes_of_degree_6() {
+        let butterfly = SatSwarm::butterfly(depth_10_tree(), 3, 3);
+        assert_eq!(butterfly.arena.nodes.len(), 8);
+        for node in butterfly.arena.nodes.iter() {
+            let mut neighbors: Vec<NodeId> = node.neighbors().collect();
+            assert_eq!(neighbors.len(), 6, "3 inputs and 3 outputs");
+            neighbors.sort();
+            let mut partners: Vec<NodeId> = (0..3).map(|stage| node.id ^ (1 << stage)).collect();
+            partners.sort();
+            assert_eq!(neighbors, partners.iter().flat_map(|&partner| [partner, partner]).collect::<Vec<_>>());
+        }
+        let stats = butterfly.topology_stats();
+        assert_eq!((stats.links, stats.diameter), (24, Some(3)));
+    }
+
+    #[test]
+    fn forks_over_parallel_links_go_to_distinct_nodes() {
+        let table: ClauseTable = ClauseTable::random_with_rng(40, 14, &mut StdRng::seed_from_u64(3));
+        let models = count_models(&table);
+        let mut swarm = with_fanout(SatSwarm::butterfly(table, 3, 100), 6);
+        swarm.set_search_mode(SearchMode::Exhaustive);
+        assert_eq!(swarm.test_satisfiability().unwrap().models_found, models);
+    }
+
+    fn energy_model(decision_energy_pj: f64, idle_per_cycle_pj: f64) -> EnergyModel {
+        EnergyModel { decision_energy_pj, unit_prop_energy_pj: 0.0, fork_send_energy_pj: 0.0, fork_receive_energy_pj: 0.0, idle_per_cycle_pj }
+    }
+
+    #[test]
+    fn hundred_decisions_cost_hundred_pj() {
+        // every clause is satisfied by the first value tried, so each of the 100 variables takes exactly one decision
+        let clauses: Vec<Vec<i32>> = (0..98).map(|var| vec![-(var + 1), -(var + 2), -(var + 3)]).collect();
+        let mut swarm: SatSwarm = SatSwarm::dense(ClauseTable::from_clauses(100, &clauses), 1, 100);
+        swarm.set_energy_model(Some(energy_model(1.0, 0.0)));
+        let result = swarm.test_satisfiability().unwrap();
+        assert!(result.simulated_result);
+        assert_eq!(result.cycles_conflict, 0);
+        assert_eq!(swarm.arena.get_node(0).energy_consumed(), 100.0);
+        assert_eq!(result.total_energy_pj, 100.0);
+    }
+
+    #[test]
+    fn idle_cycles_are_billed() {
+        let mut swarm = SatSwarm::dense(depth_10_tree(), 4, 3);
+        swarm.set_energy_model(Some(energy_model(0.0, 0.5)));
+        let result = swarm.test_satisfiability().unwrap();
+        assert!(result.cycles_idle > 0);
+        assert_eq!(result.total_energy_pj, result.cycles_idle as f64 * 0.5);
+    }
+
+    #[test]
+    fn models_leave_out_the_padding_var() {
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/sat/uf20-01.cnf")).unwrap();
+        assert_eq!((table.num_vars, table.number_of_vars()), (21, 20));
+        let mut swarm = SatSwarm::dense(table.clone(), 4, 3);
+        let model = swarm.test_satisfiability().unwrap().model.expect("uf20-01 is SAT");
+        assert_eq!(model.len(), 21);
+        assert_eq!(model[0], None);
+        assert!(model[1..].iter().all(Option::is_some));
+        let recovered = swarm.recover_satisfying_assignment().unwrap();
+        assert_eq!(recovered.len(), 20);
+        assert!(!recovered.contains_key(&0));
+        assert!(table.is_satisfied_by(&model));
+    }
+
+    #[test]
+    fn recovered_models_satisfy_every_clause() {
+        for file in ["tests/satlib/sat/uf20-01.cnf", "tests/satlib/sat/uf20-010.cnf", "tests/satlib/sat/uf20-0100.cnf"] {
+            let contents = std::fs::read_to_string(file).unwrap();
+            let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from(file)).unwrap();
+            let num_clauses = table.num_clauses;
+            let mut swarm = SatSwarm::grid(table, 2, 2, 100);
+            assert!(swarm.test_satisfiability().unwrap().simulated_result);
+            let model = swarm.recover_satisfying_assignment().expect(file);
+            // check against the literals of the file rather than the table built from them
+            let clauses = contents.lines()
+                .take_while(|line| !line.starts_with('%'))
+                .filter(|line| !line.starts_with('c') && !line.starts_with('p') && !line.trim().is_empty());
+            let mut checked = 0;
+            for clause in clauses {
+                checked += 1;
+                let literals: Vec<i32> = clause.split_whitespace().map(|literal| literal.parse().unwrap()).take_while(|&literal| literal != 0).collect();
+                assert!(literals.iter().any(|&literal| model[&(literal.unsigned_abs() as VarId)] == (literal > 0)), "{}: {} is false", file, clause);
+            }
+            assert_eq!(checked, num_clauses, "{}", file);
+        }
+        let (table, _): (ClauseTable, _) = ClauseTable::load_file(PathBuf::from("tests/satlib/unsat/uuf50-01.cnf")).unwrap();
+        let mut swarm = SatSwarm::dense(table, 4, 100);
+        assert!(!swarm.test_satisfiability().unwrap().simulated_result);
+        assert_eq!(swarm.recover_satisfying_assignment(), None);
+    }
+
+    #[test]
+    fn empty_clause_is_unsat_without_a_search() {
+        let table: ClauseTable = ClauseTable::from_dimacs_str("p cnf 3 3\n1 -2 3 0\n0\n-1 2 -3 0\n").unwrap();
+        assert!(table.has_empty_clause);
+        assert!(!minisat_table(&table).0);
+        let result = SatSwarm::dense(table, 4, 3).test_satisfiability().unwrap();
+        assert!(!result.simulated_result && !result.timed_out);
+        assert_eq!((result.simulated_cycles, result.model), (0, None));
+    }
+
+    #[test]
+    fn zero_clause_file_is_sat_before_the_search() {
+        let table: ClauseTable = ClauseTable::from_dimacs_str("p cnf 3 0\n").unwrap();
+        assert!(minisat_table(&table).0);
+        let result = SatSwarm::dense(table, 4, 3).test_satisfiability().unwrap();
+        assert!(result.simulated_result);
+        assert_eq!((result.simulated_cycles, result.forks_total), (0, 0));
+        assert_eq!(result.model, Some(vec![None; 4]), "empty model, no variable needs a value");
+    }
+
+    #[test]
+    fn single_tautology_file_is_sat_before_the_search() {
+        let table: ClauseTable = ClauseTable::from_dimacs_str("p cnf 2 1\n1 -1 2 0\n").unwrap();
+        assert!(minisat_table(&table).0);
+        let result = SatSwarm::dense(table, 4, 3).test_satisfiability().unwrap();
+        assert!(result.simulated_result);
+        assert_eq!((result.simulated_cycles, result.forks_total), (0, 0));
+        assert_eq!(result.model, Some(vec![None; 3]), "empty model, no variable needs a value");
     }
 }
@@ -2,4 +2,36 @@ pub type NodeId = usize;
 pub type VarId = u8;
 pub const CLAUSE_LENGTH: usize = 3;
 
-pub const DEBUG_PRINT: bool = false;
\ No newline at end of file
+/// How often nodes check their clause table against their assignment. A check walks the whole table, so `Full`
+/// costs about one extra scan per decision on big instances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum VerifyLevel {
+    Off,
+    Sampled(u64),       // check every Nth check point of each node
+    Full,               // check at every check point
+}
+
+/// Which busy node gets to fork when several of them could claim the same idle neighbor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ForkPolicy {
+    FirstNeighbor,      // lowest node id first
+    DeepestNeighbor,    // the node with the deepest speculative trail first, its remaining subproblems are the most refined
+}
+
+/// When the search stops. The counting modes keep searching after a model and report how many they found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SearchMode {
+    FirstModel,         // stop at the first satisfying assignment
+    Exhaustive,         // run to completion, counting every model and leaf of the search
+    AllModels(u64),     // enumerate models until the cap is reached
+}
+
+/// Energy of each node operation in picojoules, for estimating what a hardware swarm would draw
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct EnergyModel {
+    pub decision_energy_pj: f64,        // a branching decision or the flip after a conflict
+    pub unit_prop_energy_pj: f64,       // an assignment by unit propagation
+    pub fork_send_energy_pj: f64,       // copying the state into a fork
+    pub fork_receive_energy_pj: f64,    // loading a received fork
+    pub idle_per_cycle_pj: f64,         // leakage of a node with nothing to do
+}
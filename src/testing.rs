@@ -0,0 +1,359 @@
+/*
+Analysis runs built on top of the simulator, used for hardware sizing rather than correctness.
+*/
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use csv::Writer;
+
+use crate::structures::{clause_table::ClauseTable, satswarm::SatSwarm};
+use crate::{run_single, RunError, TestConfig, Topology};
+
+/// How the problem grows with the number of nodes in `speedup_analysis`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalingMode {
+    /// Same problem for every node count: speedup = cycles(1) / cycles(n)
+    Strong,
+    /// n disjoint copies of the problem on n nodes: speedup = n * cycles(1) / cycles(n)
+    Weak,
+}
+
+pub struct SpeedupData {
+    pub mode: ScalingMode,
+    pub counts: Vec<usize>,
+    pub cycles: Vec<u64>,
+    pub speedups: Vec<f64>,
+    pub efficiencies: Vec<f64>,
+} impl SpeedupData {
+    pub fn write_csv(&self, path: &str) -> csv::Result<()> {
+        let mut writer = Writer::from_path(path)?;
+        writer.write_record(&["Mode", "Num Nodes", "Simulated Cycles", "Speedup", "Efficiency"])?;
+        for i in 0..self.counts.len() {
+            writer.write_record(&[
+                format!("{:?}", self.mode),
+                self.counts[i].to_string(),
+                self.cycles[i].to_string(),
+                self.speedups[i].to_string(),
+                self.efficiencies[i].to_string(),
+            ])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Least squares fit of the serial fraction f in Amdahl's law, speedup(n) = 1 / (f + (1 - f) / n).
+    /// Rearranged as 1/speedup - 1/n = f * (1 - 1/n) the fit is linear in f. Clamped to [0, 1], 0 if no run used more than one node.
+    pub fn fit_amdahl(&self) -> f64 {
+        let (mut xy, mut xx) = (0.0, 0.0);
+        for (&n, &speedup) in self.counts.iter().zip(self.speedups.iter()) {
+            let x = 1.0 - 1.0 / n as f64;
+            let y = 1.0 / speedup - 1.0 / n as f64;
+            xy += x * y;
+            xx += x * x;
+        }
+        if xx == 0.0 { 0.0 } else { (xy / xx).clamp(0.0, 1.0) }
+    }
+
+    /// Writes `path` with a .dat data file and a .gp gnuplot script plotting speedup and efficiency against
+    /// node count on two y axes, with the fitted Amdahl curves dashed for reference (run gnuplot from that directory)
+    pub fn to_gnuplot(&self, path: &Path) -> std::io::Result<()> {
+        let data_path = path.with_extension("dat");
+        let mut data = File::create(&data_path)?;
+        writeln!(data, "# nodes speedup efficiency cycles ({:?} scaling)", self.mode)?;
+        for i in 0..self.counts.len() {
+            writeln!(data, "{} {} {} {}", self.counts[i], self.speedups[i], self.efficiencies[i], self.cycles[i])?;
+        }
+
+        let f = self.fit_amdahl();
+        let min = self.counts.iter().copied().min().unwrap_or(1).max(1);
+        let max = self.counts.iter().copied().max().unwrap_or(1);
+        let data_name = data_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let mut script = File::create(path.with_extension("gp"))?;
+        writeln!(script, "set title \"{:?} scaling\"", self.mode)?;
+        writeln!(script, "set xlabel \"Nodes\"")?;
+        writeln!(script, "set ylabel \"Speedup\"")?;
+        writeln!(script, "set y2label \"Efficiency\"")?;
+        writeln!(script, "set ytics nomirror")?;
+        writeln!(script, "set y2tics")?;
+        writeln!(script, "set y2range [0:*]")?;
+        writeln!(script, "set key top left")?;
+        if max >= 10 * min {
+            writeln!(script, "set logscale x 2")?;
+        }
+        writeln!(script, "f = {}", f)?;
+        writeln!(script, "amdahl(n) = 1 / (f + (1 - f) / n)")?;
+        writeln!(script, "plot \"{0}\" using 1:2 axes x1y1 with linespoints title \"speedup\", \\", data_name)?;
+        writeln!(script, "     \"{0}\" using 1:3 axes x1y2 with linespoints title \"efficiency\", \\", data_name)?;
+        writeln!(script, "     [{}:{}] amdahl(x) axes x1y1 dashtype 2 title sprintf(\"Amdahl speedup (f = %.3f)\", f), \\", min, max)?;
+        writeln!(script, "     [{}:{}] amdahl(x) / x axes x1y2 dashtype 2 title \"Amdahl efficiency\"", min, max)?;
+        Ok(())
+    }
+}
+
+/// Simulates the problem at `problem_path` on a dense network of every size in `node_counts` and compares
+/// each run against a single node. Everything but the node count and topology comes from `base_config`.
+pub fn speedup_analysis(problem_path: &Path, base_config: TestConfig, node_counts: &[usize], mode: ScalingMode) -> Result<SpeedupData, RunError> {
+    let (table, _) = ClauseTable::load_file(problem_path.to_path_buf())?;
+    let simulate = |table: ClauseTable, num_nodes: usize| {
+        let config = TestConfig { num_nodes, topology: Topology::Dense(num_nodes), start_nodes: 1, ..base_config.clone() };
+        SatSwarm::generate(table, &config)?.test_satisfiability()
+    };
+    let baseline = simulate(table.clone(), 1)?.simulated_cycles;
+    let mut data = SpeedupData { mode, counts: Vec::new(), cycles: Vec::new(), speedups: Vec::new(), efficiencies: Vec::new() };
+    for &n in node_counts {
+        let (result, sequential_cycles) = match mode {
+            ScalingMode::Strong => (simulate(table.clone(), n)?, baseline),
+            ScalingMode::Weak => (simulate(disjoint_copies(&table, n), n)?, n as u64 * baseline),  // n problems in a row on one node
+        };
+        data.counts.push(n);
+        data.cycles.push(result.simulated_cycles);
+        data.speedups.push(result.speedup(sequential_cycles));
+        data.efficiencies.push(result.efficiency(sequential_cycles, n));
+    }
+    Ok(data)
+}
+
+/// Simulates the file at `path` on each of `node_counts` nodes, keeping the topology family of `config`, and returns
+/// (nodes, cycles on one node / cycles on that many). Speedups above the node count are reported on stderr since
+/// they usually mean the parallel run got lucky with the search order rather than that the network scales.
+/// A node count the topology family has no network of (any count for an edge list) is an error.
+pub fn measure_speedup(path: &Path, config: &TestConfig, node_counts: &[usize]) -> Result<Vec<(usize, f64)>, RunError> {
+    let single = TestConfig { num_nodes: 1, topology: Topology::Dense(1), start_nodes: 1, checkpoint_interval: None, variable_partition: None, ..config.clone() };
+    let baseline = run_single(path, &single)?.simulated_cycles;
+    let mut speedups = Vec::new();
+    for &n in node_counts {
+        let cycles = if n == 1 {
+            baseline
+        } else {
+            let topology = config.topology.with_nodes(n).ok_or_else(|| RunError::Resize { topology: config.topology.clone(), num_nodes: n })?;
+            run_single(path, &TestConfig { num_nodes: n, topology, ..single.clone() })?.simulated_cycles
+        };
+        let speedup = baseline as f64 / cycles as f64;
+        if speedup > n as f64 {
+            eprintln!("Super-linear speedup of {:.2} on {} nodes for {:?}", speedup, n, path);
+        }
+        speedups.push((n, speedup));
+    }
+    Ok(speedups)
+}
+
+/// `copies` independent copies of the table, each on its own range of variables
+fn disjoint_copies(table: &ClauseTable, copies: usize) -> ClauseTable {
+    let vars = table.clause_table.iter().flat_map(|clause| clause.iter().map(|(term, _)| term.var as usize)).max().unwrap_or(0);
+    assert!(vars * copies < u8::MAX as usize, "{} copies of a {} variable problem do not fit in u8 variables", copies, vars);
+    let mut clauses = Vec::new();
+    if table.has_empty_clause {
+        clauses.push(Vec::new());
+    }
+    for copy in 0..copies {
+        let offset = (copy * vars) as i32;
+        for clause in table.clause_table.iter() {
+            let literals: Vec<i32> = clause.iter()
+                .filter(|(term, _)| term.var != 0)  // drop the var 0 padding, from_clauses adds its own
+                .map(|(term, _)| if term.negated { -(term.var as i32 + offset) } else { term.var as i32 + offset })
+                .collect();
+            if !literals.is_empty() {
+                clauses.push(literals);
+            }
+        }
+    }
+    ClauseTable::from_clauses(vars * copies, &clauses)
+}
+
+/// How a test changed between two logs written by `run_workload`
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunChange {
+    ResultFlipped { old: bool, new: bool },
+    CyclesChanged { old: u64, new: u64 },   // by more than the tolerance
+    OnlyInOld,
+    OnlyInNew,
+}
+
+pub struct RunComparison {
+    pub changes: Vec<(String, RunChange)>,   // by test path
+    pub unchanged: usize,
+} impl RunComparison {
+    pub fn print_summary(&self) {
+        for (test, change) in self.changes.iter() {
+            match change {
+                RunChange::ResultFlipped { old, new } => println!("{}: result {} -> {}", test, old, new),
+                RunChange::CyclesChanged { old, new } => println!("{}: cycles {} -> {} ({:+.1}%)", test, old, new, percent_change(*old, *new)),
+                RunChange::OnlyInOld => println!("{}: only in the old log", test),
+                RunChange::OnlyInNew => println!("{}: only in the new log", test),
+            }
+        }
+        let count = |f: fn(&RunChange) -> bool| self.changes.iter().filter(|(_, change)| f(change)).count();
+        println!("{} unchanged, {} flipped results, {} cycle changes, {} only in old, {} only in new",
+            self.unchanged,
+            count(|c| matches!(c, RunChange::ResultFlipped { .. })),
+            count(|c| matches!(c, RunChange::CyclesChanged { .. })),
+            count(|c| matches!(c, RunChange::OnlyInOld)),
+            count(|c| matches!(c, RunChange::OnlyInNew)));
+    }
+}
+
+fn percent_change(old: u64, new: u64) -> f64 {
+    (new as f64 - old as f64) / (old.max(1) as f64) * 100.0
+}
+
+/// Joins two logs by test path and reports the tests whose result flipped or whose simulated cycles moved by
+/// more than `tolerance_percent`, plus the tests that only one of them has
+pub fn compare_runs(old_csv: &Path, new_csv: &Path, tolerance_percent: f64) -> csv::Result<RunComparison> {
+    let old = read_results(old_csv)?;
+    let mut new = read_results(new_csv)?;
+    let mut comparison = RunComparison { changes: Vec::new(), unchanged: 0 };
+    for (test, (old_result, old_cycles)) in old {
+        match new.remove(&test) {
+            None => comparison.changes.push((test, RunChange::OnlyInOld)),
+            Some((new_result, _)) if new_result != old_result => comparison.changes.push((test, RunChange::ResultFlipped { old: old_result, new: new_result })),
+            Some((_, new_cycles)) if percent_change(old_cycles, new_cycles).abs() > tolerance_percent => {
+                comparison.changes.push((test, RunChange::CyclesChanged { old: old_cycles, new: new_cycles }));
+            },
+            Some(_) => comparison.unchanged += 1,
+        }
+    }
+    comparison.changes.extend(new.into_keys().map(|test| (test, RunChange::OnlyInNew)));
+    Ok(comparison)
+}
+
+/// Simulated result and cycles of every test in a log, found by column name so older logs with fewer columns work
+fn read_results(path: &Path) -> csv::Result<std::collections::BTreeMap<String, (bool, u64)>> {
+    let invalid = |message: String| csv::Error::from(std::io::Error::new(std::io::ErrorKind::InvalidData, message));
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|header| header == name).ok_or_else(|| invalid(format!("{:?} has no {} column", path, name)));
+    let (test_column, result_column, cycles_column) = (column("Test Path")?, column("Simulated Result")?, column("Simulated Cycles")?);
+    let mut results = std::collections::BTreeMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |i: usize| record.get(i).unwrap_or_default();
+        let result = field(result_column).parse().map_err(|_| invalid(format!("bad result {:?} in {:?}", field(result_column), path)))?;
+        let cycles = field(cycles_column).parse().map_err(|_| invalid(format!("bad cycle count {:?} in {:?}", field(cycles_column), path)))?;
+        results.insert(field(test_column).to_string(), (result, cycles));
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::minisat::minisat_table;
+
+    /// Strong scaling data with the given speedup at each node count
+    fn speedup_data(counts: &[usize], speedup: impl Fn(usize) -> f64) -> SpeedupData {
+        let speedups: Vec<f64> = counts.iter().map(|&n| speedup(n)).collect();
+        SpeedupData {
+            mode: ScalingMode::Strong,
+            counts: counts.to_vec(),
+            cycles: speedups.iter().map(|s| (1000.0 / s) as u64).collect(),
+            efficiencies: counts.iter().zip(&speedups).map(|(&n, s)| s / n as f64).collect(),
+            speedups,
+        }
+    }
+
+    #[test]
+    fn fit_amdahl_of_flat_speedup_is_fully_serial() {
+        assert_eq!(speedup_data(&[1, 2, 4, 8, 16], |_| 1.0).fit_amdahl(), 1.0);
+    }
+
+    #[test]
+    fn fit_amdahl_of_linear_speedup_is_fully_parallel() {
+        assert_eq!(speedup_data(&[1, 2, 4, 8, 16], |n| n as f64).fit_amdahl(), 0.0);
+    }
+
+    #[test]
+    fn fit_amdahl_recovers_the_serial_fraction() {
+        let f = speedup_data(&[1, 2, 4, 8, 16, 64], |n| 1.0 / (0.25 + 0.75 / n as f64)).fit_amdahl();
+        assert!((f - 0.25).abs() < 1e-12, "fitted {}", f);
+    }
+
+    #[test]
+    fn strong_scaling_speeds_up_with_falling_efficiency() {
+        // an UNSAT instance, so every node count walks the whole tree and no run gets lucky finding a model early
+        let path = Path::new("tests/satlib/unsat/uuf50-01.cnf");
+        let data = speedup_analysis(path, crate::tests::test_config(), &[1, 2, 4, 8], ScalingMode::Strong).unwrap();
+        assert_eq!(data.counts, vec![1, 2, 4, 8]);
+        assert_eq!(data.speedups[0], 1.0);
+        assert!(data.speedups[2] >= 2.0, "4 nodes only sped up {}x", data.speedups[2]);
+        assert!(data.efficiencies.windows(2).all(|pair| pair[1] < pair[0]), "efficiency did not fall: {:?}", data.efficiencies);
+    }
+
+    #[test]
+    fn weak_scaling_copies_keep_satisfiability() {
+        for (path, expected) in [("tests/satlib/sat/uf20-01.cnf", true), ("tests/satlib/unsat/uuf50-01.cnf", false)] {
+            let (table, _) = ClauseTable::load_file(path.into()).unwrap();
+            for copies in [1, 2, 4] {
+                let copied = disjoint_copies(&table, copies);
+                assert_eq!((copied.num_clauses, copied.number_of_vars()), (copies * table.num_clauses, copies * table.number_of_vars()));
+                assert_eq!(minisat_table(&copied).0, expected, "{} copies of {}", copies, path);
+            }
+        }
+        let data = speedup_analysis(Path::new("tests/satlib/sat/uf20-01.cnf"), crate::tests::test_config(), &[1, 2], ScalingMode::Weak).unwrap();
+        assert_eq!((data.mode, data.counts.clone()), (ScalingMode::Weak, vec![1, 2]));
+        assert_eq!(data.speedups[0], 1.0);
+        assert!(data.cycles.iter().all(|&cycles| cycles > 0));
+    }
+
+    #[test]
+    fn measure_speedup_compares_each_network_against_one_node() {
+        let path = Path::new("tests/satlib/unsat/uuf50-01.cnf");
+        let config = TestConfig { topology: Topology::Grid(2, 2), ..crate::tests::test_config() };
+        let speedups = measure_speedup(path, &config, &[1, 2, 4]).unwrap();
+        let single = TestConfig { num_nodes: 1, topology: Topology::Dense(1), ..config.clone() };
+        let one = run_single(path, &single).unwrap().simulated_cycles as f64;
+        let four = run_single(path, &TestConfig { num_nodes: 4, topology: Topology::Grid(2, 2), ..config.clone() }).unwrap().simulated_cycles as f64;
+        assert_eq!(speedups[0], (1, 1.0));
+        assert_eq!(speedups[1].0, 2);
+        assert_eq!(speedups[2], (4, one / four));
+        assert!(speedups[2].1 > 1.0, "4 nodes were no faster than 1");
+    }
+
+    #[test]
+    fn measure_speedup_rejects_sizes_the_topology_cannot_take() {
+        let path = Path::new("tests/satlib/sat/uf20-01.cnf");
+        let butterfly = TestConfig { topology: Topology::Butterfly(2), ..crate::tests::test_config() };
+        assert!(matches!(measure_speedup(path, &butterfly, &[1, 3]), Err(RunError::Resize { num_nodes: 3, .. })));
+        let custom = TestConfig { topology: Topology::Custom("edges.txt".into()), ..crate::tests::test_config() };
+        assert!(matches!(measure_speedup(path, &custom, &[1, 2]), Err(RunError::Resize { topology: Topology::Custom(_), num_nodes: 2 })));
+        assert_eq!(measure_speedup(path, &custom, &[1]).unwrap(), vec![(1, 1.0)], "a single node needs no resizing");
+    }
+
+    /// `contents` in a temporary csv file
+    fn temp_csv(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("sat_swarm_{}_{}.csv", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn compare_runs_reports_what_moved() {
+        // the columns are found by name, so the logs don't need the same layout
+        let old = temp_csv("old", "Test Path,Topology,Simulated Result,Simulated Cycles\n\
+            a.cnf,grid:2x2,true,100\nb.cnf,grid:2x2,true,100\nc.cnf,grid:2x2,false,100\nd.cnf,grid:2x2,false,100\ne.cnf,grid:2x2,true,100\n");
+        let new = temp_csv("new", "Simulated Cycles,Simulated Result,Test Path\n\
+            100,true,a.cnf\n100,false,b.cnf\n105,false,c.cnf\n120,false,d.cnf\n100,true,f.cnf\n");
+        let comparison = compare_runs(&old, &new, 10.0).unwrap();
+        assert_eq!(comparison.changes, vec![
+            (String::from("b.cnf"), RunChange::ResultFlipped { old: true, new: false }),
+            (String::from("d.cnf"), RunChange::CyclesChanged { old: 100, new: 120 }),
+            (String::from("e.cnf"), RunChange::OnlyInOld),
+            (String::from("f.cnf"), RunChange::OnlyInNew),
+        ]);
+        assert_eq!(comparison.unchanged, 2, "a.cnf and c.cnf, which moved less than the tolerance");
+        assert_eq!(compare_runs(&old, &new, 25.0).unwrap().unchanged, 3);
+        std::fs::remove_file(old).unwrap();
+        std::fs::remove_file(new).unwrap();
+    }
+
+    #[test]
+    fn read_results_rejects_logs_it_cannot_read() {
+        let no_cycles = temp_csv("no_cycles", "Test Path,Simulated Result\na.cnf,true\n");
+        assert!(read_results(&no_cycles).unwrap_err().to_string().contains("no Simulated Cycles column"));
+        let bad_cycles = temp_csv("bad_cycles", "Test Path,Simulated Result,Simulated Cycles\na.cnf,true,many\n");
+        assert!(read_results(&bad_cycles).unwrap_err().to_string().contains("bad cycle count"));
+        std::fs::remove_file(no_cycles).unwrap();
+        std::fs::remove_file(bad_cycles).unwrap();
+    }
+}
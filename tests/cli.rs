@@ -0,0 +1,50 @@
+//! Runs the simulator binary the way a script would
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Empty directory of its own in the temp directory, `name` has to be unique among the tests
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sat_swarm_cli_{}_{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn no_progress_output_is_clean() {
+    for flag in ["--no_progress", "--no-progress"] {
+        let dir = temp_dir(flag.trim_start_matches('-'));
+        let instances = dir.join("instances");
+        std::fs::create_dir(&instances).unwrap();
+        for name in ["uf20-01.cnf", "uf20-010.cnf"] {
+            std::fs::copy(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/satlib/sat").join(name), instances.join(name)).unwrap();
+        }
+        let output = Command::new(env!("CARGO_BIN_EXE_sat_swarm"))
+            .args([flag, "--num_nodes", "4", "--topology", "grid", "--num_vars", "20", "--test_path"])
+            .arg(&instances)
+            .current_dir(&dir)  // the logs go to dir/logs
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}: {}", flag, String::from_utf8_lossy(&output.stderr));
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        for text in [&stdout, &stderr] {
+            assert!(!text.contains(['\r', '\x1b']), "progress bar drawn with {} in {:?}", flag, text);
+        }
+        assert_eq!(stdout.lines().filter(|line| line.starts_with("Running test:")).count(), 2);
+        assert_eq!(std::fs::read_dir(dir.join("logs")).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn invalid_topology_exits_with_a_message() {
+    let output = Command::new(env!("CARGO_BIN_EXE_sat_swarm"))
+        .args(["--topology", "hypercube", "--num_nodes", "4"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Invalid value for --topology: hypercube"), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}